@@ -1,10 +1,18 @@
 //! First fit free list memory allocator.
 
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
 use core::alloc::{AllocError, Allocator, GlobalAlloc, Layout};
+#[cfg(not(test))]
+use core::arch::asm;
 use core::cmp::{max, min};
-use core::ops::Range;
+use core::mem::{align_of, size_of, transmute, MaybeUninit};
+use core::ops::{Deref, DerefMut, Range};
 use core::ptr::{null_mut, NonNull};
 use core::slice::from_raw_parts as slice_from_raw_parts;
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
 
 use crate::sync::Lock;
 #[cfg(not(test))]
@@ -13,17 +21,228 @@ use crate::{DMA_RANGE, HEAP_RANGE};
 /// Heap allocator instance.
 #[cfg(not(test))]
 #[global_allocator]
-pub static HEAP: Shell = Shell::new(&HEAP_CORE);
-/// DMA allocator instance.
+pub static HEAP: Shell = Shell::new(&HEAP_CORE, &HEAP_COUNTERS);
+/// Default DMA allocator instance.
 #[cfg(not(test))]
-pub static DMA: Shell = Shell::new(&DMA_CORE);
+pub static DMA: Shell = DMA_POOL.allocator();
 
 /// Heap allocator core.
 #[cfg(not(test))]
 static HEAP_CORE: Lock<Core> = unsafe { Core::new(HEAP_RANGE) };
-/// DMA allocator core.
+/// Default DMA pool, covering the whole DMA region.
+#[cfg(not(test))]
+static DMA_POOL: DmaPool = unsafe { DmaPool::new(DMA_RANGE.start, DMA_RANGE.end - DMA_RANGE.start) };
+/// Heap allocator statistics.
+#[cfg(not(test))]
+static HEAP_COUNTERS: Counters = Counters::new();
+/// Address of the out of memory handler, or zero for the default.
+static OOM_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+/// Registers a handler to be called when a heap allocation or an allocation
+/// that can't fail runs out of memory, replacing the default handler which
+/// just panics.
+///
+/// The handler is called with the allocator's lock released, so it can
+/// allocate memory from the other allocator or take statistics.
+///
+/// * `handler`: Handler function, called with the layout that couldn't be
+///   satisfied.
+pub fn set_oom_handler(handler: fn(Layout) -> !)
+{
+    OOM_HANDLER.store(handler as usize, Ordering::Release);
+}
+
+/// Allocates uninitialized DMA memory for a value of type `T` aligned to the
+/// specified boundary.
+///
+/// * `align`: Required alignment, which must be a power of two.
+///
+/// Returns the allocated memory.
+///
+/// Panics if `align` is not a power of two.
+#[cfg(not(test))]
+pub fn alloc_aligned<T>(align: usize) -> AlignedBox<'static, T>
+{
+    DMA.alloc_aligned(align)
+}
+
+/// Allocates zero-initialized DMA memory for a value of type `T`.
+///
+/// Returns the allocated value.
+///
+/// The caller must ensure that zero is a valid bit pattern for `T`.
+#[cfg(not(test))]
+pub unsafe fn dma_zeroed<T>() -> Box<T, Shell<'static>>
+{
+    DMA.alloc_zeroed_box::<T>().assume_init()
+}
+
+/// Grows a boxed slice, moving it only if it can't be grown in place.
+///
+/// * `old`: Slice to grow.
+/// * `new_len`: New number of elements, with the new elements set to their
+///   default value.
+///
+/// Returns the grown slice.
+///
+/// Panics if `new_len` is smaller than the current length.
+pub fn grow<'a, T: Copy + Default>(old: Box<[T], Shell<'a>>, new_len: usize) -> Box<[T], Shell<'a>>
+{
+    let old_len = old.len();
+    assert!(new_len >= old_len,
+            "Attempted to grow a slice of {old_len} elements to {new_len}");
+    let old_layout = Layout::array::<T>(old_len).unwrap();
+    let new_layout = Layout::array::<T>(new_len).expect("Slice too large");
+    let (base, alloc) = Box::into_raw_with_allocator(old);
+    let base = unsafe { NonNull::new_unchecked(base.cast::<u8>()) };
+    // Empty slices don't own any memory.
+    let new = if old_layout.size() == 0 {
+        alloc.allocate(new_layout)
+    } else {
+        unsafe { alloc.grow(base, old_layout, new_layout) }
+    };
+    let base = new.unwrap_or_else(|_| out_of_memory(new_layout))
+                  .as_mut_ptr()
+                  .cast::<T>();
+    (old_len .. new_len).for_each(|idx| unsafe { base.add(idx).write(T::default()) });
+    // Make sure that the contents reach memory before the buffer can be handed to a
+    // peripheral.
+    fence(Ordering::Release);
+    let base = NonNull::slice_from_raw_parts(unsafe { NonNull::new_unchecked(base) }, new_len);
+    unsafe { Box::from_raw_in(base.as_ptr(), alloc) }
+}
+
+/// Shrinks a boxed slice, releasing the memory past its new end.
+///
+/// * `old`: Slice to shrink.
+/// * `new_len`: New number of elements, with the elements past it discarded.
+///
+/// Returns the shrunk slice.
+///
+/// Panics if `new_len` is larger than the current length.
+pub fn shrink<'a, T: Copy>(old: Box<[T], Shell<'a>>, new_len: usize) -> Box<[T], Shell<'a>>
+{
+    let old_len = old.len();
+    assert!(new_len <= old_len,
+            "Attempted to shrink a slice of {old_len} elements to {new_len}");
+    let old_layout = Layout::array::<T>(old_len).unwrap();
+    let new_layout = Layout::array::<T>(new_len).unwrap();
+    let (base, alloc) = Box::into_raw_with_allocator(old);
+    let base = unsafe { NonNull::new_unchecked(base.cast::<u8>()) };
+    let base = if new_layout.size() == 0 {
+        // Empty slices don't own any memory.
+        if old_layout.size() != 0 {
+            unsafe { alloc.deallocate(base, old_layout) };
+        }
+        NonNull::<T>::dangling()
+    } else {
+        let new = unsafe { alloc.shrink(base, old_layout, new_layout) };
+        new.unwrap_or_else(|_| out_of_memory(new_layout))
+           .as_non_null_ptr()
+           .cast::<T>()
+    };
+    fence(Ordering::Release);
+    let base = NonNull::slice_from_raw_parts(base, new_len);
+    unsafe { Box::from_raw_in(base.as_ptr(), alloc) }
+}
+
+/// Writes any cached modifications to a buffer back to memory so that
+/// peripherals can see them.
+///
+/// Every cache line touched by the buffer is cleaned, so the buffer doesn't
+/// need to be aligned.
+///
+/// * `base`: Base address of the buffer.
+/// * `len`: Length of the buffer in bytes.
+#[cfg(not(test))]
+pub fn dma_clean(base: *const u8, len: usize)
+{
+    cache_lines(base as usize, len, cache_line_size()).for_each(|line| unsafe {
+        asm!("dc cvac, {line}", line = in (reg) line, options(nostack, preserves_flags))
+    });
+    unsafe { asm!("dsb sy", options(nostack, preserves_flags)) };
+}
+
+/// Discards any cached copies of a buffer so that modifications made to it by
+/// peripherals become visible.
+///
+/// Any modifications made by the cores that haven't been written back are
+/// lost, including to data sharing the first and last cache lines with the
+/// buffer, so the buffer must be aligned to and padded to the cache line size
+/// of 64 bytes.
+///
+/// * `base`: Base address of the buffer.
+/// * `len`: Length of the buffer in bytes.
+#[cfg(not(test))]
+pub unsafe fn dma_invalidate(base: *const u8, len: usize)
+{
+    cache_lines(base as usize, len, cache_line_size()).for_each(|line| {
+        asm!("dc ivac, {line}", line = in (reg) line, options(nostack, preserves_flags))
+    });
+    asm!("dsb sy", options(nostack, preserves_flags));
+}
+
+/// Writes any cached modifications to a buffer back to memory and discards
+/// the cached copies, so that peripherals see each other's modifications.
+///
+/// Unlike [`dma_invalidate`], this never loses modifications made by the
+/// cores, so the buffer doesn't need to be aligned, but the peripherals must
+/// not write to parts of the cache lines with pending modifications.
+///
+/// * `base`: Base address of the buffer.
+/// * `len`: Length of the buffer in bytes.
+#[cfg(not(test))]
+pub fn dma_clean_invalidate(base: *const u8, len: usize)
+{
+    cache_lines(base as usize, len, cache_line_size()).for_each(|line| unsafe {
+        asm!("dc civac, {line}", line = in (reg) line, options(nostack, preserves_flags))
+    });
+    unsafe { asm!("dsb sy", options(nostack, preserves_flags)) };
+}
+
+/// Reads the size of the smallest data cache line.
+///
+/// Returns the size in bytes.
 #[cfg(not(test))]
-static DMA_CORE: Lock<Core> = unsafe { Core::new(DMA_RANGE) };
+fn cache_line_size() -> usize
+{
+    let ctr: usize;
+    unsafe { asm!("mrs {ctr}, ctr_el0", ctr = out (reg) ctr, options(nomem, nostack, preserves_flags)) };
+    // The size is encoded as the base 2 logarithm of the number of words.
+    4 << (ctr >> 16 & 0xF)
+}
+
+/// Computes the addresses of all the cache lines touched by a buffer.
+///
+/// * `base`: Base address of the buffer.
+/// * `len`: Length of the buffer in bytes.
+/// * `line`: Cache line size, which must be a power of two.
+///
+/// Returns an iterator over the base address of every cache line.
+fn cache_lines(base: usize, len: usize, line: usize) -> impl Iterator<Item = usize>
+{
+    if len == 0 {
+        return (0 .. 0).step_by(line);
+    }
+    let start = base & !(line - 1);
+    let end = (base + len + line - 1) & !(line - 1);
+    (start .. end).step_by(line)
+}
+
+/// Calls the registered out of memory handler.
+///
+/// * `layout`: Layout that couldn't be satisfied.
+fn out_of_memory(layout: Layout) -> !
+{
+    let handler = OOM_HANDLER.load(Ordering::Acquire);
+    if handler != 0 {
+        let handler = unsafe { transmute::<usize, fn(Layout) -> !>(handler) };
+        handler(layout);
+    }
+    panic!("Out of memory allocating {} bytes aligned to {}",
+           layout.size(),
+           layout.align());
+}
 
 /// Free list allocator front-end.
 #[derive(Clone, Copy, Debug)]
@@ -31,6 +250,70 @@ pub struct Shell<'a>
 {
     /// Shared core of all copies of this allocator.
     core: &'a Lock<Core>,
+    /// Shared statistics of all copies of this allocator.
+    counters: &'a Counters,
+}
+
+/// Memory pool with its own allocator and statistics, which keeps
+/// allocations with different lifetimes from fragmenting each other.
+#[derive(Debug)]
+pub struct DmaPool
+{
+    /// Allocator core managing the pool.
+    core: Lock<Core>,
+    /// Statistics of the pool.
+    counters: Counters,
+}
+
+/// Growable buffer shared with peripherals, whose contents must be explicitly
+/// synchronized with memory around every transfer.
+#[derive(Debug)]
+pub struct DmaVec<'a, T>
+{
+    /// Backing storage.
+    vec: Vec<T, Shell<'a>>,
+}
+
+/// Owned allocation aligned to a stronger boundary than its type requires,
+/// which remembers the layout it was allocated with so that it can be
+/// deallocated with the same layout.
+#[derive(Debug)]
+pub struct AlignedBox<'a, T>
+{
+    /// Base of the allocation.
+    base: NonNull<MaybeUninit<T>>,
+    /// Layout the allocation was made with.
+    layout: Layout,
+    /// Allocator that owns the allocation.
+    alloc: Shell<'a>,
+}
+
+/// Snapshot of the statistics of an allocator.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllocStats
+{
+    /// Bytes currently allocated.
+    pub used: usize,
+    /// Highest number of bytes allocated at any one time.
+    pub peak: usize,
+    /// Total number of allocations.
+    pub allocs: usize,
+    /// Total number of deallocations.
+    pub frees: usize,
+}
+
+/// Shared allocator statistics.
+#[derive(Debug)]
+struct Counters
+{
+    /// Bytes currently allocated.
+    used: AtomicUsize,
+    /// Highest number of bytes allocated at any one time.
+    peak: AtomicUsize,
+    /// Total number of allocations.
+    allocs: AtomicUsize,
+    /// Total number of deallocations.
+    frees: AtomicUsize,
 }
 
 /// Shared allocator core.
@@ -58,11 +341,184 @@ impl<'a> Shell<'a>
     /// Creates and initializes a new allocator shell.
     ///
     /// * `core`: Shared core of all instances of this allocator.
+    /// * `counters`: Shared statistics of all instances of this allocator.
     ///
     /// Returns the created allocator shell.
-    const fn new(core: &'a Lock<Core>) -> Self
+    const fn new(core: &'a Lock<Core>, counters: &'a Counters) -> Self
+    {
+        Self { core, counters }
+    }
+
+    /// Takes a snapshot of the statistics of this allocator.
+    ///
+    /// Returns the statistics.
+    pub fn stats(&self) -> AllocStats
+    {
+        AllocStats { used: self.counters.used.load(Ordering::Relaxed),
+                     peak: self.counters.peak.load(Ordering::Relaxed),
+                     allocs: self.counters.allocs.load(Ordering::Relaxed),
+                     frees: self.counters.frees.load(Ordering::Relaxed) }
+    }
+
+    /// Allocates uninitialized memory for a value of type `T` aligned to the
+    /// specified boundary, or to the alignment of `T` if stronger.
+    ///
+    /// * `align`: Required alignment, which must be a power of two.
+    ///
+    /// Returns the allocated memory.
+    ///
+    /// Panics if `align` is not a power of two.
+    pub fn alloc_aligned<T>(self, align: usize) -> AlignedBox<'a, T>
+    {
+        let layout =
+            Layout::from_size_align(size_of::<T>(), max(align, align_of::<T>())).expect("Invalid alignment requested");
+        let base = self.allocate(layout).unwrap_or_else(|_| out_of_memory(layout));
+        AlignedBox { base: base.as_non_null_ptr().cast::<MaybeUninit<T>>(),
+                     layout,
+                     alloc: self }
+    }
+
+    /// Allocates zero-initialized memory for a value of type `T`.
+    ///
+    /// Returns the allocated memory.
+    pub fn alloc_zeroed_box<T>(self) -> Box<MaybeUninit<T>, Self>
     {
-        Self { core }
+        let layout = Layout::new::<T>();
+        let base = self.allocate_zeroed(layout).unwrap_or_else(|_| out_of_memory(layout));
+        unsafe { Box::from_raw_in(base.as_mut_ptr().cast::<MaybeUninit<T>>(), self) }
+    }
+}
+
+impl DmaPool
+{
+    /// Creates and initializes a new pool.
+    ///
+    /// * `base`: Base address of the memory region, which must be aligned to 16
+    ///   bytes.
+    /// * `size`: Size of the memory region.
+    ///
+    /// Returns the newly created pool.
+    ///
+    /// The caller must ensure that the memory region is valid, can be accessed
+    /// by the peripherals that will use it, and is not used by anything else,
+    /// including other pools.
+    pub const unsafe fn new(base: usize, size: usize) -> Self
+    {
+        Self { core: Core::new(base .. base + size),
+               counters: Counters::new() }
+    }
+
+    /// Returns an allocator that allocates from this pool.
+    pub const fn allocator(&self) -> Shell
+    {
+        Shell::new(&self.core, &self.counters)
+    }
+
+    /// Takes a snapshot of the statistics of this pool.
+    ///
+    /// Returns the statistics.
+    pub fn stats(&self) -> AllocStats
+    {
+        self.allocator().stats()
+    }
+}
+
+impl<'a, T> DmaVec<'a, T>
+{
+    /// Creates and initializes a new empty buffer.
+    ///
+    /// * `alloc`: Allocator to allocate the backing storage from.
+    ///
+    /// Returns the newly created buffer.
+    pub fn new_in(alloc: Shell<'a>) -> Self
+    {
+        Self { vec: Vec::new_in(alloc) }
+    }
+
+    /// Creates and initializes a new empty buffer with room for the specified
+    /// number of elements.
+    ///
+    /// * `capacity`: Number of elements to allocate room for.
+    /// * `alloc`: Allocator to allocate the backing storage from.
+    ///
+    /// Returns the newly created buffer.
+    pub fn with_capacity_in(capacity: usize, alloc: Shell<'a>) -> Self
+    {
+        Self { vec: Vec::with_capacity_in(capacity, alloc) }
+    }
+
+    /// Returns the range of addresses occupied by the elements, which is what
+    /// the synchronization methods cover.
+    pub fn byte_range(&self) -> Range<usize>
+    {
+        let base = self.vec.as_ptr() as usize;
+        base .. base + self.vec.len() * size_of::<T>()
+    }
+
+    /// Writes any cached modifications to the elements back to memory, which
+    /// must be done after modifying them and before a peripheral reads them.
+    #[cfg(not(test))]
+    pub fn sync_to_device(&self)
+    {
+        let range = self.byte_range();
+        dma_clean(range.start as *const u8, range.len());
+    }
+
+    /// Discards any cached copies of the elements, which must be done after a
+    /// peripheral writes to them and before reading them.
+    ///
+    /// Modifications made by the cores are written back first, so nothing is
+    /// lost regardless of alignment, but the peripheral must not write to the
+    /// buffer while it has modifications that haven't been synchronized.
+    #[cfg(not(test))]
+    pub fn sync_from_device(&mut self)
+    {
+        let range = self.byte_range();
+        dma_clean_invalidate(range.start as *const u8, range.len());
+    }
+}
+
+impl<'a, T> Deref for DmaVec<'a, T>
+{
+    type Target = Vec<T, Shell<'a>>;
+
+    fn deref(&self) -> &Self::Target
+    {
+        &self.vec
+    }
+}
+
+impl<'a, T> DerefMut for DmaVec<'a, T>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target
+    {
+        &mut self.vec
+    }
+}
+
+impl<'a, T> Deref for AlignedBox<'a, T>
+{
+    type Target = MaybeUninit<T>;
+
+    fn deref(&self) -> &Self::Target
+    {
+        unsafe { self.base.as_ref() }
+    }
+}
+
+impl<'a, T> DerefMut for AlignedBox<'a, T>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target
+    {
+        unsafe { self.base.as_mut() }
+    }
+}
+
+impl<'a, T> Drop for AlignedBox<'a, T>
+{
+    fn drop(&mut self)
+    {
+        unsafe { self.alloc.deallocate(self.base.cast::<u8>(), self.layout) };
     }
 }
 
@@ -70,33 +526,34 @@ unsafe impl<'a> GlobalAlloc for Shell<'a>
 {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8
     {
-        self.core
-            .lock()
-            .allocate(layout)
+        self.allocate(layout)
+            .map(|base| base.as_mut_ptr().cast::<u8>())
+            .unwrap_or_else(|_| out_of_memory(layout))
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8
+    {
+        self.allocate_zeroed(layout)
             .map(|base| base.as_mut_ptr().cast::<u8>())
-            .unwrap_or(null_mut())
+            .unwrap_or_else(|_| out_of_memory(layout))
     }
 
     unsafe fn dealloc(&self, base: *mut u8, layout: Layout)
     {
-        self.core.lock().deallocate(NonNull::new_unchecked(base), layout);
+        self.deallocate(NonNull::new_unchecked(base), layout);
     }
 
     unsafe fn realloc(&self, base: *mut u8, layout: Layout, new_size: usize) -> *mut u8
     {
         let new_layout = Layout::from_size_align(new_size, layout.align()).unwrap();
         if new_size >= layout.size() {
-            return self.core
-                       .lock()
-                       .grow(NonNull::new_unchecked(base), layout, new_layout)
+            return self.grow(NonNull::new_unchecked(base), layout, new_layout)
                        .map(|ptr| ptr.as_mut_ptr().cast::<u8>())
-                       .unwrap_or(null_mut());
+                       .unwrap_or_else(|_| out_of_memory(new_layout));
         }
-        self.core
-            .lock()
-            .shrink(NonNull::new_unchecked(base), layout, new_layout)
+        self.shrink(NonNull::new_unchecked(base), layout, new_layout)
             .map(|ptr| ptr.as_mut_ptr().cast::<u8>())
-            .unwrap_or(null_mut())
+            .unwrap_or_else(|_| out_of_memory(new_layout))
     }
 }
 
@@ -104,24 +561,68 @@ unsafe impl<'a> Allocator for Shell<'a>
 {
     fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>
     {
-        self.core.lock().allocate(layout)
+        let res = self.core.lock().allocate(layout)?;
+        self.counters.allocs.fetch_add(1, Ordering::Relaxed);
+        self.counters.add(layout.size());
+        Ok(res)
+    }
+
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError>
+    {
+        let res = self.allocate(layout)?;
+        unsafe { res.as_mut_ptr().write_bytes(0, res.len()) };
+        // Make sure that the zeroes reach memory before the buffer can be handed to a
+        // peripheral.
+        fence(Ordering::Release);
+        Ok(res)
     }
 
     unsafe fn deallocate(&self, base: NonNull<u8>, layout: Layout)
     {
-        self.core.lock().deallocate(base, layout)
+        self.core.lock().deallocate(base, layout);
+        self.counters.frees.fetch_add(1, Ordering::Relaxed);
+        self.counters.used.fetch_sub(layout.size(), Ordering::Relaxed);
     }
 
     unsafe fn grow(&self, base: NonNull<u8>, old_layout: Layout, new_layout: Layout)
                    -> Result<NonNull<[u8]>, AllocError>
     {
-        self.core.lock().grow(base, old_layout, new_layout)
+        let res = self.core.lock().grow(base, old_layout, new_layout)?;
+        self.counters.add(new_layout.size() - old_layout.size());
+        Ok(res)
     }
 
     unsafe fn shrink(&self, base: NonNull<u8>, old_layout: Layout, new_layout: Layout)
                      -> Result<NonNull<[u8]>, AllocError>
     {
-        self.core.lock().shrink(base, old_layout, new_layout)
+        let res = self.core.lock().shrink(base, old_layout, new_layout)?;
+        self.counters
+            .used
+            .fetch_sub(old_layout.size() - new_layout.size(), Ordering::Relaxed);
+        Ok(res)
+    }
+}
+
+impl Counters
+{
+    /// Creates and initializes a new set of zeroed allocator statistics.
+    ///
+    /// Returns the created statistics.
+    const fn new() -> Self
+    {
+        Self { used: AtomicUsize::new(0),
+               peak: AtomicUsize::new(0),
+               allocs: AtomicUsize::new(0),
+               frees: AtomicUsize::new(0) }
+    }
+
+    /// Accounts for newly allocated bytes, updating the peak if necessary.
+    ///
+    /// * `size`: Number of bytes allocated.
+    fn add(&self, size: usize)
+    {
+        let used = self.used.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(used, Ordering::Relaxed);
     }
 }
 
@@ -352,9 +853,15 @@ impl Core
             Layout::from_size_align((old_layout.size() + 0xF) & !0xF, max(old_layout.align(), 16)).unwrap();
         let new_layout =
             Layout::from_size_align((new_layout.size() + 0xF) & !0xF, max(new_layout.align(), 16)).unwrap();
-        if new_layout.size() >= old_layout.size() {
+        if new_layout.size() > old_layout.size() {
             return Err(AllocError);
         }
+        // Sizes that round up to the same block leave nothing to release.
+        if new_layout.size() == old_layout.size() && base & (new_layout.align() - 1) == 0 {
+            let slice = slice_from_raw_parts(base as *mut u8, old_layout.size());
+            let slice = NonNull::from(slice);
+            return Ok(slice);
+        }
         let head = self.head
                        .as_mut()
                        .expect("Attempted to reallocate using an uninitialized allocator");
@@ -603,6 +1110,209 @@ mod tests
         assert!(matches!(err, TestError::Full));
     }
 
+    #[test]
+    fn alloc_aligned()
+    {
+        for align in [64, 0x1000] {
+            let buf = Buffer::new();
+            let core = unsafe { Core::new(buf.range()) };
+            let counters = Counters::new();
+            let alloc = Shell::new(&core, &counters);
+            let mut val = alloc.alloc_aligned::<[u8; 16]>(align);
+            let base = val.as_ptr() as usize;
+            assert_eq!(base & (align - 1), 0);
+            assert!(buf.range().contains(&base));
+            val.write([0x5A; 16]);
+            assert_eq!(unsafe { val.assume_init_ref() }, &[0x5A; 16]);
+            drop(val);
+            assert_eq!(alloc.stats().used, 0);
+            assert_eq!(alloc.stats().frees, 1);
+        }
+    }
+
+    #[test]
+    fn alloc_zeroed()
+    {
+        let buf = Buffer::new();
+        let core = unsafe { Core::new(buf.range()) };
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
+        let val = unsafe { alloc.alloc_zeroed_box::<[u8; 0x200]>().assume_init() };
+        assert!(val.iter().all(|byte| *byte == 0));
+        assert!(buf.buf[0x210 ..].iter().all(|byte| *byte == 0xFF));
+    }
+
+    #[test]
+    fn grow_in_place()
+    {
+        let buf = Buffer::new();
+        let core = unsafe { Core::new(buf.range()) };
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
+        let old: Box<[u16], Shell> = Box::new_in([1, 2, 3, 4, 5, 6, 7, 8], alloc);
+        let base = old.as_ptr();
+        let new = grow(old, 32);
+        assert_eq!(new.as_ptr(), base);
+        assert_eq!(new[.. 8], [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(new[8 ..].iter().all(|val| *val == 0));
+        let new = shrink(new, 4);
+        assert_eq!(*new, [1, 2, 3, 4]);
+        assert_eq!(alloc.stats().used, 8);
+    }
+
+    #[test]
+    fn shrink_same_block()
+    {
+        let buf = Buffer::new();
+        let core = unsafe { Core::new(buf.range()) };
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
+        let old: Box<[u16], Shell> = Box::new_in([1, 2, 3, 4, 5, 6, 7, 8], alloc);
+        let base = old.as_ptr();
+        let new = shrink(old, 8);
+        assert_eq!(new.as_ptr(), base);
+        assert_eq!(*new, [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(alloc.stats().used, 16);
+        let old: Box<[u8], Shell> = Box::new_in([7; 20], alloc);
+        let base = old.as_ptr();
+        let new = shrink(old, 18);
+        assert_eq!(new.as_ptr(), base);
+        assert_eq!(*new, [7; 18]);
+        assert_eq!(alloc.stats().used, 34);
+    }
+
+    #[test]
+    fn grow_move()
+    {
+        let buf = Buffer::new();
+        let core = unsafe { Core::new(buf.range()) };
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
+        let old: Box<[u16], Shell> = Box::new_in([1, 2, 3, 4, 5, 6, 7, 8], alloc);
+        // Block the memory right after the slice.
+        let _next = Box::new_in([0u8; 16], alloc);
+        let base = old.as_ptr();
+        let new = grow(old, 32);
+        assert_ne!(new.as_ptr(), base);
+        assert_eq!(new[.. 8], [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert!(new[8 ..].iter().all(|val| *val == 0));
+        let new = shrink(new, 0);
+        assert!(new.is_empty());
+        let new = grow(new, 2);
+        assert_eq!(*new, [0, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "OOM: 0x2000 bytes")]
+    fn oom_handler()
+    {
+        set_oom_handler(oom);
+        let buf = Buffer::new();
+        let core = unsafe { Core::new(buf.range()) };
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
+        alloc.alloc_zeroed_box::<[u8; 0x2000]>();
+    }
+
+    #[test]
+    #[should_panic(expected = "OOM: 0x2000 bytes")]
+    fn oom_handler_global()
+    {
+        set_oom_handler(oom);
+        let buf = Buffer::new();
+        let core = unsafe { Core::new(buf.range()) };
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
+        let layout = Layout::from_size_align(0x2000, 16).unwrap();
+        unsafe { GlobalAlloc::alloc(&alloc, layout) };
+    }
+
+    #[test]
+    fn pools()
+    {
+        let buf0 = Buffer::new();
+        let buf1 = Buffer::new();
+        let pool0 = unsafe { DmaPool::new(buf0.range().start, buf0.buf.len()) };
+        let pool1 = unsafe { DmaPool::new(buf1.range().start, buf1.buf.len()) };
+        let val0 = Box::new_in([0u8; 0x100], pool0.allocator());
+        let val1 = Box::new_in([0u8; 0x400], pool1.allocator());
+        assert!(buf0.range().contains(&(val0.as_ptr() as usize)));
+        assert!(buf1.range().contains(&(val1.as_ptr() as usize)));
+        assert_eq!(pool0.stats().used, 0x100);
+        assert_eq!(pool1.stats().used, 0x400);
+        drop(val0);
+        assert_eq!(pool0.stats().used, 0);
+        assert_eq!(pool1.stats().used, 0x400);
+        // Exhausting one pool leaves the other one intact.
+        let layout = Layout::from_size_align(0x1000, 16).unwrap();
+        assert!(pool0.allocator().allocate(layout).is_ok());
+        assert!(pool1.allocator().allocate(layout).is_err());
+    }
+
+    #[test]
+    fn dma_vec()
+    {
+        let buf = Buffer::new();
+        let core = unsafe { Core::new(buf.range()) };
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
+        let mut vec = DmaVec::<u32>::new_in(alloc);
+        assert!(vec.byte_range().is_empty());
+        (0 .. 100).for_each(|val| vec.push(val));
+        assert!(vec.iter().copied().eq(0 .. 100));
+        let range = vec.byte_range();
+        assert_eq!(range.start, vec.as_ptr() as usize);
+        assert_eq!(range.len(), 400);
+        assert!(buf.range().contains(&range.start) && buf.range().contains(&(range.end - 1)));
+        // Every element must be covered by the cache maintenance.
+        let lines = cache_lines(range.start, range.len(), 64).collect::<Vec<_>>();
+        assert!(vec.iter()
+                   .all(|val| lines.contains(&(val as *const u32 as usize & !63))));
+        vec.truncate(10);
+        assert_eq!(vec.byte_range().len(), 40);
+        let vec = DmaVec::<u64>::with_capacity_in(8, alloc);
+        assert!(vec.capacity() >= 8);
+        assert!(vec.byte_range().is_empty());
+    }
+
+    #[test]
+    fn cache_lines_count()
+    {
+        assert_eq!(cache_lines(0x1000, 64, 64).collect::<Vec<_>>(), [0x1000]);
+        assert_eq!(cache_lines(0x1000, 65, 64).count(), 2);
+        assert_eq!(cache_lines(0x1020, 64, 64).collect::<Vec<_>>(), [0x1000, 0x1040]);
+        assert_eq!(cache_lines(0x103F, 2, 64).collect::<Vec<_>>(), [0x1000, 0x1040]);
+        assert_eq!(cache_lines(0x1000, 0x1000, 128).count(), 32);
+        assert_eq!(cache_lines(0x1000, 0, 64).count(), 0);
+    }
+
+    #[test]
+    fn stats()
+    {
+        let buf = Buffer::new();
+        let core = unsafe { Core::new(buf.range()) };
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
+        let small = Layout::from_size_align(0x100, 16).unwrap();
+        let large = Layout::from_size_align(0x400, 16).unwrap();
+        let first = alloc.allocate(small).unwrap();
+        let second = alloc.allocate(large).unwrap();
+        unsafe { alloc.deallocate(first.as_non_null_ptr(), small) };
+        let stats = alloc.stats();
+        assert_eq!(stats.used, 0x400);
+        assert_eq!(stats.peak, 0x500);
+        assert_eq!(stats.allocs, 2);
+        assert_eq!(stats.frees, 1);
+        let grown = Layout::from_size_align(0x600, 16).unwrap();
+        let second = unsafe { alloc.grow(second.as_non_null_ptr(), large, grown).unwrap() };
+        unsafe { alloc.deallocate(second.as_non_null_ptr(), grown) };
+        let stats = alloc.stats();
+        assert_eq!(stats.used, 0);
+        assert_eq!(stats.peak, 0x600);
+        assert_eq!(stats.allocs, 2);
+        assert_eq!(stats.frees, 2);
+    }
+
     #[test]
     fn dealloc_tight()
     {
@@ -699,9 +1409,12 @@ mod tests
     {
         let mut buf = Buffer::new();
         let core = unsafe { Core::new(buf.range()) };
-        let alloc = Shell::new(&core);
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
         buf.provide(&core, input).map_err(TestError::Input)?;
-        let base = unsafe { alloc.alloc(layout) as usize };
+        // The global allocator interface diverges on failure, so go through the
+        // fallible one.
+        let base = alloc.allocate(layout).map_or(0, |base| base.as_mut_ptr() as usize);
         buf.validate(&core, output).map_err(TestError::Output)?;
         if base == 0 {
             return Err(TestError::Full);
@@ -710,12 +1423,18 @@ mod tests
         Ok(base)
     }
 
+    fn oom(layout: Layout) -> !
+    {
+        panic!("OOM: 0x{:x} bytes", layout.size());
+    }
+
     fn test_dealloc(base: usize, layout: Layout, input: &[Range<usize>], output: &[Range<usize>])
                     -> Result<(), TestError>
     {
         let mut buf = Buffer::new();
         let core = unsafe { Core::new(buf.range()) };
-        let alloc = Shell::new(&core);
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
         buf.provide(&core, input).map_err(TestError::Input)?;
         let base = base + buf.range().start;
         unsafe { alloc.dealloc(base as _, layout) };
@@ -728,7 +1447,8 @@ mod tests
     {
         let mut buf = Buffer::new();
         let core = unsafe { Core::new(buf.range()) };
-        let alloc = Shell::new(&core);
+        let counters = Counters::new();
+        let alloc = Shell::new(&core, &counters);
         buf.provide(&core, input).map_err(TestError::Input)?;
         let base = base + buf.range().start;
         let size = min(layout.size(), new_size);
@@ -240,8 +240,8 @@ map:
 
 // Interrupt vector.
 //
-// Panics on any EL2 interrupts, any Sync or SError EL1 interrupts, and does nothing for FIQs and IRQs
-// since those are handled synchronously.
+// Panics on any EL2 interrupts and any Sync or SError EL1 interrupts, and forwards IRQs and FIQs to
+// their respective handlers.
 .balign 0x800
 ivec:
 .irp kind,0,4,8,c
@@ -255,11 +255,7 @@ ivec:
     cmp x0, #0x4
     mov x0, #0x\kind + 1
     bne fault
-    mrs x0, spsr_el1
-    orr x0, x0, #0xc0
-    msr spsr_el1, x0
-    ldp x0, fp, [sp], #0x10
-    eret
+    b irq_entry
 .balign 0x80
     stp x0, fp, [sp, #-0x10]!
     mov fp, sp
@@ -267,11 +263,7 @@ ivec:
     cmp x0, #0x4
     mov x0, #0x\kind + 2
     bne fault
-    mrs x0, spsr_el1
-    orr x0, x0, #0xc0
-    msr spsr_el1, x0
-    ldp x0, fp, [sp], #0x10
-    eret
+    b fiq_entry
 .balign 0x80
     mov x0, #0x\kind + 3
     mov fp, sp
@@ -279,4 +271,153 @@ ivec:
 .balign 0x80
 .endr
 
+// Interrupt request entry.
+//
+// Expects x0 and fp to have been saved by the vector. Switches to SP_EL0 so that nested handlers
+// run on the interrupted thread's stack, saves everything that the handler is allowed to clobber
+// along with the exception state, since the handler can unmask IRQs to allow preemption, and masks
+// IRQs on return if the handler asks for it, which it does when they are meant to be dispatched
+// synchronously.
+irq_entry:
+    msr spsel, #0
+    stp x1, x2, [sp, #-0x10]!
+    stp x3, x4, [sp, #-0x10]!
+    stp x5, x6, [sp, #-0x10]!
+    stp x7, x8, [sp, #-0x10]!
+    stp x9, x10, [sp, #-0x10]!
+    stp x11, x12, [sp, #-0x10]!
+    stp x13, x14, [sp, #-0x10]!
+    stp x15, x16, [sp, #-0x10]!
+    stp x17, x18, [sp, #-0x10]!
+    mrs x1, fpsr
+    mrs x2, fpcr
+    stp x1, x2, [sp, #-0x10]!
+    mrs x1, elr_el1
+    mrs x2, spsr_el1
+    stp x1, x2, [sp, #-0x10]!
+    str lr, [sp, #-0x10]!
+    stp q0, q1, [sp, #-0x20]!
+    stp q2, q3, [sp, #-0x20]!
+    stp q4, q5, [sp, #-0x20]!
+    stp q6, q7, [sp, #-0x20]!
+    stp q8, q9, [sp, #-0x20]!
+    stp q10, q11, [sp, #-0x20]!
+    stp q12, q13, [sp, #-0x20]!
+    stp q14, q15, [sp, #-0x20]!
+    stp q16, q17, [sp, #-0x20]!
+    stp q18, q19, [sp, #-0x20]!
+    stp q20, q21, [sp, #-0x20]!
+    stp q22, q23, [sp, #-0x20]!
+    stp q24, q25, [sp, #-0x20]!
+    stp q26, q27, [sp, #-0x20]!
+    stp q28, q29, [sp, #-0x20]!
+    stp q30, q31, [sp, #-0x20]!
+    bl handle_irq
+    ldp q30, q31, [sp], #0x20
+    ldp q28, q29, [sp], #0x20
+    ldp q26, q27, [sp], #0x20
+    ldp q24, q25, [sp], #0x20
+    ldp q22, q23, [sp], #0x20
+    ldp q20, q21, [sp], #0x20
+    ldp q18, q19, [sp], #0x20
+    ldp q16, q17, [sp], #0x20
+    ldp q14, q15, [sp], #0x20
+    ldp q12, q13, [sp], #0x20
+    ldp q10, q11, [sp], #0x20
+    ldp q8, q9, [sp], #0x20
+    ldp q6, q7, [sp], #0x20
+    ldp q4, q5, [sp], #0x20
+    ldp q2, q3, [sp], #0x20
+    ldp q0, q1, [sp], #0x20
+    ldr lr, [sp], #0x10
+    ldp x1, x2, [sp], #0x10
+    cbz w0, 0f
+    orr x2, x2, #0x80
+0:
+    msr elr_el1, x1
+    msr spsr_el1, x2
+    ldp x1, x2, [sp], #0x10
+    msr fpsr, x1
+    msr fpcr, x2
+    ldp x17, x18, [sp], #0x10
+    ldp x15, x16, [sp], #0x10
+    ldp x13, x14, [sp], #0x10
+    ldp x11, x12, [sp], #0x10
+    ldp x9, x10, [sp], #0x10
+    ldp x7, x8, [sp], #0x10
+    ldp x5, x6, [sp], #0x10
+    ldp x3, x4, [sp], #0x10
+    ldp x1, x2, [sp], #0x10
+    msr spsel, #1
+    ldp x0, fp, [sp], #0x10
+    eret
+
+// Fast interrupt entry.
+//
+// Expects x0 and fp to have been saved by the vector, and saves everything else that the handler
+// is allowed to clobber, including all the SIMD registers since the interrupted code can be in the
+// middle of anything.
+fiq_entry:
+    stp x1, x2, [sp, #-0x10]!
+    stp x3, x4, [sp, #-0x10]!
+    stp x5, x6, [sp, #-0x10]!
+    stp x7, x8, [sp, #-0x10]!
+    stp x9, x10, [sp, #-0x10]!
+    stp x11, x12, [sp, #-0x10]!
+    stp x13, x14, [sp, #-0x10]!
+    stp x15, x16, [sp, #-0x10]!
+    stp x17, x18, [sp, #-0x10]!
+    mrs x1, fpsr
+    mrs x2, fpcr
+    stp x1, x2, [sp, #-0x10]!
+    str lr, [sp, #-0x10]!
+    stp q0, q1, [sp, #-0x20]!
+    stp q2, q3, [sp, #-0x20]!
+    stp q4, q5, [sp, #-0x20]!
+    stp q6, q7, [sp, #-0x20]!
+    stp q8, q9, [sp, #-0x20]!
+    stp q10, q11, [sp, #-0x20]!
+    stp q12, q13, [sp, #-0x20]!
+    stp q14, q15, [sp, #-0x20]!
+    stp q16, q17, [sp, #-0x20]!
+    stp q18, q19, [sp, #-0x20]!
+    stp q20, q21, [sp, #-0x20]!
+    stp q22, q23, [sp, #-0x20]!
+    stp q24, q25, [sp, #-0x20]!
+    stp q26, q27, [sp, #-0x20]!
+    stp q28, q29, [sp, #-0x20]!
+    stp q30, q31, [sp, #-0x20]!
+    bl handle_fiq
+    ldp q30, q31, [sp], #0x20
+    ldp q28, q29, [sp], #0x20
+    ldp q26, q27, [sp], #0x20
+    ldp q24, q25, [sp], #0x20
+    ldp q22, q23, [sp], #0x20
+    ldp q20, q21, [sp], #0x20
+    ldp q18, q19, [sp], #0x20
+    ldp q16, q17, [sp], #0x20
+    ldp q14, q15, [sp], #0x20
+    ldp q12, q13, [sp], #0x20
+    ldp q10, q11, [sp], #0x20
+    ldp q8, q9, [sp], #0x20
+    ldp q6, q7, [sp], #0x20
+    ldp q4, q5, [sp], #0x20
+    ldp q2, q3, [sp], #0x20
+    ldp q0, q1, [sp], #0x20
+    ldr lr, [sp], #0x10
+    ldp x1, x2, [sp], #0x10
+    msr fpsr, x1
+    msr fpcr, x2
+    ldp x17, x18, [sp], #0x10
+    ldp x15, x16, [sp], #0x10
+    ldp x13, x14, [sp], #0x10
+    ldp x11, x12, [sp], #0x10
+    ldp x9, x10, [sp], #0x10
+    ldp x7, x8, [sp], #0x10
+    ldp x5, x6, [sp], #0x10
+    ldp x3, x4, [sp], #0x10
+    ldp x1, x2, [sp], #0x10
+    ldp x0, fp, [sp], #0x10
+    eret
+
 .section .text
@@ -0,0 +1,102 @@
+//! Extended Display Identification Data parsing.
+//!
+//! Documentation:
+//!
+//! * [VESA Enhanced EDID Standard](https://glenwing.github.io/docs/VESA-EEDID-A2.pdf)
+//!   3.1, 3.10.2, and 3.11
+//!
+//! Only the parts needed to figure out the native resolution of the attached
+//! display are implemented.
+
+/// Size of an EDID block.
+pub const EDID_BLOCK_SIZE: usize = 128;
+/// Fixed header at the start of the base EDID block.
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+/// Offset of the number of extension blocks in the base block.
+const EXTENSIONS_OFFSET: usize = 126;
+/// Offset of the first detailed timing descriptor, which describes the
+/// preferred mode, in the base block.
+const PREFERRED_TIMING_OFFSET: usize = 54;
+
+/// Checks whether a base EDID block has a valid header and checksum.
+///
+/// * `block`: Base EDID block.
+///
+/// Returns whether the block is valid.
+pub fn is_valid(block: &[u8; EDID_BLOCK_SIZE]) -> bool
+{
+    // All the bytes, including the checksum in the last byte, must add up to zero.
+    let sum = block.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    block[.. HEADER.len()] == HEADER && sum == 0
+}
+
+/// Returns the number of extension blocks that follow a base EDID block.
+pub fn extension_count(block: &[u8; EDID_BLOCK_SIZE]) -> usize
+{
+    block[EXTENSIONS_OFFSET] as usize
+}
+
+/// Extracts the preferred resolution from a base EDID block.
+///
+/// * `block`: Base EDID block.
+///
+/// Returns the width and height of the preferred mode in pixels, or [`None`]
+/// if the block is invalid or does not describe a preferred mode.
+pub fn preferred_resolution(block: &[u8; EDID_BLOCK_SIZE]) -> Option<(usize, usize)>
+{
+    if !is_valid(block) {
+        return None;
+    }
+    let desc = &block[PREFERRED_TIMING_OFFSET .. PREFERRED_TIMING_OFFSET + 18];
+    // A zero pixel clock marks a display descriptor rather than a timing one.
+    if desc[0] == 0 && desc[1] == 0 {
+        return None;
+    }
+    // The lower 8 bits of each dimension are followed by the upper 4 bits in the
+    // upper nibble of a byte shared with the blanking size.
+    let width = desc[2] as usize | (desc[4] as usize >> 4) << 8;
+    let height = desc[5] as usize | (desc[7] as usize >> 4) << 8;
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn sample() -> [u8; EDID_BLOCK_SIZE]
+    {
+        let mut block = [0; EDID_BLOCK_SIZE];
+        block[.. HEADER.len()].copy_from_slice(&HEADER);
+        // 1920x1080 at 148.5MHz.
+        let desc = [0x02, 0x3A, 0x80, 0x18, 0x71, 0x38, 0x2D, 0x40];
+        block[PREFERRED_TIMING_OFFSET .. PREFERRED_TIMING_OFFSET + desc.len()].copy_from_slice(&desc);
+        block[EXTENSIONS_OFFSET] = 1;
+        let sum = block.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        block[EDID_BLOCK_SIZE - 1] = sum.wrapping_neg();
+        block
+    }
+
+    #[test]
+    fn edid_preferred_resolution()
+    {
+        let block = sample();
+        assert!(is_valid(&block));
+        assert_eq!(extension_count(&block), 1);
+        assert_eq!(preferred_resolution(&block), Some((1920, 1080)));
+    }
+
+    #[test]
+    fn edid_invalid()
+    {
+        let mut block = sample();
+        block[EDID_BLOCK_SIZE - 1] ^= 0x1;
+        assert!(!is_valid(&block));
+        assert_eq!(preferred_resolution(&block), None);
+        let mut block = sample();
+        // Break the header while keeping the checksum intact.
+        block[1] = 0;
+        block[EDID_BLOCK_SIZE - 1] = block[EDID_BLOCK_SIZE - 1].wrapping_add(0xFF);
+        assert!(!is_valid(&block));
+    }
+}
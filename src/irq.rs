@@ -9,22 +9,48 @@
 
 extern crate alloc;
 
-use alloc::collections::BTreeMap;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
+#[cfg(not(test))]
 use core::arch::asm;
-use core::ptr::write_volatile;
-use core::sync::atomic::{fence, Ordering};
+#[cfg(test)]
+use core::cell::RefCell;
+#[cfg(not(test))]
+use core::mem::transmute;
+use core::sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+#[cfg(not(test))]
+use core::time::Duration;
 
-use crate::sync::{Lazy, RwLock};
-use crate::PERRY_RANGE;
+#[cfg(not(test))]
+use crate::sync::Lazy;
+use crate::sync::{Lock, RwLock};
+use crate::time::{now, TimeStats};
+use crate::CPU_COUNT;
+#[cfg(not(test))]
+use crate::{cpu_id, debug, PERRY_RANGE};
 
 /// Number of SPIs on the BCM2711.
 const SPI_COUNT: usize = 192;
 /// Total number of IRQs on the BCM2711.
 const IRQ_COUNT: usize = SPI_COUNT + 32;
-/// Base address of theGIC 400.
+/// Base address of the GIC 400.
+#[cfg(not(test))]
 const GIC_BASE: usize = 0x3840000 + PERRY_RANGE.start;
+/// Base address of the simulated GIC 400 for tests, which is never
+/// dereferenced.
+#[cfg(test)]
+const GIC_BASE: usize = 0x3840000;
+/// Distributor control register.
+const GICD_CTLR: *mut u32 = (GIC_BASE + 0x1000) as _;
+/// Group 0 enable bit of the distributor control register.
+const GICD_CTLR_ENABLEGRP0: u32 = 0x1;
+/// Group 1 enable bit of the distributor control register.
+const GICD_CTLR_ENABLEGRP1: u32 = 0x2;
+/// IRQ group registers.
+const GICD_IGROUPR: *mut [u32; IRQ_COUNT >> 5] = (GIC_BASE + 0x1080) as _;
 /// IRQ set enable registers.
 const GICD_ISENABLER: *mut [u32; IRQ_COUNT >> 5] = (GIC_BASE + 0x1100) as _;
 /// IRQ clear enable registers.
@@ -37,22 +63,112 @@ const GICD_ITARGETSR: *mut [u8; IRQ_COUNT] = (GIC_BASE + 0x1800) as _;
 const GICD_ICFGR: *mut [u32; IRQ_COUNT >> 4 /* Two bits per field */] = (GIC_BASE + 0x1c00) as _;
 /// Software Generated IRQ register.
 const GICD_SGIR: *mut u32 = (GIC_BASE + 0x1F00) as _;
+/// CPU interface control register.
+const GICC_CTLR: *mut u32 = (GIC_BASE + 0x2000) as _;
+/// Group 0 enable bit of the CPU interface control register.
+const GICC_CTLR_ENABLEGRP0: u32 = 0x1;
+/// Group 1 enable bit of the CPU interface control register.
+const GICC_CTLR_ENABLEGRP1: u32 = 0x2;
+/// Bit of the CPU interface control register that signals group 0 IRQs as
+/// FIQs.
+const GICC_CTLR_FIQEN: u32 = 0x8;
 /// IRQ minimum priority register.
 const GICC_PMR: *mut u32 = (GIC_BASE + 0x2004) as _;
 /// IRQ acknowledge register.
 const GICC_IAR: *mut u32 = (GIC_BASE + 0x200C) as _;
-/// IRQ dismissal register.
+/// Running priority register.
+const GICC_RPR: *const u32 = (GIC_BASE + 0x2014) as _;
+/// Highest priority pending IRQ register.
+const GICC_HPPIR: *const u32 = (GIC_BASE + 0x2018) as _;
+/// IRQ dismissal register, which also deactivates the IRQ unless split EOI
+/// is enabled, in which case it only drops the running priority.
 const GICC_EOIR: *mut u32 = (GIC_BASE + 0x2010) as _;
+/// IRQ deactivation register, only used with split EOI.
+const GICC_DIR: *mut u32 = (GIC_BASE + 0x3000) as _;
+/// Split EOI bit of the CPU interface control register.
+const GICC_CTLR_EOIMODE_NS: u32 = 0x200;
+/// Interrupt ID read from the acknowledge register when no IRQ is pending.
+#[cfg(test)]
+const SPURIOUS_IRQ: u32 = 0x3FF;
+
+#[cfg(test)]
+std::thread_local! {
+    /// Simulated GIC 400 for tests.
+    static GIC: RefCell<Gic> = RefCell::new(Gic::default());
+}
 
 /// Global interrupt controller driver.
+#[cfg(not(test))]
 pub static IRQ: Lazy<Irq> = Lazy::new(Irq::new);
 
 /// IRQ driver.
 pub struct Irq
 {
-    /// Registered handlers.
+    /// Registered handlers, shared with the handlers being run so that looking
+    /// them up never allocates.
+    #[allow(clippy::type_complexity)]
+    handlers: RwLock<BTreeMap<u32, Arc<Vec<fn()>>>>,
+    /// Registered closure handlers, shared like the function handlers.
     #[allow(clippy::type_complexity)]
-    handlers: RwLock<BTreeMap<u32, Vec<fn()>>>,
+    closures: RwLock<BTreeMap<u32, Arc<Vec<Arc<dyn Fn() + Send + Sync>>>>>,
+    /// Number of times each IRQ was dispatched.
+    counts: [AtomicU64; IRQ_COUNT],
+    /// Time spent running the handlers of each IRQ.
+    timings: [TimeStats; IRQ_COUNT],
+    /// Number of times that either no IRQ was pending when checked or the
+    /// acknowledged IRQ had no handler.
+    spurious_count: AtomicU64,
+    /// IRQ routed to the FIQ path, or `u32::MAX` if none.
+    fiq_irq: AtomicU32,
+    /// Address of the handler of the IRQ routed to the FIQ path, or zero if
+    /// none.
+    fiq_handler: AtomicUsize,
+    /// Whether handlers can be preempted by higher priority IRQs.
+    preemptible: AtomicBool,
+    /// Whether each core's CPU interface separates priority drop from
+    /// deactivation.
+    split_eoi: [AtomicBool; CPU_COUNT],
+    /// Handler running on each core.
+    running: [Running; CPU_COUNT],
+    /// Work deferred by handlers to run after their IRQs are dismissed.
+    deferred: Lock<VecDeque<Box<dyn FnOnce() + Send>>>,
+}
+
+/// Handler running on a core, used to isolate handlers that panic.
+#[derive(Debug)]
+struct Running
+{
+    /// Value read from the acknowledge register for the IRQ being handled.
+    val: AtomicU32,
+    /// Address of the handler function or closure being run.
+    handler: AtomicUsize,
+    /// Number of nested IRQs being handled.
+    depth: AtomicU32,
+}
+
+/// Simulated GIC 400 for tests.
+#[cfg(test)]
+#[derive(Debug, Default)]
+struct Gic
+{
+    /// Last value written to each register, keyed by address.
+    regs: BTreeMap<usize, u32>,
+    /// Every write in order as address and value pairs.
+    writes: Vec<(usize, u32)>,
+    /// Values to be returned by the acknowledge register, which returns
+    /// [`SPURIOUS_IRQ`] once they run out.
+    acks: VecDeque<u32>,
+}
+
+/// Time spent running the handlers of an IRQ.
+#[cfg(not(test))]
+#[derive(Clone, Copy, Debug)]
+pub struct IrqTiming
+{
+    /// Total time across all dispatches.
+    pub total: Duration,
+    /// Longest time taken by a single dispatch.
+    pub max: Duration,
 }
 
 impl Irq
@@ -64,24 +180,36 @@ impl Irq
     {
         unsafe {
             // Disable all IRQs.
-            (*GICD_ICENABLER).iter_mut()
-                             .for_each(|element| write_volatile(element, 0xFFFFFFFF));
+            (0 .. IRQ_COUNT >> 5).for_each(|idx| write_reg(element(GICD_ICENABLER, idx), 0xFFFFFFFF));
             // Set the minimum priority level (higher values correspond to lower priority
             // levels).
-            GICC_PMR.write_volatile(0xFF);
+            write_reg(GICC_PMR, 0xFF);
             // Raise the priority of every IRQ as matching the lowest priority level masks
             // them.
-            (*GICD_IPRIORITYR).iter_mut()
-                              .for_each(|element| write_volatile(element, 0x7F));
+            (0 .. IRQ_COUNT).for_each(|idx| write_reg(element(GICD_IPRIORITYR, idx), 0x7F));
             // Make all IRQs level triggered.
-            (*GICD_ICFGR).iter_mut()
-                         .for_each(|element| write_volatile(element, 0x55555555));
+            (0 .. IRQ_COUNT >> 4).for_each(|idx| write_reg(element(GICD_ICFGR, idx), 0x55555555));
             // Deliver all SPIs to all cores.
-            (*GICD_ITARGETSR).iter_mut()
-                             .skip(32)
-                             .for_each(|element| write_volatile(element, 0xFF));
+            (32 .. IRQ_COUNT).for_each(|idx| write_reg(element(GICD_ITARGETSR, idx), 0xFF));
+            // Enable both groups in the distributor and in this core's CPU interface.
+            write_reg(GICD_CTLR,
+                      read_reg(GICD_CTLR) | GICD_CTLR_ENABLEGRP0 | GICD_CTLR_ENABLEGRP1);
+            write_reg(GICC_CTLR,
+                      read_reg(GICC_CTLR) | GICC_CTLR_ENABLEGRP0 | GICC_CTLR_ENABLEGRP1);
         }
-        Self { handlers: RwLock::new(BTreeMap::new()) }
+        Self { handlers: RwLock::new(BTreeMap::new()),
+               closures: RwLock::new(BTreeMap::new()),
+               counts: [(); IRQ_COUNT].map(|_| AtomicU64::new(0)),
+               timings: [(); IRQ_COUNT].map(|_| TimeStats::new()),
+               spurious_count: AtomicU64::new(0),
+               fiq_irq: AtomicU32::new(u32::MAX),
+               fiq_handler: AtomicUsize::new(0),
+               preemptible: AtomicBool::new(false),
+               split_eoi: [(); CPU_COUNT].map(|_| AtomicBool::new(false)),
+               running: [(); CPU_COUNT].map(|_| Running { val: AtomicU32::new(0),
+                                                          handler: AtomicUsize::new(0),
+                                                          depth: AtomicU32::new(0) }),
+               deferred: Lock::new(VecDeque::new()) }
     }
 
     /// Registers a handler to be called when the specified IRQ is triggered.
@@ -95,16 +223,259 @@ impl Irq
         // If there's at least one handler for this IRQ, just add the new handler
         // without touching the controller's registers.
         if let Some(vec) = handlers.get_mut(&irq) {
-            vec.push(handler);
+            Arc::make_mut(vec).push(handler);
             return;
         }
         // Figure out which register and bit to enable for the given IRQ.
-        let val = 0x1 << (irq & 0x1F);
-        let idx = irq as usize >> 5;
-        unsafe { write_volatile((*GICD_ISENABLER).get_mut(idx).unwrap(), val) };
+        let (idx, val) = irq_bit(irq);
+        unsafe { write_reg(element(GICD_ISENABLER, idx), val) };
         // Add a new vector of handlers along with the new handler.
         let vec = vec![handler];
-        handlers.insert(irq, vec);
+        handlers.insert(irq, Arc::new(vec));
+    }
+
+    /// Registers a closure to be called when the specified IRQ is triggered,
+    /// allowing drivers to capture their own state.
+    ///
+    /// * `irq`: IRQ to wait for.
+    /// * `handler`: Handler closure to register.
+    pub fn register_boxed(&self, irq: u32, handler: Box<dyn Fn() + Send + Sync>)
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        let mut closures = self.closures.wlock();
+        // If there's at least one closure for this IRQ, just add the new closure
+        // without touching the controller's registers.
+        if let Some(vec) = closures.get_mut(&irq) {
+            Arc::make_mut(vec).push(Arc::from(handler));
+            return;
+        }
+        // Enabling an IRQ that also has function handlers has no effect.
+        let (idx, val) = irq_bit(irq);
+        unsafe { write_reg(element(GICD_ISENABLER, idx), val) };
+        let vec = vec![Arc::from(handler)];
+        closures.insert(irq, Arc::new(vec));
+    }
+
+    /// Masks the specified IRQ at the controller without touching its
+    /// handlers.
+    ///
+    /// * `irq`: IRQ to mask.
+    pub fn disable(&self, irq: u32)
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        let (idx, val) = irq_bit(irq);
+        unsafe { write_reg(element(GICD_ICENABLER, idx), val) };
+    }
+
+    /// Unmasks the specified IRQ at the controller after it was masked with
+    /// [`Irq::disable`].
+    ///
+    /// * `irq`: IRQ to unmask.
+    ///
+    /// Panics if the IRQ has no registered handlers.
+    pub fn enable(&self, irq: u32)
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        // Hold the locks so that the handlers can't be unregistered in the meantime.
+        let handlers = self.handlers.rlock();
+        let closures = self.closures.rlock();
+        assert!(handlers.contains_key(&irq) || closures.contains_key(&irq),
+                "Attempted to enable IRQ #{irq} without any handlers");
+        let (idx, val) = irq_bit(irq);
+        unsafe { write_reg(element(GICD_ISENABLER, idx), val) };
+    }
+
+    /// Queues work to be run by the dispatcher once it's done dismissing the
+    /// current IRQ, allowing handlers to offload long tasks.
+    ///
+    /// * `work`: Function to run.
+    pub fn defer(&self, work: fn())
+    {
+        self.defer_boxed(Box::new(work));
+    }
+
+    /// Queues a closure to be run by the dispatcher once it's done dismissing
+    /// the current IRQ, allowing handlers to offload long tasks with their own
+    /// state.
+    ///
+    /// * `work`: Closure to run.
+    pub fn defer_boxed(&self, work: Box<dyn FnOnce() + Send>)
+    {
+        self.deferred.lock().push_back(work);
+    }
+
+    /// Runs all the work queued so far in FIFO order.
+    ///
+    /// Work queued while this function runs is left for the next call, so that
+    /// work that keeps deferring more work can't starve the dispatcher.
+    pub fn run_deferred(&self)
+    {
+        let count = self.deferred.lock().len();
+        for _ in 0 .. count {
+            // Don't hold the lock while running the work so that it can queue more.
+            let work = self.deferred.lock().pop_front();
+            if let Some(work) = work {
+                work();
+            }
+        }
+    }
+
+    /// Unregisters a handler previously registered for the specified IRQ,
+    /// disabling the IRQ once it has no handlers left.
+    ///
+    /// * `irq`: IRQ that the handler was waiting for.
+    /// * `handler`: Handler function to unregister.
+    pub fn unregister(&self, irq: u32, handler: fn())
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        let mut handlers = self.handlers.wlock();
+        let vec = if let Some(vec) = handlers.get_mut(&irq) {
+            vec
+        } else {
+            return;
+        };
+        // Function pointers are compared by address.
+        if let Some(pos) = vec.iter().position(|other| *other as usize == handler as usize) {
+            Arc::make_mut(vec).remove(pos);
+        }
+        if !vec.is_empty() {
+            return;
+        }
+        handlers.remove(&irq);
+        // Keep the IRQ enabled if closures are still waiting for it.
+        if self.closures.rlock().contains_key(&irq) {
+            return;
+        }
+        // Figure out which register and bit to disable for the given IRQ.
+        let (idx, val) = irq_bit(irq);
+        unsafe { write_reg(element(GICD_ICENABLER, idx), val) };
+    }
+
+    /// Restricts delivery of the specified Shared Peripheral Interrupt to a set
+    /// of cores.
+    ///
+    /// * `irq`: IRQ to configure.
+    /// * `cpu_mask`: Bit mask of the cores to deliver the IRQ to.
+    pub fn set_affinity(&self, irq: u32, cpu_mask: u8)
+    {
+        assert!((32 .. IRQ_COUNT).contains(&(irq as usize)),
+                "IRQ #{irq} is not a Shared Peripheral Interrupt");
+        assert!(cpu_mask != 0 && cpu_mask >> CPU_COUNT == 0,
+                "Invalid CPU mask for IRQ #{irq}: 0x{cpu_mask:X}");
+        unsafe { write_reg(element(GICD_ITARGETSR, irq as usize), cpu_mask) };
+    }
+
+    /// Sets the priority of the specified IRQ.
+    ///
+    /// Lower values correspond to higher priority levels, and the GIC 400 only
+    /// implements the 5 most significant bits. IRQs whose priority is not
+    /// higher than the one in `GICC_PMR`, which is set to `0xFF`, are masked,
+    /// so a priority of `0xF8` or above prevents the IRQ from being delivered
+    /// at all. The default priority is `0x7F`.
+    ///
+    /// By default handlers run from [`Self::dispatch`] with IRQs masked at the
+    /// CPU, so one IRQ is acknowledged and fully handled at a time, and
+    /// priorities only decide which pending IRQ gets acknowledged first. Once
+    /// preemption is enabled with [`Irq::set_preemptible`], a higher priority
+    /// IRQ also preempts the handlers of a lower priority one.
+    ///
+    /// * `irq`: IRQ to configure.
+    /// * `priority`: Priority level.
+    #[cfg(not(test))]
+    pub fn set_priority(&self, irq: u32, priority: u8)
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        unsafe { write_reg(element(GICD_IPRIORITYR, irq as usize), priority) };
+    }
+
+    /// Configures the specified IRQ as either edge or level triggered.
+    ///
+    /// * `irq`: IRQ to configure.
+    /// * `edge`: Whether the IRQ is edge triggered.
+    pub fn set_trigger(&self, irq: u32, edge: bool)
+    {
+        // The configuration of SGIs is read-only.
+        assert!((16 .. IRQ_COUNT).contains(&(irq as usize)),
+                "Attempted to configure the trigger of IRQ #{irq}");
+        let (idx, bit) = trigger_bit(irq);
+        // Hold the lock so that concurrent updates to the same register don't
+        // overwrite one another.
+        let _handlers = self.handlers.wlock();
+        unsafe {
+            let reg = element(GICD_ICFGR, idx);
+            let val = read_reg(reg);
+            let val = if edge { val | bit } else { val & !bit };
+            write_reg(reg, val);
+        }
+    }
+
+    /// Assigns the specified IRQ to an interrupt group.
+    ///
+    /// Group 0 is meant for secure or real-time interrupts and group 1 for
+    /// everything else, and both groups are enabled at initialization.
+    /// Writing the group registers requires secure access to the controller,
+    /// which depends on the boot stub, and is otherwise ignored.
+    ///
+    /// * `irq`: IRQ to configure.
+    /// * `group`: Group to assign the IRQ to, either 0 or 1.
+    pub fn set_group(&self, irq: u32, group: u8)
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        assert!(group <= 1, "Invalid group for IRQ #{irq}: {group}");
+        // Figure out which register and bit to update for the given IRQ.
+        let (idx, bit) = irq_bit(irq);
+        // Hold the lock to serialize the read-modify-write with other configuration
+        // changes.
+        let _handlers = self.handlers.wlock();
+        unsafe {
+            let reg = element(GICD_IGROUPR, idx);
+            let val = read_reg(reg);
+            let val = if group == 1 { val | bit } else { val & !bit };
+            write_reg(reg, val);
+        }
+    }
+
+    /// Routes the specified IRQ to the FIQ path, allowing its handler to
+    /// preempt other code even while IRQs are masked.
+    ///
+    /// * `irq`: IRQ to route.
+    /// * `handler`: Handler function to call when the IRQ is triggered.
+    ///
+    /// The handler runs in exception context on the current core's exception
+    /// stack. Unlike AArch32, AArch64 has no FIQ mode with banked registers, so
+    /// the exception vector saves all the caller-saved and SIMD registers
+    /// before calling the handler. The handler can interrupt code holding any
+    /// lock, so it must never lock anything itself.
+    ///
+    /// Only a single IRQ can be routed to the FIQ path, and only the calling
+    /// core is configured to take it, so SPIs should also be targeted at this
+    /// core with [`Irq::set_affinity`]. Group 0 interrupts are only available
+    /// with secure access to the controller, which depends on the boot stub.
+    pub fn route_fiq(&self, irq: u32, handler: fn())
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        assert!(self.fiq_handler
+                    .compare_exchange(0, handler as usize, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok(),
+                "Only one IRQ can be routed to the FIQ path");
+        self.fiq_irq.store(irq, Ordering::Release);
+        let (idx, bit) = irq_bit(irq);
+        // Hold the lock to serialize the read-modify-write with other configuration
+        // changes.
+        let _handlers = self.handlers.wlock();
+        unsafe {
+            // Group 0 interrupts are signaled as FIQs.
+            let reg = element(GICD_IGROUPR, idx);
+            write_reg(reg, read_reg(reg) & !bit);
+            // Use the highest priority so that the FIQ preempts IRQs being dispatched.
+            write_reg(element(GICD_IPRIORITYR, irq as usize), 0x0);
+            // Enable group 0 in the distributor.
+            write_reg(GICD_CTLR, read_reg(GICD_CTLR) | GICD_CTLR_ENABLEGRP0);
+            // Enable group 0 and signal it as FIQ in this core's CPU interface.
+            write_reg(GICC_CTLR, read_reg(GICC_CTLR) | GICC_CTLR_ENABLEGRP0 | GICC_CTLR_FIQEN);
+            write_reg(element(GICD_ISENABLER, idx), bit);
+        }
+        unmask_fiqs();
     }
 
     /// Raises the specified Software Generated Interrupt on all cores.
@@ -114,34 +485,1070 @@ impl Irq
     {
         assert!(irq < 16,
                 "Attempted to trigger a Software Generated Interrupt outside of the valid range");
-        let val = 0xFF8000 | irq; // Target all cores.
-        unsafe { GICD_SGIR.write_volatile(val) };
+        unsafe { write_reg(GICD_SGIR, sgi_request(irq, None)) };
+    }
+
+    /// Raises the specified Software Generated Interrupt on a single core.
+    ///
+    /// * `irq`: IRQ to raise.
+    /// * `cpu`: Core to signal.
+    pub fn trigger_core(&self, irq: u32, cpu: u8)
+    {
+        assert!(irq < 16,
+                "Attempted to trigger a Software Generated Interrupt outside of the valid range");
+        assert!((cpu as usize) < CPU_COUNT, "Core #{cpu} is out of range");
+        unsafe { write_reg(GICD_SGIR, sgi_request(irq, Some(cpu))) };
+    }
+
+    /// Returns the number of times that the specified IRQ was dispatched.
+    ///
+    /// * `irq`: IRQ to query.
+    pub fn count(&self, irq: u32) -> u64
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        self.counts[irq as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns the time spent running the handlers of the specified IRQ.
+    ///
+    /// * `irq`: IRQ to query.
+    ///
+    /// Time spent in handlers of higher priority IRQs that preempted these
+    /// handlers is included.
+    #[cfg(not(test))]
+    pub fn timing(&self, irq: u32) -> IrqTiming
+    {
+        assert!((irq as usize) < IRQ_COUNT, "IRQ #{irq} is out of range");
+        let timing = &self.timings[irq as usize];
+        IrqTiming { total: timing.total(),
+                    max: timing.max() }
+    }
+
+    /// Returns the number of times that each IRQ that fired at least once was
+    /// dispatched.
+    #[cfg(not(test))]
+    pub fn stats(&self) -> BTreeMap<u32, u64>
+    {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(irq, count)| (irq as u32, count.load(Ordering::Relaxed)))
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    /// Returns the number of times that the dispatcher either found no pending
+    /// IRQ and had to wait for one or acknowledged an IRQ without a handler.
+    ///
+    /// The latter can happen when an IRQ fires while its handlers are being
+    /// registered or unregistered.
+    pub fn spurious_count(&self) -> u64
+    {
+        self.spurious_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the priority of the IRQ being handled by the current core, or
+    /// `0xFF` if none is, which shows whether the values passed to
+    /// [`Self::set_priority`] are taking effect.
+    pub fn running_priority(&self) -> u8
+    {
+        let val = unsafe { read_reg(GICC_RPR) };
+        decode_rpr(val)
+    }
+
+    /// Returns the highest priority IRQ pending on the current core without
+    /// acknowledging it, or nothing if none is pending.
+    pub fn highest_pending(&self) -> Option<u32>
+    {
+        let val = unsafe { read_reg(GICC_HPPIR) };
+        decode_hppir(val)
+    }
+
+    /// Enables or disables preemption of handlers by higher priority IRQs.
+    ///
+    /// * `preemptible`: Whether to allow preemption.
+    ///
+    /// When enabled, IRQs are unmasked while handlers run, so the controller
+    /// can interrupt them with any pending IRQ whose priority, as set by
+    /// [`Irq::set_priority`], is higher than that of the running IRQ. The
+    /// controller keeps track of the running priorities as a stack that is
+    /// pushed on every acknowledgement and popped on every dismissal, which the
+    /// nesting of handlers naturally keeps in order. Preempting handlers run on
+    /// the stack of the preempted thread, and neither they nor the preempted
+    /// handlers may hold locks that the other can take, including by
+    /// registering or unregistering handlers. The dispatcher itself doesn't
+    /// allocate to run preempting handlers, but preempting handlers that
+    /// allocate can deadlock against preempted handlers that allocate too, as
+    /// both take the heap's lock, so at most one of them may allocate. Disabled
+    /// by default, in which case handlers always run to completion.
+    pub fn set_preemptible(&self, preemptible: bool)
+    {
+        self.preemptible.store(preemptible, Ordering::Relaxed);
+    }
+
+    /// Enables or disables split EOI on the current core, separating the
+    /// priority drop from the deactivation of every dispatched IRQ.
+    ///
+    /// * `split`: Whether to enable split EOI.
+    ///
+    /// When enabled, the running priority is dropped right after an IRQ is
+    /// acknowledged, so IRQs of any priority can preempt its handlers once
+    /// unmasked, while the IRQ itself remains active, and therefore can't
+    /// fire again, until the handlers return and it is deactivated. Must be
+    /// called on every core that should use split EOI while it isn't
+    /// handling any IRQ. Disabled by default.
+    pub fn set_split_eoi(&self, split: bool)
+    {
+        let cpu = cpu_id();
+        unsafe {
+            let ctlr = read_reg(GICC_CTLR);
+            let ctlr = if split {
+                ctlr | GICC_CTLR_EOIMODE_NS
+            } else {
+                ctlr & !GICC_CTLR_EOIMODE_NS
+            };
+            write_reg(GICC_CTLR, ctlr);
+        }
+        self.split_eoi[cpu].store(split, Ordering::Relaxed);
+    }
+
+    /// Isolates a panic raised by an IRQ handler on the current core, which
+    /// is meant to be called by the panic handler.
+    ///
+    /// Without unwinding there is no way to resume the panicking handler, so
+    /// it is unregistered, its IRQ is dismissed, and the current core goes
+    /// back to dispatching IRQs on top of the stack frames of the abandoned
+    /// handler, leaking them along with any lock or resource that they were
+    /// holding. Since every panicking handler is unregistered, the leaked
+    /// stack is bounded by the number of handlers that ever panic. Recovery is
+    /// only attempted from the outermost handler run by the dispatcher, as the
+    /// code preempted by a nested handler can't be abandoned safely.
+    ///
+    /// Returns without doing anything if the panic can't be isolated, in which
+    /// case the caller should halt the system.
+    #[cfg(not(test))]
+    pub fn isolate_panic(&self)
+    {
+        if self.abandon_running() {
+            self.dispatch()
+        }
+    }
+
+    /// Unregisters the handler running on the current core and dismisses its
+    /// IRQ on behalf of [`Self::isolate_panic`].
+    ///
+    /// Returns whether the handler was abandoned, in which case IRQs are masked
+    /// and the current core can go back to dispatching.
+    fn abandon_running(&self) -> bool
+    {
+        let running = &self.running[cpu_id()];
+        if running.depth.load(Ordering::Relaxed) != 1 {
+            return false;
+        }
+        let val = running.val.load(Ordering::Relaxed);
+        let handler = running.handler.load(Ordering::Relaxed);
+        let irq = val & 0x3FF;
+        // Don't wait for the locks, as the panicking handler might be holding them.
+        let (mut handlers, mut closures) = match (self.handlers.try_wlock(), self.closures.try_wlock()) {
+            (Some(handlers), Some(closures)) => (handlers, closures),
+            _ => return false,
+        };
+        if let Some(vec) = handlers.get_mut(&irq) {
+            Arc::make_mut(vec).retain(|other| *other as usize != handler);
+            if vec.is_empty() {
+                handlers.remove(&irq);
+            }
+        }
+        if let Some(vec) = closures.get_mut(&irq) {
+            Arc::make_mut(vec).retain(|other| Arc::as_ptr(other) as *const u8 as usize != handler);
+            if vec.is_empty() {
+                closures.remove(&irq);
+            }
+        }
+        if !handlers.contains_key(&irq) && !closures.contains_key(&irq) {
+            let (idx, bit) = irq_bit(irq);
+            unsafe { write_reg(element(GICD_ICENABLER, idx), bit) };
+        }
+        drop(closures);
+        drop(handlers);
+        #[cfg(not(test))]
+        debug!("Unregistered a panicking handler of IRQ #{irq}");
+        running.depth.store(0, Ordering::Relaxed);
+        mask_irqs();
+        fence(Ordering::SeqCst);
+        // With split EOI the priority was already dropped before running the handlers.
+        if self.split_eoi[cpu_id()].load(Ordering::Relaxed) {
+            unsafe { write_reg(GICC_DIR, val) };
+        } else {
+            unsafe { write_reg(GICC_EOIR, val) };
+        }
+        true
     }
 
     /// Checks for and processes pending IRQs in an infinite loop.
+    #[cfg(not(test))]
     pub fn dispatch(&self) -> !
     {
         loop {
-            let val = unsafe { GICC_IAR.read_volatile() };
-            fence(Ordering::SeqCst);
-            let irq = val & 0x3FF; // Strip sender info from SGIs.
-            if irq as usize >= IRQ_COUNT {
-                unsafe {
-                    asm!("msr daifclr, 0x3",
-                         "wfi",
-                         "msr daifset, 0x3",
-                         options(nomem, nostack, preserves_flags))
-                };
-                continue;
-            }
-            let handlers = self.handlers
-                               .rlock()
-                               .get(&irq)
-                               .expect("Received an IRQ without a handler")
-                               .clone();
-            handlers.iter().for_each(|handler| handler());
+            self.dispatch_once();
+        }
+    }
+
+    /// Checks for and processes pending IRQs until the specified flag is set.
+    ///
+    /// * `stop`: Flag checked after every IRQ and every wake up.
+    ///
+    /// Since waiting for an IRQ can only be interrupted by an IRQ, whoever sets
+    /// the flag should also trigger one to make sure that the dispatcher
+    /// notices.
+    pub fn dispatch_until(&self, stop: &AtomicBool)
+    {
+        while !stop.load(Ordering::Acquire) {
+            self.dispatch_once();
+        }
+    }
+
+    /// Processes the highest priority pending IRQ, or waits for one if none is
+    /// pending.
+    fn dispatch_once(&self)
+    {
+        let val = unsafe { read_reg(GICC_IAR) };
+        fence(Ordering::SeqCst);
+        let irq = val & 0x3FF; // Strip sender info from SGIs.
+        if irq as usize >= IRQ_COUNT {
+            self.spurious_count.fetch_add(1, Ordering::Relaxed);
+            self.run_deferred();
+            wait_for_irq();
+            return;
+        }
+        self.handle_acknowledged(val, self.preemptible.load(Ordering::Relaxed));
+        self.run_deferred();
+    }
+
+    /// Acknowledges and handles an IRQ that preempted the current code on
+    /// behalf of the exception vector.
+    ///
+    /// Returns whether IRQs must remain masked on return, which is the case
+    /// when preemption is disabled and the IRQ is therefore left for the
+    /// dispatcher.
+    fn preempt(&self) -> bool
+    {
+        if !self.preemptible.load(Ordering::Relaxed) {
+            return true;
+        }
+        let val = unsafe { read_reg(GICC_IAR) };
+        fence(Ordering::SeqCst);
+        let irq = val & 0x3FF; // Strip sender info from SGIs.
+        if irq as usize >= IRQ_COUNT {
+            return false;
+        }
+        self.handle_acknowledged(val, true);
+        false
+    }
+
+    /// Runs all the handlers of an acknowledged IRQ and dismisses it.
+    ///
+    /// * `val`: Value read from the acknowledge register.
+    /// * `preemptible`: Whether to unmask IRQs while the handlers run.
+    fn handle_acknowledged(&self, val: u32, preemptible: bool)
+    {
+        let running = &self.running[cpu_id()];
+        let depth = running.depth.fetch_add(1, Ordering::Relaxed);
+        let outer = running.val.swap(val, Ordering::Relaxed);
+        let split = self.split_eoi[cpu_id()].load(Ordering::Relaxed);
+        if split {
+            // Drop the running priority before running the handlers, leaving the IRQ
+            // active until they return.
             fence(Ordering::SeqCst);
-            unsafe { GICC_EOIR.write_volatile(val as _) };
+            unsafe { write_reg(GICC_EOIR, val) };
+        }
+        self.handle(val & 0x3FF, preemptible);
+        fence(Ordering::SeqCst);
+        if split {
+            unsafe { write_reg(GICC_DIR, val) };
+        } else {
+            unsafe { write_reg(GICC_EOIR, val) };
         }
+        running.val.store(outer, Ordering::Relaxed);
+        running.depth.store(depth, Ordering::Relaxed);
+    }
+
+    /// Runs all the handlers of an acknowledged IRQ.
+    ///
+    /// * `irq`: IRQ to handle.
+    /// * `preemptible`: Whether to unmask IRQs while the handlers run.
+    fn handle(&self, irq: u32, preemptible: bool)
+    {
+        // Only take references to the handlers, as the preempted code might be
+        // holding the heap's lock. Changes made while they run copy the lists
+        // instead, leaving the last reference, and therefore the deallocation of the
+        // old lists, to this function.
+        let handlers = self.handlers.rlock().get(&irq).cloned();
+        let closures = self.closures.rlock().get(&irq).cloned();
+        // Enabling or disabling an IRQ and updating its handlers don't happen
+        // atomically, so just dismiss IRQs that arrive in between.
+        if handlers.is_none() && closures.is_none() {
+            self.spurious_count.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.counts[irq as usize].fetch_add(1, Ordering::Relaxed);
+        }
+        if preemptible {
+            unmask_irqs();
+        }
+        let running = &self.running[cpu_id()].handler;
+        let outer = running.load(Ordering::Relaxed);
+        let start = now();
+        handlers.iter().flat_map(|vec| vec.iter()).for_each(|handler| {
+                                                      running.store(*handler as usize, Ordering::Relaxed);
+                                                      handler()
+                                                  });
+        closures.iter().flat_map(|vec| vec.iter()).for_each(|handler| {
+                                                      running.store(Arc::as_ptr(handler) as *const u8 as usize,
+                                                                    Ordering::Relaxed);
+                                                      handler()
+                                                  });
+        self.timings[irq as usize].record(start, now());
+        running.store(outer, Ordering::Relaxed);
+        if preemptible {
+            mask_irqs();
+        }
+    }
+}
+
+/// Acknowledges and handles an IRQ that preempted the current code.
+///
+/// Called from the exception vector with all the caller-saved registers and
+/// the exception state preserved.
+///
+/// Returns whether IRQs must remain masked on return, which is the case when
+/// preemption is disabled and the IRQ is therefore left for the dispatcher.
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn handle_irq() -> bool
+{
+    IRQ.preempt()
+}
+
+/// Acknowledges and handles the IRQ routed to the FIQ path.
+///
+/// Called from the exception vector with all the caller-saved registers
+/// preserved.
+#[cfg(not(test))]
+#[no_mangle]
+pub extern "C" fn handle_fiq()
+{
+    let val = unsafe { read_reg(GICC_IAR) };
+    fence(Ordering::SeqCst);
+    let irq = val & 0x3FF;
+    if irq as usize >= IRQ_COUNT {
+        return;
+    }
+    if irq == IRQ.fiq_irq.load(Ordering::Acquire) {
+        let handler = IRQ.fiq_handler.load(Ordering::Acquire);
+        if handler != 0 {
+            let handler = unsafe { transmute::<usize, fn()>(handler) };
+            handler();
+        }
+    }
+    fence(Ordering::SeqCst);
+    unsafe { write_reg(GICC_EOIR, val) };
+}
+
+/// Encodes a request to raise a Software Generated Interrupt.
+///
+/// * `irq`: IRQ to raise.
+/// * `cpu`: Core to signal, or [`None`] to signal all cores.
+///
+/// Returns the value to write to the Software Generated IRQ register.
+fn sgi_request(irq: u32, cpu: Option<u8>) -> u32
+{
+    match cpu {
+        // Use the target list with a single core in it.
+        Some(cpu) => (1 << (cpu as u32 + 16)) | irq,
+        // Target all cores.
+        None => 0xFF8000 | irq,
+    }
+}
+
+/// Locates the bit of an IRQ in the registers with one bit per IRQ, such as
+/// the enable and group registers.
+///
+/// * `irq`: IRQ to locate.
+///
+/// Returns the index of the register and the mask of the bit.
+fn irq_bit(irq: u32) -> (usize, u32)
+{
+    (irq as usize >> 5, 0x1 << (irq & 0x1F))
+}
+
+/// Locates the field of an IRQ in the trigger configuration registers, where
+/// the most significant bit of each two-bit field selects edge triggering.
+///
+/// * `irq`: IRQ to locate.
+///
+/// Returns the index of the register and the mask of the edge trigger bit.
+fn trigger_bit(irq: u32) -> (usize, u32)
+{
+    (irq as usize >> 4, 0x2 << ((irq & 0xF) << 1))
+}
+
+/// Extracts the running priority from the running priority register.
+///
+/// * `val`: Value read from the register.
+///
+/// Returns the running priority.
+fn decode_rpr(val: u32) -> u8
+{
+    (val & 0xFF) as u8
+}
+
+/// Extracts the IRQ from the highest priority pending IRQ register.
+///
+/// * `val`: Value read from the register.
+///
+/// Returns the pending IRQ, or nothing if the value doesn't refer to one.
+fn decode_hppir(val: u32) -> Option<u32>
+{
+    let irq = val & 0x3FF; // Strip sender info from SGIs.
+    if irq as usize >= IRQ_COUNT {
+        return None;
+    }
+    Some(irq)
+}
+
+/// Computes the address of a register in a register array.
+///
+/// * `array`: Register array.
+/// * `idx`: Index of the register.
+///
+/// Returns the address of the register.
+///
+/// Panics if the index is out of range.
+fn element<T, const N: usize>(array: *mut [T; N], idx: usize) -> *mut T
+{
+    assert!(idx < N, "Register #{idx} is out of range");
+    array.cast::<T>().wrapping_add(idx)
+}
+
+/// Reads a controller register.
+///
+/// * `reg`: Register to read.
+///
+/// Returns the value read.
+#[cfg(not(test))]
+unsafe fn read_reg(reg: *const u32) -> u32
+{
+    reg.read_volatile()
+}
+
+/// Reads a register of the simulated controller for tests, popping the next
+/// queued value from the acknowledge register.
+#[cfg(test)]
+unsafe fn read_reg(reg: *const u32) -> u32
+{
+    GIC.with(|gic| {
+           let mut gic = gic.borrow_mut();
+           if core::ptr::eq(reg, GICC_IAR) {
+               return gic.acks.pop_front().unwrap_or(SPURIOUS_IRQ);
+           }
+           gic.regs.get(&(reg as usize)).copied().unwrap_or(0)
+       })
+}
+
+/// Writes to a controller register.
+///
+/// * `reg`: Register to write.
+/// * `val`: Value to write.
+#[cfg(not(test))]
+unsafe fn write_reg<T: Copy + Into<u32>>(reg: *mut T, val: T)
+{
+    reg.write_volatile(val);
+}
+
+/// Writes to a register of the simulated controller for tests, logging the
+/// write.
+#[cfg(test)]
+unsafe fn write_reg<T: Copy + Into<u32>>(reg: *mut T, val: T)
+{
+    GIC.with(|gic| {
+           let mut gic = gic.borrow_mut();
+           gic.regs.insert(reg as usize, val.into());
+           gic.writes.push((reg as usize, val.into()));
+       });
+}
+
+/// Unmasks IRQs on the current core.
+#[cfg(not(test))]
+fn unmask_irqs()
+{
+    unsafe { asm!("msr daifclr, #0x2", options(nomem, nostack, preserves_flags)) };
+}
+
+/// Does nothing in tests, where IRQs are only ever simulated.
+#[cfg(test)]
+fn unmask_irqs() {}
+
+/// Masks IRQs on the current core.
+#[cfg(not(test))]
+fn mask_irqs()
+{
+    unsafe { asm!("msr daifset, #0x2", options(nomem, nostack, preserves_flags)) };
+}
+
+/// Does nothing in tests, where IRQs are only ever simulated.
+#[cfg(test)]
+fn mask_irqs() {}
+
+/// Unmasks FIQs on the current core.
+#[cfg(not(test))]
+fn unmask_fiqs()
+{
+    unsafe { asm!("msr daifclr, #0x1", options(nomem, nostack, preserves_flags)) };
+}
+
+/// Does nothing in tests, where FIQs are only ever simulated.
+#[cfg(test)]
+fn unmask_fiqs() {}
+
+/// Waits for an IRQ on the current core with IRQs and FIQs briefly unmasked,
+/// leaving IRQs masked again on return.
+#[cfg(not(test))]
+fn wait_for_irq()
+{
+    unsafe {
+        asm!("msr daifclr, 0x3",
+             "wfi",
+             "msr daifset, 0x2",
+             options(nomem, nostack, preserves_flags))
+    };
+}
+
+/// Returns immediately in tests, where the simulated acknowledge register
+/// is only fed by the tests themselves.
+#[cfg(test)]
+fn wait_for_irq() {}
+
+/// Returns the core running tests, which always behaves as the first.
+#[cfg(test)]
+fn cpu_id() -> usize
+{
+    0
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    /// Number of times that [`first`] was called.
+    static FIRST: AtomicU64 = AtomicU64::new(0);
+    /// Number of times that [`second`] was called.
+    static SECOND: AtomicU64 = AtomicU64::new(0);
+    /// Number of times that [`deferred`] was called.
+    static DEFERRED: AtomicU64 = AtomicU64::new(0);
+
+    #[test]
+    fn sgi_encoding()
+    {
+        assert_eq!(sgi_request(5, None), 0xFF8005);
+        assert_eq!(sgi_request(5, Some(2)), 0x40005);
+        assert_eq!(sgi_request(15, Some(0)), 0x1000F);
+        assert_eq!(sgi_request(0, Some(3)), 0x80000);
+    }
+
+    #[test]
+    fn trigger_core()
+    {
+        let irq = Irq::new();
+        clear_writes();
+        irq.trigger_core(7, 2);
+        irq.trigger(7);
+        assert_eq!(writes(GICD_SGIR), [0x40007, 0xFF8007]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn trigger_core_out_of_range()
+    {
+        let irq = Irq::new();
+        irq.trigger_core(7, CPU_COUNT as u8);
+    }
+
+    #[test]
+    fn deferred_order()
+    {
+        let irq: &'static Irq = Box::leak(Box::new(Irq::new()));
+        let order = Arc::new(Lock::new(Vec::new()));
+        for idx in 0 .. 3 {
+            let order = order.clone();
+            irq.defer_boxed(Box::new(move || order.lock().push(idx)));
+        }
+        // Work deferred while draining is left for the next run.
+        let inner = order.clone();
+        irq.defer_boxed(Box::new(move || {
+                            inner.lock().push(3);
+                            let inner = inner.clone();
+                            irq.defer_boxed(Box::new(move || inner.lock().push(4)));
+                        }));
+        irq.run_deferred();
+        assert_eq!(*order.lock(), [0, 1, 2, 3]);
+        irq.run_deferred();
+        assert_eq!(*order.lock(), [0, 1, 2, 3, 4]);
+        irq.run_deferred();
+        assert_eq!(*order.lock(), [0, 1, 2, 3, 4]);
+        // Plain functions are deferred in the same queue.
+        irq.defer(deferred);
+        irq.run_deferred();
+        assert_eq!(DEFERRED.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn preemption_nesting()
+    {
+        let irq: &'static Irq = Box::leak(Box::new(Irq::new()));
+        irq.set_preemptible(true);
+        let seen = Arc::new(Lock::new(Vec::new()));
+        // Records the IRQ being handled and the nesting depth.
+        let record = |seen: &Lock<Vec<(u32, u32)>>| {
+            let running = &irq.running[cpu_id()];
+            seen.lock()
+                .push((running.val.load(Ordering::Relaxed), running.depth.load(Ordering::Relaxed)));
+        };
+        let inner = seen.clone();
+        irq.register_boxed(41, Box::new(move || record(&inner)));
+        let outer = seen.clone();
+        irq.register_boxed(40,
+                           Box::new(move || {
+                               record(&outer);
+                               // Simulate a higher priority IRQ arriving in the middle of this handler.
+                               acknowledge(&[41]);
+                               assert!(!irq.preempt());
+                               record(&outer);
+                           }));
+        acknowledge(&[40]);
+        clear_writes();
+        irq.dispatch_once();
+        assert_eq!(*seen.lock(), [(40, 1), (41, 2), (40, 1)]);
+        assert_eq!(writes(GICC_EOIR), [41, 40]);
+        let running = &irq.running[cpu_id()];
+        assert_eq!(running.val.load(Ordering::Relaxed), 0);
+        assert_eq!(running.depth.load(Ordering::Relaxed), 0);
+        assert_eq!(irq.count(40), 1);
+        assert_eq!(irq.count(41), 1);
+    }
+
+    #[test]
+    fn preemption_shared_handlers()
+    {
+        let irq: &'static Irq = Box::leak(Box::new(Irq::new()));
+        irq.set_preemptible(true);
+        let seen = Arc::new(Lock::new(Vec::new()));
+        let inner = seen.clone();
+        irq.register_boxed(41,
+                           Box::new(move || {
+                               // The dispatcher shares the registered lists instead of copying
+                               // them.
+                               let count = Arc::strong_count(&irq.closures.rlock()[&41]);
+                               inner.lock().push(count);
+                           }));
+        irq.register(41, first);
+        let outer = seen.clone();
+        irq.register_boxed(40,
+                           Box::new(move || {
+                               // Allocate in the preempted handler.
+                               let mut buf = Vec::with_capacity(16);
+                               buf.push(0u32);
+                               acknowledge(&[41]);
+                               assert!(!irq.preempt());
+                               buf.push(1);
+                               outer.lock().push(buf.len());
+                           }));
+        acknowledge(&[40]);
+        irq.dispatch_once();
+        assert_eq!(*seen.lock(), [2, 2]);
+        // Nothing is left referencing the lists once the handlers return.
+        assert_eq!(Arc::strong_count(&irq.closures.rlock()[&41]), 1);
+        assert_eq!(Arc::strong_count(&irq.handlers.rlock()[&41]), 1);
+        assert_eq!(irq.count(41), 1);
+    }
+
+    #[test]
+    fn preemption_disabled()
+    {
+        let irq = Irq::new();
+        acknowledge(&[40]);
+        clear_writes();
+        // The IRQ is left pending for the dispatcher.
+        assert!(irq.preempt());
+        assert!(writes(GICC_EOIR).is_empty());
+        irq.set_preemptible(true);
+        assert!(!irq.preempt());
+        assert_eq!(writes(GICC_EOIR), [40]);
+        // Nothing is dismissed when no IRQ is pending.
+        assert!(!irq.preempt());
+        assert_eq!(writes(GICC_EOIR), [40]);
+    }
+
+    #[test]
+    fn trigger_encoding()
+    {
+        assert_eq!(trigger_bit(16), (1, 0x2));
+        assert_eq!(trigger_bit(31), (1, 0x80000000));
+        assert_eq!(trigger_bit(37), (2, 0x800));
+        assert_eq!(trigger_bit(223), (13, 0x80000000));
+    }
+
+    #[test]
+    fn trigger_neighbours()
+    {
+        let irq = Irq::new();
+        irq.set_trigger(37, true);
+        assert_eq!(value(element(GICD_ICFGR, 2)), 0x55555D55);
+        irq.set_trigger(38, true);
+        assert_eq!(value(element(GICD_ICFGR, 2)), 0x55557D55);
+        irq.set_trigger(37, false);
+        assert_eq!(value(element(GICD_ICFGR, 2)), 0x55557555);
+        // The registers of other IRQs are left alone.
+        assert_eq!(value(element(GICD_ICFGR, 1)), 0x55555555);
+        assert_eq!(value(element(GICD_ICFGR, 3)), 0x55555555);
+    }
+
+    #[test]
+    fn unregister()
+    {
+        let irq = Irq::new();
+        clear_writes();
+        // Unregistering from an IRQ without handlers does nothing.
+        irq.unregister(40, first);
+        assert!(writes(element(GICD_ICENABLER, 1)).is_empty());
+        irq.register(40, first);
+        assert_eq!(writes(element(GICD_ISENABLER, 1)), [0x100]);
+        // Neither does unregistering a handler that was never registered.
+        irq.unregister(40, second);
+        assert_eq!(irq.handlers.rlock()[&40].len(), 1);
+        irq.register(40, second);
+        irq.unregister(40, first);
+        assert_eq!(irq.handlers.rlock()[&40].len(), 1);
+        assert!(writes(element(GICD_ICENABLER, 1)).is_empty());
+        // The IRQ is only disabled once its last handler is gone.
+        irq.unregister(40, second);
+        assert!(!irq.handlers.rlock().contains_key(&40));
+        assert_eq!(writes(element(GICD_ICENABLER, 1)), [0x100]);
+    }
+
+    #[test]
+    fn unregister_with_closures()
+    {
+        let irq = Irq::new();
+        irq.register(63, first);
+        irq.register_boxed(63, Box::new(|| ()));
+        clear_writes();
+        // Closures keep the IRQ enabled.
+        irq.unregister(63, first);
+        assert!(!irq.handlers.rlock().contains_key(&63));
+        assert!(writes(element(GICD_ICENABLER, 1)).is_empty());
+    }
+
+    #[test]
+    fn affinity()
+    {
+        let irq = Irq::new();
+        assert_eq!(value(element(GICD_ITARGETSR, 96)), 0xFF);
+        irq.set_affinity(96, 0x4);
+        assert_eq!(value(element(GICD_ITARGETSR, 96)), 0x4);
+        // The target bytes of other IRQs are left alone.
+        assert_eq!(value(element(GICD_ITARGETSR, 95)), 0xFF);
+        assert_eq!(value(element(GICD_ITARGETSR, 97)), 0xFF);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid CPU mask for IRQ #96: 0x10")]
+    fn affinity_out_of_range()
+    {
+        let irq = Irq::new();
+        irq.set_affinity(96, 0x10);
+    }
+
+    #[test]
+    fn dispatch_without_handlers()
+    {
+        let irq = Irq::new();
+        acknowledge(&[50]);
+        clear_writes();
+        irq.dispatch_once();
+        assert_eq!(writes(GICC_EOIR), [50]);
+        assert_eq!(irq.spurious_count(), 1);
+        assert_eq!(irq.count(50), 0);
+    }
+
+    #[test]
+    fn fiq_routing()
+    {
+        let irq = Irq::new();
+        unsafe { write_reg(element(GICD_IGROUPR, 3), 0xFFFFFFFF) };
+        clear_writes();
+        irq.route_fiq(97, first);
+        // Only the group bit of the routed IRQ is cleared.
+        assert_eq!(value(element(GICD_IGROUPR, 3)), 0xFFFFFFFD);
+        assert_eq!(value(element(GICD_IPRIORITYR, 97)), 0x0);
+        assert_eq!(value(GICD_CTLR), GICD_CTLR_ENABLEGRP0 | GICD_CTLR_ENABLEGRP1);
+        assert_eq!(value(GICC_CTLR),
+                   GICC_CTLR_ENABLEGRP0 | GICC_CTLR_ENABLEGRP1 | GICC_CTLR_FIQEN);
+        assert_eq!(writes(element(GICD_ISENABLER, 3)), [0x2]);
+        assert_eq!(irq.fiq_irq.load(Ordering::Relaxed), 97);
+        assert_eq!(irq.fiq_handler.load(Ordering::Relaxed), first as fn() as usize);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only one IRQ can be routed to the FIQ path")]
+    fn fiq_routing_twice()
+    {
+        let irq = Irq::new();
+        irq.route_fiq(97, first);
+        irq.route_fiq(98, second);
+    }
+
+    #[test]
+    fn disable_enable()
+    {
+        let irq = Irq::new();
+        irq.register(75, first);
+        clear_writes();
+        irq.disable(75);
+        assert_eq!(writes(element(GICD_ICENABLER, 2)), [0x800]);
+        irq.enable(75);
+        assert_eq!(writes(element(GICD_ISENABLER, 2)), [0x800]);
+        // The handlers are left alone.
+        assert_eq!(irq.handlers.rlock()[&75].len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Attempted to enable IRQ #75 without any handlers")]
+    fn enable_without_handlers()
+    {
+        let irq = Irq::new();
+        irq.enable(75);
+    }
+
+    #[test]
+    fn dispatch_until_stopped()
+    {
+        let irq = Irq::new();
+        let stop = Arc::new(AtomicBool::new(true));
+        acknowledge(&[70]);
+        // Nothing is acknowledged once the flag is set.
+        irq.dispatch_until(&stop);
+        assert_eq!(GIC.with(|gic| gic.borrow().acks.len()), 1);
+        stop.store(false, Ordering::Relaxed);
+        let handler = stop.clone();
+        irq.register_boxed(70, Box::new(move || handler.store(true, Ordering::Release)));
+        acknowledge(&[70]);
+        clear_writes();
+        // Returns after the IRQ whose handler sets the flag.
+        irq.dispatch_until(&stop);
+        assert_eq!(writes(GICC_EOIR), [70]);
+        assert_eq!(GIC.with(|gic| gic.borrow().acks.len()), 1);
+    }
+
+    #[test]
+    fn split_eoi_order()
+    {
+        let irq = Irq::new();
+        irq.set_split_eoi(true);
+        assert_eq!(value(GICC_CTLR) & GICC_CTLR_EOIMODE_NS, GICC_CTLR_EOIMODE_NS);
+        // The priority must already be dropped, but the IRQ still active, while the
+        // handler runs.
+        irq.register_boxed(80,
+                           Box::new(|| {
+                               assert_eq!(writes(GICC_EOIR), [80]);
+                               assert!(writes(GICC_DIR).is_empty());
+                           }));
+        acknowledge(&[80]);
+        clear_writes();
+        irq.dispatch_once();
+        let order = GIC.with(|gic| {
+                           gic.borrow()
+                              .writes
+                              .iter()
+                              .filter(|(addr, _)| [GICC_EOIR as usize, GICC_DIR as usize].contains(addr))
+                              .copied()
+                              .collect::<Vec<_>>()
+                       });
+        assert_eq!(order, [(GICC_EOIR as usize, 80), (GICC_DIR as usize, 80)]);
+        assert_eq!(irq.count(80), 1);
+        irq.set_split_eoi(false);
+        assert_eq!(value(GICC_CTLR) & GICC_CTLR_EOIMODE_NS, 0);
+    }
+
+    #[test]
+    fn abandon_handler()
+    {
+        let irq = Irq::new();
+        irq.register(60, first);
+        irq.register(60, second);
+        clear_writes();
+        // Nested handlers can't be abandoned.
+        simulate_running(&irq, 60, first as fn() as usize, 2);
+        assert!(!irq.abandon_running());
+        assert_eq!(irq.handlers.rlock()[&60].len(), 2);
+        // Only the panicking handler is dropped and the IRQ is dismissed.
+        simulate_running(&irq, 60, first as fn() as usize, 1);
+        assert!(irq.abandon_running());
+        assert_eq!(irq.handlers.rlock()[&60][0] as usize, second as fn() as usize);
+        assert_eq!(irq.running[cpu_id()].depth.load(Ordering::Relaxed), 0);
+        assert_eq!(writes(GICC_EOIR), [60]);
+        assert!(writes(element(GICD_ICENABLER, 1)).is_empty());
+        // With split EOI the IRQ is deactivated instead, and disabled along with its
+        // last handler.
+        irq.set_split_eoi(true);
+        simulate_running(&irq, 60, second as fn() as usize, 1);
+        assert!(irq.abandon_running());
+        assert!(!irq.handlers.rlock().contains_key(&60));
+        assert_eq!(writes(GICC_DIR), [60]);
+        assert_eq!(writes(GICC_EOIR), [60]);
+        assert_eq!(writes(element(GICD_ICENABLER, 1)), [0x10000000]);
+    }
+
+    #[test]
+    fn abandon_closure()
+    {
+        let irq: &'static Irq = Box::leak(Box::new(Irq::new()));
+        let running = Arc::new(AtomicUsize::new(0));
+        let address = running.clone();
+        // Record the address that the closure is known by while it runs.
+        irq.register_boxed(61,
+                           Box::new(move || {
+                               let running = irq.running[cpu_id()].handler.load(Ordering::Relaxed);
+                               address.store(running, Ordering::Relaxed);
+                           }));
+        irq.register_boxed(61, Box::new(|| ()));
+        acknowledge(&[61]);
+        irq.dispatch_once();
+        clear_writes();
+        simulate_running(irq, 61, running.load(Ordering::Relaxed), 1);
+        assert!(irq.abandon_running());
+        assert_eq!(irq.closures.rlock()[&61].len(), 1);
+        assert_eq!(writes(GICC_EOIR), [61]);
+    }
+
+    /// Deferred work that only counts its calls.
+    fn deferred()
+    {
+        DEFERRED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Makes the simulated core look like it's running a handler.
+    ///
+    /// * `irq`: Driver to update.
+    /// * `val`: Value read from the acknowledge register.
+    /// * `handler`: Address of the handler.
+    /// * `depth`: Number of nested IRQs being handled.
+    fn simulate_running(irq: &Irq, val: u32, handler: usize, depth: u32)
+    {
+        let running = &irq.running[cpu_id()];
+        running.val.store(val, Ordering::Relaxed);
+        running.handler.store(handler, Ordering::Relaxed);
+        running.depth.store(depth, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn group_encoding()
+    {
+        assert_eq!(irq_bit(0), (0, 0x1));
+        assert_eq!(irq_bit(31), (0, 0x80000000));
+        assert_eq!(irq_bit(32), (1, 0x1));
+        assert_eq!(irq_bit(97), (3, 0x2));
+        assert_eq!(irq_bit(223), (6, 0x80000000));
+    }
+
+    #[test]
+    fn group()
+    {
+        let irq = Irq::new();
+        for num in [0, 31, 32, 97, 223] {
+            let (idx, bit) = irq_bit(num);
+            irq.set_group(num, 1);
+            assert_eq!(value(element(GICD_IGROUPR, idx)) & bit, bit);
+        }
+        assert_eq!(value(element(GICD_IGROUPR, 0)), 0x80000001);
+        assert_eq!(value(element(GICD_IGROUPR, 3)), 0x2);
+        irq.set_group(31, 0);
+        assert_eq!(value(element(GICD_IGROUPR, 0)), 0x1);
+        // The registers of other IRQs are left alone.
+        assert_eq!(value(element(GICD_IGROUPR, 1)), 0x1);
+        assert_eq!(value(element(GICD_IGROUPR, 6)), 0x80000000);
+    }
+
+    #[test]
+    fn priority_decoding()
+    {
+        assert_eq!(decode_rpr(0xFF), 0xFF);
+        assert_eq!(decode_rpr(0x78), 0x78);
+        assert_eq!(decode_rpr(0xFFFFFF80), 0x80);
+        assert_eq!(decode_hppir(0x25), Some(37));
+        assert_eq!(decode_hppir(0xC05), Some(5)); // SGI #5 sent by core #3.
+        assert_eq!(decode_hppir(0xDF), Some(223));
+        assert_eq!(decode_hppir(0xE0), None);
+        assert_eq!(decode_hppir(0x3FE), None);
+        assert_eq!(decode_hppir(0x3FF), None);
+        let irq = Irq::new();
+        unsafe {
+            write_reg(GICC_RPR.cast_mut(), 0x80);
+            write_reg(GICC_HPPIR.cast_mut(), 0xC05);
+        }
+        assert_eq!(irq.running_priority(), 0x80);
+        assert_eq!(irq.highest_pending(), Some(5));
+    }
+
+    /// Handler that does nothing, other than differing from [`second`].
+    fn first()
+    {
+        FIRST.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Handler that does nothing, other than differing from [`first`].
+    fn second()
+    {
+        SECOND.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Queues values to be returned by the acknowledge register of the
+    /// simulated controller.
+    ///
+    /// * `vals`: Values to queue.
+    fn acknowledge(vals: &[u32])
+    {
+        GIC.with(|gic| gic.borrow_mut().acks.extend(vals));
+    }
+
+    /// Forgets all the writes logged by the simulated controller so far.
+    fn clear_writes()
+    {
+        GIC.with(|gic| gic.borrow_mut().writes.clear());
+    }
+
+    /// Returns the last value written to the specified register of the
+    /// simulated controller.
+    ///
+    /// * `reg`: Register to query.
+    fn value<T>(reg: *mut T) -> u32
+    {
+        GIC.with(|gic| gic.borrow().regs.get(&(reg as usize)).copied().unwrap_or(0))
+    }
+
+    /// Returns the values written to the specified register of the simulated
+    /// controller in order.
+    ///
+    /// * `reg`: Register to query.
+    fn writes<T>(reg: *mut T) -> Vec<u32>
+    {
+        GIC.with(|gic| {
+               gic.borrow()
+                  .writes
+                  .iter()
+                  .filter(|(addr, _)| *addr == reg as usize)
+                  .map(|(_, val)| *val)
+                  .collect()
+           })
     }
 }
@@ -0,0 +1,268 @@
+//! Interrupt-safe logging.
+//!
+//! Writing to the UART takes a lock, which an interrupt handler could end up
+//! spinning on forever if it interrupted the lock holder on the same core.
+//! Instead, interrupt handlers format their messages into a lock-free ring of
+//! fixed size slots with [`irq_log`], and the main loop sends them to the UART
+//! later with [`flush`].
+
+use core::cell::UnsafeCell;
+use core::fmt::{Arguments, Result as FormatResult, Write};
+use core::str::from_utf8_unchecked;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of messages that can be waiting to be flushed.
+#[cfg(not(test))]
+const LOG_SLOTS: usize = 32;
+/// Maximum length of a message in bytes, with longer messages being truncated.
+const MSG_LEN: usize = 120;
+
+/// Global interrupt-safe log.
+#[cfg(not(test))]
+static LOG: LogRing<LOG_SLOTS> = LogRing::new();
+
+/// Empty message slot.
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: Slot = Slot { seq: AtomicUsize::new(0),
+                                msg: UnsafeCell::new(Message { buf: [0; MSG_LEN],
+                                                               len: 0 }) };
+
+/// Bounded lock-free ring of messages with any number of writers and a single
+/// flusher.
+#[derive(Debug)]
+struct LogRing<const N: usize>
+{
+    /// Message slots.
+    slots: [Slot; N],
+    /// Number of messages claimed by writers since creation, wrapping around.
+    tail: AtomicUsize,
+    /// Number of messages flushed since creation, wrapping around.
+    head: AtomicUsize,
+    /// Number of messages dropped because the ring was full.
+    dropped: AtomicUsize,
+}
+
+/// Message slot.
+#[derive(Debug)]
+struct Slot
+{
+    /// Sequence number of the slot minus its index, which is a multiple of the
+    /// ring's size while the slot is free and one more than that once a
+    /// message is published to it.
+    seq: AtomicUsize,
+    /// Message stored in the slot.
+    msg: UnsafeCell<Message>,
+}
+
+/// Formatted message.
+#[derive(Debug)]
+struct Message
+{
+    /// Message text.
+    buf: [u8; MSG_LEN],
+    /// Length of the text in bytes.
+    len: usize,
+}
+
+impl<const N: usize> LogRing<N>
+{
+    /// Creates and initializes a new empty ring.
+    ///
+    /// Returns the newly created ring.
+    const fn new() -> Self
+    {
+        Self { slots: [EMPTY_SLOT; N],
+               tail: AtomicUsize::new(0),
+               head: AtomicUsize::new(0),
+               dropped: AtomicUsize::new(0) }
+    }
+
+    /// Formats a message into a free slot.
+    ///
+    /// * `args`: Message to format.
+    ///
+    /// Returns whether the message was stored, as it's dropped when the ring
+    /// is full.
+    fn write(&self, args: Arguments) -> bool
+    {
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        let (slot, seq) = loop {
+            let idx = tail % N;
+            let slot = &self.slots[idx];
+            let seq = slot.seq.load(Ordering::Acquire).wrapping_add(idx);
+            if seq != tail {
+                // Either the slot still holds a message from the previous round, in
+                // which case the ring is full, or another writer claimed it first.
+                if (seq as isize).wrapping_sub(tail as isize) < 0 {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return false;
+                }
+                tail = self.tail.load(Ordering::Relaxed);
+                continue;
+            }
+            match self.tail
+                      .compare_exchange_weak(tail, tail.wrapping_add(1), Ordering::Relaxed, Ordering::Relaxed)
+            {
+                Ok(_) => break (slot, seq),
+                Err(current) => tail = current,
+            }
+        };
+        let msg = unsafe { &mut *slot.msg.get() };
+        msg.len = 0;
+        // Formatting errors only mean that the message was truncated.
+        msg.write_fmt(args).ok();
+        slot.seq
+            .store(seq.wrapping_add(1).wrapping_sub(tail % N), Ordering::Release);
+        true
+    }
+
+    /// Passes every published message to the provided function in the order
+    /// in which they were claimed, stopping at the first message still being
+    /// written.
+    ///
+    /// Must not be called by more than one context at a time.
+    ///
+    /// * `f`: Function to call with each message.
+    fn flush(&self, mut f: impl FnMut(&str))
+    {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            let idx = head % N;
+            let slot = &self.slots[idx];
+            let seq = slot.seq.load(Ordering::Acquire).wrapping_add(idx);
+            if seq != head.wrapping_add(1) {
+                break;
+            }
+            let msg = unsafe { &*slot.msg.get() };
+            // Messages are only ever written through `Write`, and truncation happens
+            // at character boundaries.
+            f(unsafe { from_utf8_unchecked(&msg.buf[.. msg.len]) });
+            slot.seq
+                .store(head.wrapping_add(N).wrapping_sub(idx), Ordering::Release);
+            head = head.wrapping_add(1);
+            self.head.store(head, Ordering::Relaxed);
+        }
+    }
+}
+
+unsafe impl<const N: usize> Sync for LogRing<N> {}
+
+impl Write for Message
+{
+    fn write_str(&mut self, text: &str) -> FormatResult
+    {
+        let room = MSG_LEN - self.len;
+        let mut len = text.len().min(room);
+        while !text.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len .. self.len + len].copy_from_slice(&text.as_bytes()[.. len]);
+        self.len += len;
+        if len < text.len() {
+            return Err(core::fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// Logs a message from any context, including interrupt handlers, without
+/// taking any locks.
+///
+/// * `args`: Message to log, truncated to a fixed length.
+///
+/// Returns whether the message was stored, as it's dropped if too many
+/// messages are waiting to be flushed.
+#[cfg(not(test))]
+pub fn irq_log(args: Arguments) -> bool
+{
+    LOG.write(args)
+}
+
+/// Passes every message logged so far to the provided function in order,
+/// which is meant to be called periodically from the main loop.
+///
+/// * `f`: Function to call with each message.
+#[cfg(not(test))]
+pub fn flush(f: impl FnMut(&str))
+{
+    LOG.flush(f)
+}
+
+/// Returns the number of messages dropped so far because too many were
+/// waiting to be flushed.
+#[cfg(not(test))]
+pub fn dropped_count() -> usize
+{
+    LOG.dropped.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::string::{String, ToString};
+    use std::thread;
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn log_ring_order()
+    {
+        let ring = LogRing::<4>::new();
+        let mut msgs = Vec::new();
+        for round in 0 .. 3 {
+            assert!(ring.write(format_args!("first {round}")));
+            assert!(ring.write(format_args!("second {round}")));
+            ring.flush(|msg| msgs.push(msg.to_string()));
+        }
+        assert_eq!(msgs,
+                   ["first 0", "second 0", "first 1", "second 1", "first 2", "second 2"]);
+    }
+
+    #[test]
+    fn log_ring_full()
+    {
+        let ring = LogRing::<2>::new();
+        assert!(ring.write(format_args!("a")));
+        assert!(ring.write(format_args!("b")));
+        assert!(!ring.write(format_args!("c")));
+        assert_eq!(ring.dropped.load(Ordering::Relaxed), 1);
+        let long = "x".repeat(MSG_LEN * 2);
+        let mut msgs = Vec::new();
+        ring.flush(|msg| msgs.push(msg.to_string()));
+        assert!(ring.write(format_args!("{long}")));
+        ring.flush(|msg| msgs.push(msg.to_string()));
+        assert_eq!(msgs, ["a", "b", &long[.. MSG_LEN]]);
+    }
+
+    #[test]
+    fn log_ring_writers()
+    {
+        const COUNT: usize = 1000;
+        let ring = LogRing::<8>::new();
+        let mut msgs = Vec::<String>::new();
+        thread::scope(|scope| {
+            for writer in 0 .. 2 {
+                let ring = &ring;
+                scope.spawn(move || {
+                         for val in 0 .. COUNT {
+                             while !ring.write(format_args!("{writer}:{val}")) {
+                                 thread::yield_now();
+                             }
+                         }
+                     });
+            }
+            while msgs.len() < COUNT * 2 {
+                ring.flush(|msg| msgs.push(msg.to_string()));
+                thread::yield_now();
+            }
+        });
+        for writer in 0 .. 2 {
+            let vals = msgs.iter()
+                           .filter_map(|msg| msg.strip_prefix(&std::format!("{writer}:")))
+                           .map(|val| val.parse::<usize>().unwrap())
+                           .collect::<Vec<_>>();
+            assert_eq!(vals, (0 .. COUNT).collect::<Vec<_>>());
+        }
+    }
+}
@@ -12,15 +12,16 @@
 #![feature(portable_simd)]
 
 mod alloc;
-#[cfg(not(test))]
+mod edid;
 mod irq;
+mod log;
 mod math;
-#[cfg(not(test))]
 mod mbox;
 #[cfg(not(test))]
 mod sched;
+mod smp;
 mod sync;
-#[cfg(not(test))]
+mod time;
 mod touch;
 #[cfg(not(test))]
 mod uart;
@@ -33,7 +34,6 @@ use core::arch::{asm, global_asm};
 use core::f32::consts::FRAC_PI_2;
 #[cfg(not(test))]
 use core::fmt::Write;
-#[cfg(not(test))]
 use core::ops::Range;
 #[cfg(not(test))]
 use core::panic::PanicInfo;
@@ -57,16 +57,13 @@ use self::video::{Triangle, VIDEO};
 #[cfg(not(test))]
 const HEAP_RANGE: Range<usize> = 0x40000000 .. 0x80000000 - (32 << 20);
 /// DMA RANGE.
-#[cfg(not(test))]
 const DMA_RANGE: Range<usize> = 0x1000 .. 0x80000;
 /// Peripherals range.
 #[cfg(not(test))]
 const PERRY_RANGE: Range<usize> = 0x80000000 .. 0x84000000;
 /// Video core reserved range.
-#[cfg(not(test))]
 const VC_RANGE: Range<usize> = 0x84000000 .. 0x86000000;
 /// Logical CPU count.
-#[cfg(not(test))]
 const CPU_COUNT: usize = 4;
 /// Software generated IRQ that halts the system.
 #[cfg(not(test))]
@@ -81,6 +78,7 @@ global_asm!(include_str!("boot.s"));
 pub extern "C" fn start() -> !
 {
     let affinity = cpu_id();
+    smp::set_online();
     debug!("Booted core #{affinity}");
     if affinity == 0 {
         IRQ.register(HALT_IRQ, || halt());
@@ -100,8 +98,11 @@ async fn ticker() -> !
     let mut rot = Quaternion::default();
     let scale = Scalar::default();
     let mut recog = Recognizer::new();
+    let mut last = time::now();
     loop {
-        recog.sample();
+        let now = time::now();
+        recog.sample(now - last);
+        last = now;
         let vec0 = Vector::from_components(0.0, 0.0, 1.0);
         let vec1 = recog.translated();
         let axis = vec0.cross(vec0 + vec1);
@@ -111,6 +112,7 @@ async fn ticker() -> !
         let mdl = Matrix::from_components(pos, rot, scale);
         VIDEO.enqueue(tri.geom(), mdl, cam, proj);
         VIDEO.commit().await;
+        log::flush(|msg| debug!("{msg}"));
     }
 }
 
@@ -201,6 +203,8 @@ fn panic(info: &PanicInfo) -> !
     uart.write_char('\n').unwrap();
     drop(uart);
     backtrace();
+    // Keep servicing IRQs if only an IRQ handler is at fault.
+    IRQ.isolate_panic();
     IRQ.trigger(HALT_IRQ);
     halt();
 }
@@ -209,15 +213,7 @@ fn panic(info: &PanicInfo) -> !
 #[cfg(not(test))]
 fn cpu_id() -> usize
 {
-    let id: usize;
-    unsafe {
-        asm!(
-            "mrs {id}, mpidr_el1",
-            "and {id}, {id}, #0xff",
-            id = out (reg) id,
-            options (nomem, nostack, preserves_flags));
-    }
-    id
+    smp::current_core() as usize
 }
 
 /// Sends the return addresses of all the function calls from this function all
@@ -0,0 +1,109 @@
+//! Bounding box math.
+//!
+//! Provides an axis-aligned bounding box for hit-testing.  Boxes with both
+//! bounds on the Z plane behave as 2D rectangles for points on that plane.
+
+use core::simd::SimdPartialOrd;
+
+use super::*;
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb
+{
+    /// Lower bound of every component.
+    pub(super) min: Vector,
+    /// Upper bound of every component.
+    pub(super) max: Vector,
+}
+
+impl Aabb
+{
+    /// Creates and initializes a new bounding box from two opposite corners.
+    ///
+    /// * `min`: Corner with the smallest components.
+    /// * `max`: Corner with the largest components.
+    ///
+    /// Returns the newly created bounding box.
+    pub fn new(min: Vector, max: Vector) -> Self
+    {
+        Self { min, max }
+    }
+
+    /// Checks whether a point is inside or on the boundary of this box.
+    ///
+    /// * `point`: Point to test.
+    ///
+    /// Returns whether the point is contained.
+    pub fn contains(self, point: Vector) -> bool
+    {
+        let above = point.vec.simd_ge(self.min.vec);
+        let below = point.vec.simd_le(self.max.vec);
+        (above & below).all()
+    }
+
+    /// Checks whether this box overlaps or touches another box.
+    ///
+    /// * `other`: Box to test against.
+    ///
+    /// Returns whether the boxes intersect.
+    pub fn intersects(self, other: Self) -> bool
+    {
+        let above = self.max.vec.simd_ge(other.min.vec);
+        let below = self.min.vec.simd_le(other.max.vec);
+        (above & below).all()
+    }
+
+    /// Grows this box to include a point.
+    ///
+    /// * `point`: Point to include.
+    ///
+    /// Returns the resulting bounding box.
+    pub fn expand(self, point: Vector) -> Self
+    {
+        Self { min: self.min.min(point),
+               max: self.max.max(point) }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn aabb_contains()
+    {
+        let aabb = Aabb::new(Vector::from_components(-1.0, -0.5, 0.0),
+                             Vector::from_components(1.0, 0.5, 0.0));
+        assert!(aabb.contains(Vector::from_components(0.0, 0.0, 0.0)));
+        assert!(aabb.contains(Vector::from_components(-1.0, -0.5, 0.0)));
+        assert!(aabb.contains(Vector::from_components(1.0, 0.5, 0.0)));
+        assert!(!aabb.contains(Vector::from_components(1.001, 0.0, 0.0)));
+        assert!(!aabb.contains(Vector::from_components(0.0, -0.501, 0.0)));
+    }
+
+    #[test]
+    fn aabb_intersects()
+    {
+        let aabb = Aabb::new(Vector::from_components(0.0, 0.0, 0.0),
+                             Vector::from_components(1.0, 1.0, 0.0));
+        let overlap = Aabb::new(Vector::from_components(0.5, 0.5, 0.0),
+                                Vector::from_components(2.0, 2.0, 0.0));
+        let disjoint = Aabb::new(Vector::from_components(1.5, 0.0, 0.0),
+                                 Vector::from_components(2.0, 1.0, 0.0));
+        assert!(aabb.intersects(overlap));
+        assert!(overlap.intersects(aabb));
+        assert!(!aabb.intersects(disjoint));
+        assert!(!disjoint.intersects(aabb));
+    }
+
+    #[test]
+    fn aabb_expand()
+    {
+        let origin = Vector::from_components(0.0, 0.0, 0.0);
+        let aabb = Aabb::new(origin, origin).expand(Vector::from_components(-1.0, 2.0, 0.0));
+        assert!(aabb.contains(Vector::from_components(-0.5, 1.0, 0.0)));
+        assert!(!aabb.contains(Vector::from_components(0.5, 1.0, 0.0)));
+    }
+}
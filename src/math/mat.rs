@@ -21,6 +21,56 @@ pub struct Matrix
 
 impl Matrix
 {
+    /// Creates and initializes a new identity matrix.
+    ///
+    /// Returns the newly created matrix.
+    pub fn identity() -> Self
+    {
+        let mat0 = f32x4::from([1.0, 0.0, 0.0, 0.0]);
+        let mat1 = f32x4::from([0.0, 1.0, 0.0, 0.0]);
+        let mat2 = f32x4::from([0.0, 0.0, 1.0, 0.0]);
+        let mat3 = f32x4::from([0.0, 0.0, 0.0, 1.0]);
+        let mat = [mat0, mat1, mat2, mat3];
+        Self { transform: mat,
+               rot: mat }
+    }
+
+    /// Creates and initializes a new rotation matrix from a quaternion.
+    ///
+    /// * `rot`: Rotation quaternion.
+    ///
+    /// Returns the newly created matrix.
+    pub fn from_quaternion(rot: Quaternion) -> Self
+    {
+        Self::from_components(Vector::default(), rot, Scalar::default())
+    }
+
+    /// Creates and initializes a new translation matrix.
+    ///
+    /// * `pos`: Translation vector.
+    ///
+    /// Returns the newly created matrix.
+    pub fn from_translation(pos: Vector) -> Self
+    {
+        let mut this = Self::identity();
+        this.transform[3] += pos.vec;
+        this
+    }
+
+    /// Creates and initializes a new uniform scale matrix.
+    ///
+    /// * `scale`: Scale scalar.
+    ///
+    /// Returns the newly created matrix.
+    pub fn from_scale(scale: Scalar) -> Self
+    {
+        let mut this = Self::identity();
+        this.transform[0] *= scale.val;
+        this.transform[1] *= scale.val;
+        this.transform[2] *= scale.val;
+        this
+    }
+
     /// Creates and initializes a new matrix from the provided components.
     ///
     /// * `pos`: Translation vector.
@@ -50,6 +100,132 @@ impl Matrix
         Self { transform, rot }
     }
 
+    /// Creates and initializes a new view matrix for a camera at the specified
+    /// position looking at a target.
+    ///
+    /// The view space is right-handed, with the camera looking down the
+    /// negative Z axis and the Y axis pointing up, matching the conventions of
+    /// [`Matrix::perspective`].
+    ///
+    /// * `eye`: Position of the camera.
+    /// * `target`: Point that the camera looks at, which must not be the eye.
+    /// * `up`: Upward direction, which must not be parallel to the view
+    ///   direction.
+    ///
+    /// Returns the newly created matrix.
+    pub fn look_at(eye: Vector, target: Vector, up: Normal) -> Self
+    {
+        let fwd = normalize(target.vec - eye.vec);
+        let side = normalize(cross(fwd, up.vec));
+        let up = cross(side, fwd);
+        let rot0 = f32x4::from([side[0], up[0], -fwd[0], 0.0]);
+        let rot1 = f32x4::from([side[1], up[1], -fwd[1], 0.0]);
+        let rot2 = f32x4::from([side[2], up[2], -fwd[2], 0.0]);
+        let rot3 = f32x4::from([0.0, 0.0, 0.0, 1.0]);
+        let rot = [rot0, rot1, rot2, rot3];
+        let mut transform = rot;
+        transform[3] = f32x4::from([-dot(side, eye.vec), -dot(up, eye.vec), dot(fwd, eye.vec), 1.0]);
+        Self { transform, rot }
+    }
+
+    /// Creates and initializes a new perspective projection matrix.
+    ///
+    /// The projection is right-handed, with the camera looking down the
+    /// negative Z axis, and maps depth to the [0, 1] range of clip space, with
+    /// the near plane at 0 and the far plane at 1, just like [`Projector`].
+    ///
+    /// * `fov_y`: Vertical field of view in radians.
+    /// * `aspect`: Width divided by height of the viewport.
+    /// * `near`: Distance to the near clipping plane.
+    /// * `far`: Distance to the far clipping plane.
+    ///
+    /// Returns the newly created matrix.
+    pub fn perspective(fov_y: Scalar, aspect: Scalar, near: Scalar, far: Scalar) -> Self
+    {
+        let (near, far) = (near.val[0], far.val[0]);
+        let scale = (fov_y.val[0] * 0.5).tan().recip();
+        let invrange = (near - far).recip();
+        let mat0 = f32x4::from([scale / aspect.val[0], 0.0, 0.0, 0.0]);
+        let mat1 = f32x4::from([0.0, scale, 0.0, 0.0]);
+        let mat2 = f32x4::from([0.0, 0.0, far * invrange, -1.0]);
+        let mat3 = f32x4::from([0.0, 0.0, near * far * invrange, 0.0]);
+        let mut this = Self::identity();
+        this.transform = [mat0, mat1, mat2, mat3];
+        this
+    }
+
+    /// Creates and initializes a new orthographic projection matrix.
+    ///
+    /// Follows the same conventions as [`Matrix::perspective`], mapping the
+    /// specified box to [-1, 1] horizontally and vertically and to [0, 1] in
+    /// depth.
+    ///
+    /// * `left`: Left clipping plane.
+    /// * `right`: Right clipping plane.
+    /// * `bottom`: Bottom clipping plane.
+    /// * `top`: Top clipping plane.
+    /// * `near`: Distance to the near clipping plane.
+    /// * `far`: Distance to the far clipping plane.
+    ///
+    /// Returns the newly created matrix.
+    pub fn orthographic(left: Scalar, right: Scalar, bottom: Scalar, top: Scalar, near: Scalar, far: Scalar) -> Self
+    {
+        let (left, right) = (left.val[0], right.val[0]);
+        let (bottom, top) = (bottom.val[0], top.val[0]);
+        let (near, far) = (near.val[0], far.val[0]);
+        let xrange = (right - left).recip();
+        let yrange = (top - bottom).recip();
+        let zrange = (near - far).recip();
+        let mat0 = f32x4::from([2.0 * xrange, 0.0, 0.0, 0.0]);
+        let mat1 = f32x4::from([0.0, 2.0 * yrange, 0.0, 0.0]);
+        let mat2 = f32x4::from([0.0, 0.0, zrange, 0.0]);
+        let mat3 = f32x4::from([-(right + left) * xrange, -(top + bottom) * yrange, near * zrange, 1.0]);
+        let mut this = Self::identity();
+        this.transform = [mat0, mat1, mat2, mat3];
+        this
+    }
+
+    /// Projects a vector through this matrix, performing the perspective
+    /// division.
+    ///
+    /// * `vec`: Vector to project.
+    ///
+    /// Returns the resulting vector in normalized coordinates, with the
+    /// original W component preserved in the last lane.
+    pub fn project(self, vec: Vector) -> ProjectedVector
+    {
+        let mut vec = vec.vec;
+        vec[3] = 1.0;
+        vec = mat_vec_mul(self.transform, vec);
+        let mut w = f32x4::splat(vec[3].recip());
+        w[3] = 1.0;
+        vec *= w;
+        ProjectedVector { vec }
+    }
+
+    /// Computes the inverse of this matrix, supporting any transformation
+    /// including projections.
+    ///
+    /// Unlike [`Matrix::recip`], this works for non-rigid transformations at
+    /// the cost of a general 4x4 inversion.
+    ///
+    /// Returns the computed inverse, or nothing if this matrix is singular.
+    pub fn inverse(self) -> Option<Self>
+    {
+        let transform = mat_inverse(self.transform)?;
+        let rot = mat_inverse(self.rot)?;
+        Some(Self { transform, rot })
+    }
+
+    /// Computes the transpose of this matrix.
+    ///
+    /// Returns the computed transpose.
+    pub fn transpose(self) -> Self
+    {
+        Self { transform: mat_transpose(self.transform),
+               rot: mat_transpose(self.rot) }
+    }
+
     /// Computes the reciprocal or inverse of this matrix.
     ///
     /// Returns the computed reciprocal.
@@ -101,6 +277,16 @@ impl Mul<Vector> for Matrix
     }
 }
 
+impl Mul<Vector4> for Matrix
+{
+    type Output = Vector4;
+
+    fn mul(self, other: Vector4) -> Vector4
+    {
+        Vector4 { vec: mat_vec_mul(self.transform, other.vec) }
+    }
+}
+
 impl Mul<Normal> for Matrix
 {
     type Output = Normal;
@@ -124,13 +310,7 @@ impl Default for Matrix
 {
     fn default() -> Self
     {
-        let mat0 = f32x4::from([1.0, 0.0, 0.0, 0.0]);
-        let mat1 = f32x4::from([0.0, 1.0, 0.0, 0.0]);
-        let mat2 = f32x4::from([0.0, 0.0, 1.0, 0.0]);
-        let mat3 = f32x4::from([0.0, 0.0, 0.0, 1.0]);
-        let mat = [mat0, mat1, mat2, mat3];
-        Self { transform: mat,
-               rot: mat }
+        Self::identity()
     }
 }
 
@@ -232,6 +412,144 @@ mod tests
         assert!(is_roughly(point.vec, f32x4::from([1.0, 0.0, 3.0, 0.0])));
     }
 
+    #[test]
+    fn matrix_identity()
+    {
+        let pos = Vector::from_components(1.0, 2.0, 3.0);
+        let axis = Vector::from_components(1.0, 1.0, -1.0);
+        let angle = Angle::from_radians(FRAC_PI_3 * 2.0);
+        let rot = Quaternion::from_axis_angle(axis, angle);
+        let scale = Scalar::from_val(2.0);
+        let transform = Matrix::from_components(pos, rot, scale);
+        let lhs = Matrix::identity() * transform;
+        let rhs = transform * Matrix::identity();
+        for (lhs, rhs) in lhs.transform.iter().zip(rhs.transform.iter()) {
+            assert!(is_roughly(*lhs, *rhs));
+        }
+        for (lhs, expected) in lhs.transform.iter().zip(transform.transform.iter()) {
+            assert!(is_roughly(*lhs, *expected));
+        }
+        let point = Vector::from_components(1.0, 2.0, 3.0);
+        let point = Matrix::identity() * point;
+        assert!(is_roughly(point.vec, f32x4::from([1.0, 2.0, 3.0, 0.0])));
+    }
+
+    #[test]
+    fn matrix_general_inverse()
+    {
+        let pos = Vector::from_components(1.0, 2.0, 3.0);
+        let axis = Vector::from_components(1.0, 1.0, -1.0);
+        let angle = Angle::from_radians(FRAC_PI_3 * 2.0);
+        let rot = Quaternion::from_axis_angle(axis, angle);
+        let transform = Matrix::from_components(pos, rot, Scalar::from_val(2.0));
+        let identity = Matrix::identity();
+        let res = transform.inverse().unwrap() * transform;
+        for (actual, expected) in res.transform.iter().zip(identity.transform.iter()) {
+            assert!(is_roughly(*actual, *expected));
+        }
+        let proj = Matrix::perspective(Scalar::from_val(FRAC_PI_2),
+                                       Scalar::from_val(1.5),
+                                       Scalar::from_val(0.1),
+                                       Scalar::from_val(100.0));
+        let res = proj * proj.inverse().unwrap();
+        for (actual, expected) in res.transform.iter().zip(identity.transform.iter()) {
+            assert!(is_roughly(*actual, *expected));
+        }
+        assert!(Matrix::from_scale(Scalar::from_val(0.0)).inverse().is_none());
+    }
+
+    #[test]
+    fn matrix_transpose()
+    {
+        let pos = Vector::from_components(1.0, 2.0, 3.0);
+        let transform = Matrix::from_translation(pos).transpose();
+        assert!(is_roughly(transform.transform[0], f32x4::from([1.0, 0.0, 0.0, 1.0])));
+        assert!(is_roughly(transform.transform[3], f32x4::from([0.0, 0.0, 0.0, 1.0])));
+        let transform = transform.transpose();
+        assert!(is_roughly(transform.transform[3], f32x4::from([1.0, 2.0, 3.0, 1.0])));
+    }
+
+    #[test]
+    fn matrix_look_at()
+    {
+        let eye = Vector::from_components(1.0, 2.0, 3.0);
+        let target = Vector::from_components(4.0, 2.0, 3.0);
+        let up = Vector::from_components(0.0, 1.0, 0.0).normalized();
+        let view = Matrix::look_at(eye, target, up);
+        assert!(is_roughly((view * eye).vec, f32x4::splat(0.0)));
+        assert!(is_roughly((view * target).vec, f32x4::from([0.0, 0.0, -3.0, 0.0])));
+        let above = Vector::from_components(1.0, 3.0, 3.0);
+        assert!(is_roughly((view * above).vec, f32x4::from([0.0, 1.0, 0.0, 0.0])));
+        let right = Vector::from_components(1.0, 2.0, 4.0);
+        assert!(is_roughly((view * right).vec, f32x4::from([1.0, 0.0, 0.0, 0.0])));
+    }
+
+    #[test]
+    fn matrix_from_quaternion()
+    {
+        let axis = Vector::from_components(0.0, 0.0, 1.0);
+        let angle = Angle::from_radians(FRAC_PI_2);
+        let rot = Quaternion::from_axis_angle(axis, angle);
+        let transform = Matrix::from_quaternion(rot);
+        assert!(is_roughly(transform.transform[0], f32x4::from([0.0, 1.0, 0.0, 0.0])));
+        assert!(is_roughly(transform.transform[1], f32x4::from([-1.0, 0.0, 0.0, 0.0])));
+        assert!(is_roughly(transform.transform[2], f32x4::from([0.0, 0.0, 1.0, 0.0])));
+        assert!(is_roughly(transform.transform[3], f32x4::from([0.0, 0.0, 0.0, 1.0])));
+        let rot = Quaternion::from_axis_angle(axis, Angle::from_radians(-FRAC_PI_2));
+        let transform = transform * Matrix::from_quaternion(rot);
+        assert!(is_roughly(transform.transform[0], f32x4::from([1.0, 0.0, 0.0, 0.0])));
+        assert!(is_roughly(transform.transform[1], f32x4::from([0.0, 1.0, 0.0, 0.0])));
+        assert!(is_roughly(transform.transform[2], f32x4::from([0.0, 0.0, 1.0, 0.0])));
+    }
+
+    #[test]
+    fn matrix_translation_scale()
+    {
+        let pos = Vector::from_components(1.0, 2.0, 3.0);
+        let scale = Scalar::from_val(2.0);
+        let transform = Matrix::from_translation(pos) * Matrix::from_scale(scale);
+        let point = Vector::from_components(1.0, 1.0, 1.0);
+        let point = transform * point;
+        assert!(is_roughly(point.vec, f32x4::from([3.0, 4.0, 5.0, 0.0])));
+    }
+
+    #[test]
+    fn matrix_perspective()
+    {
+        let fov = Scalar::from_val(FRAC_PI_2);
+        let aspect = Scalar::from_val(2.0);
+        let proj = Matrix::perspective(fov, aspect, Scalar::from_val(0.5), Scalar::from_val(2.0));
+        let point = Vector::from_components(0.0, 0.0, -0.5);
+        let res = proj.project(point);
+        assert!(is_roughly(res.vec, f32x4::from([0.0, 0.0, 0.0, 0.5])));
+        let point = Vector::from_components(1.0, 0.5, -0.5);
+        let res = proj.project(point);
+        assert!(is_roughly(res.vec, f32x4::from([1.0, 1.0, 0.0, 0.5])));
+        let point = Vector::from_components(-4.0, -2.0, -2.0);
+        let res = proj.project(point);
+        assert!(is_roughly(res.vec, f32x4::from([-1.0, -1.0, 1.0, 2.0])));
+    }
+
+    #[test]
+    fn matrix_orthographic()
+    {
+        let proj = Matrix::orthographic(Scalar::from_val(-2.0),
+                                        Scalar::from_val(2.0),
+                                        Scalar::from_val(-1.0),
+                                        Scalar::from_val(1.0),
+                                        Scalar::from_val(0.0),
+                                        Scalar::from_val(4.0));
+        let point = Vector::from_components(0.0, 0.0, 0.0);
+        let res = proj.project(point);
+        assert!(is_roughly(res.vec, f32x4::from([0.0, 0.0, 0.0, 1.0])));
+        let point = Vector::from_components(2.0, -1.0, -4.0);
+        let res = proj.project(point);
+        assert!(is_roughly(res.vec, f32x4::from([1.0, -1.0, 1.0, 1.0])));
+        let point = Vector::from_components(-1.0, 0.5, -2.0);
+        let res = proj.project(point);
+        assert!(is_roughly(res.vec, f32x4::from([-0.5, 0.5, 0.5, 1.0])));
+    }
+
     #[test]
     fn matrix_inverse()
     {
@@ -1,5 +1,6 @@
 //! Linear algebra math.
 
+mod aabb;
 mod angle;
 #[cfg(not(test))]
 mod color;
@@ -7,9 +8,11 @@ mod mat;
 mod normal;
 mod proj;
 mod quat;
+mod ray;
 mod scalar;
 mod triang;
 mod vec;
+mod vec4;
 
 #[cfg(not(test))]
 use core::arch::asm;
@@ -19,6 +22,7 @@ use core::f32::consts::{FRAC_PI_2, PI};
 use core::simd::SimdPartialOrd;
 use core::simd::{f32x4, SimdFloat};
 
+pub use self::aabb::*;
 pub use self::angle::*;
 #[cfg(not(test))]
 pub use self::color::*;
@@ -26,9 +30,35 @@ pub use self::mat::*;
 pub use self::normal::*;
 pub use self::proj::*;
 pub use self::quat::*;
+#[cfg(not(test))]
+pub use self::ray::*;
 pub use self::scalar::*;
 pub use self::triang::*;
 pub use self::vec::*;
+pub use self::vec4::*;
+
+/// Smallest absolute pivot that a matrix can have without being considered
+/// singular.
+const SINGULAR_THRESHOLD: f32 = 1.0 / 1048576.0;
+
+/// Number of decimal places printed by the [`Display`](core::fmt::Display)
+/// implementations unless a precision is specified.
+const DISPLAY_PRECISION: usize = 3;
+
+/// Approximate equality comparison for types subject to floating point
+/// error.
+pub trait ApproxEq: Copy + Sized
+{
+    /// Checks whether this value and another differ by at most the specified
+    /// epsilon in every component.
+    ///
+    /// * `other`: Value to compare against.
+    /// * `epsilon`: Maximum absolute difference per component, with 1/4096
+    ///   being a good default for values close to unit length.
+    ///
+    /// Returns whether both values are approximately equal.
+    fn approx_eq(self, other: Self, epsilon: Scalar) -> bool;
+}
 
 #[cfg(not(test))]
 pub trait SoftFloatOps: Copy + Sized
@@ -61,6 +91,19 @@ pub trait SoftFloatOps: Copy + Sized
     /// Returns the computed inverse cosine.
     fn acos(self) -> Self;
 
+    /// Computes an approximation of the inverse sine of this value.
+    ///
+    /// Returns the computed inverse sine.
+    fn asin(self) -> Self;
+
+    /// Computes an approximation of the four quadrant inverse tangent of this
+    /// value and another.
+    ///
+    /// * `other`: X coordinate, with this value being the Y coordinate.
+    ///
+    /// Returns the computed angle in the [-pi, pi] range.
+    fn atan2(self, other: Self) -> Self;
+
     /// Computes the square root of this value.
     ///
     /// Returns the computed square root.
@@ -117,6 +160,43 @@ impl SoftFloatOps for f32
         res - FRAC_PI_2
     }
 
+    fn asin(self) -> Self
+    {
+        self.atan2((1.0 - self * self).sqrt())
+    }
+
+    fn atan2(self, other: Self) -> Self
+    {
+        if other == 0.0 {
+            return if self > 0.0 {
+                FRAC_PI_2
+            } else if self < 0.0 {
+                -FRAC_PI_2
+            } else {
+                0.0
+            };
+        }
+        // Formula: atan(z) = z*(a+z^2*(b+z^2*(c+z^2*(d+z^2*e)))) for z in [-1, 1],
+        // with atan(z) = pi/2-atan(1/z) outside of that range.
+        let (yabs, xabs) = (if self < 0.0 { -self } else { self }, if other < 0.0 { -other } else { other });
+        let swap = yabs > xabs;
+        let z = if swap { other / self } else { self / other };
+        let sq = z * z;
+        let mut res = 0.0208351;
+        res = res * sq - 0.085133;
+        res = res * sq + 0.180141;
+        res = res * sq - 0.3302995;
+        res = res * sq + 0.999866;
+        res *= z;
+        if swap {
+            res = if z < 0.0 { -FRAC_PI_2 - res } else { FRAC_PI_2 - res };
+        }
+        if other < 0.0 {
+            res += if self < 0.0 { -PI } else { PI };
+        }
+        res
+    }
+
     fn sqrt(self) -> Self
     {
         let res: Self;
@@ -127,13 +207,26 @@ impl SoftFloatOps for f32
     }
 }
 
+/// Checks whether all the components of two vectors differ by at most the
+/// specified epsilon.
+///
+/// * `lhs`: Left hand side vector.
+/// * `rhs`: Right hand side vector.
+/// * `epsilon`: Maximum absolute difference per component.
+///
+/// Returns whether the vectors are approximately equal.
+#[inline]
+fn approx_eq(lhs: f32x4, rhs: f32x4, epsilon: f32x4) -> bool
+{
+    (lhs - rhs).abs().reduce_max() <= epsilon[0]
+}
+
 /// Computes the dot product between two vectors.
 ///
 /// * `lhs`: Left hand side vector.
 /// * `rhs`: Right hand side vector.
 ///
 /// Returns the computed dot product.
-#[cfg(not(test))]
 #[inline]
 fn dot(lhs: f32x4, rhs: f32x4) -> f32
 {
@@ -146,12 +239,11 @@ fn dot(lhs: f32x4, rhs: f32x4) -> f32
 /// * `rhs`: Right hand side vector.
 ///
 /// Returns the resulting vector.
-#[cfg(not(test))]
 #[inline]
 fn cross(lhs: f32x4, rhs: f32x4) -> f32x4
 {
     let x = lhs[1] * rhs[2] - lhs[2] * rhs[1];
-    let y = lhs[0] * rhs[2] - lhs[2] * rhs[0];
+    let y = lhs[2] * rhs[0] - lhs[0] * rhs[2];
     let z = lhs[0] * rhs[1] - lhs[1] * rhs[0];
     f32x4::from([x, y, z, 0.0])
 }
@@ -173,7 +265,6 @@ fn normalize(vec: f32x4) -> f32x4
 /// * `rhs`: Right hand side vector.
 ///
 /// Returns the resulting squared distance.
-#[cfg(not(test))]
 #[inline]
 fn sq_dist(lhs: f32x4, rhs: f32x4) -> f32
 {
@@ -218,6 +309,62 @@ fn mat_mul(lhs: [f32x4; 4], rhs: [f32x4; 4]) -> [f32x4; 4]
     [res0, res1, res2, res3]
 }
 
+/// Computes the inverse of a matrix using Gauss-Jordan elimination with
+/// partial pivoting.
+///
+/// * `mat`: Matrix to invert.
+///
+/// Returns the resulting matrix, or nothing if the matrix is singular.
+fn mat_inverse(mat: [f32x4; 4]) -> Option<[f32x4; 4]>
+{
+    let abs = |val: f32| if val < 0.0 { -val } else { val };
+    let mut mat = mat;
+    let mut res = [f32x4::from([1.0, 0.0, 0.0, 0.0]),
+                   f32x4::from([0.0, 1.0, 0.0, 0.0]),
+                   f32x4::from([0.0, 0.0, 1.0, 0.0]),
+                   f32x4::from([0.0, 0.0, 0.0, 1.0])];
+    for col in 0 .. 4 {
+        let pivot = (col .. 4).fold(col, |best, row| {
+                                  if abs(mat[row][col]) > abs(mat[best][col]) {
+                                      row
+                                  } else {
+                                      best
+                                  }
+                              });
+        if abs(mat[pivot][col]) < SINGULAR_THRESHOLD {
+            return None;
+        }
+        mat.swap(col, pivot);
+        res.swap(col, pivot);
+        let recip = f32x4::splat(mat[col][col].recip());
+        mat[col] *= recip;
+        res[col] *= recip;
+        for row in (0 .. 4).filter(|row| *row != col) {
+            let factor = f32x4::splat(mat[row][col]);
+            mat[row] -= mat[col] * factor;
+            res[row] -= res[col] * factor;
+        }
+    }
+    Some(res)
+}
+
+/// Computes the transpose of a matrix.
+///
+/// * `mat`: Matrix to transpose.
+///
+/// Returns the resulting matrix.
+#[inline]
+fn mat_transpose(mat: [f32x4; 4]) -> [f32x4; 4]
+{
+    let mut res = mat;
+    for row in 0 .. 4 {
+        for col in 0 .. 4 {
+            res[row][col] = mat[col][row];
+        }
+    }
+    res
+}
+
 /// Computes the product of a matrix and a vector.
 ///
 /// * `mat`: Matrix to multiply the vector by.
@@ -16,7 +16,6 @@ pub struct Normal
     pub(super) weight: f32x4,
 }
 
-#[cfg(not(test))]
 impl Normal
 {
     /// Creates and initializes a new normal from a regular vector.
@@ -31,6 +30,15 @@ impl Normal
     }
 }
 
+impl ApproxEq for Normal
+{
+    /// Only compares directions, ignoring the weights of the normals.
+    fn approx_eq(self, other: Self, epsilon: Scalar) -> bool
+    {
+        approx_eq(self.vec, other.vec, epsilon.val)
+    }
+}
+
 impl Add<Self> for Normal
 {
     type Output = Self;
@@ -60,3 +68,20 @@ impl Mul<Scalar> for Normal
                weight: self.weight * other.val }
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn normal_approx_eq()
+    {
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let normal = Normal::from_vec(Vector::from_components(1.0, 1.0, 0.0));
+        let other = Normal::from_vec(Vector::from_components(2.0, 2.0, 0.0)) * Scalar::from_val(2.0);
+        assert!(normal.approx_eq(other, epsilon));
+        let other = Normal::from_vec(Vector::from_components(1.0, -1.0, 0.0));
+        assert!(!normal.approx_eq(other, epsilon));
+    }
+}
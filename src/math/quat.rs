@@ -3,10 +3,27 @@
 //! Provides a packed and non-redundant 3D transformation.
 
 use core::default::Default;
+use core::f32::consts::{FRAC_PI_2, PI};
+use core::fmt::{Display, Formatter, Result as FormatResult};
 use core::ops::{Mul, MulAssign};
 
 use super::*;
 
+/// Cosine of the angle between two quaternions above which spherical
+/// interpolation falls back to normalized linear interpolation.
+const SLERP_THRESHOLD: f32 = 0.9995;
+/// Absolute sine of the pitch above which Euler angle extraction treats the
+/// rotation as gimbal locked.
+const GIMBAL_LOCK_THRESHOLD: f32 = 0.9999;
+/// Maximum deviation from one of the squared length of a quaternion for it to
+/// be considered a unit quaternion.
+const UNIT_TOLERANCE: f32 = 1.0 / 4096.0;
+/// Squared length below which a quaternion is considered degenerate.
+const DEGENERATE_SQ_LEN: f32 = 1.0e-12;
+/// Sine of the half angle below which a rotation is considered too small to
+/// have a meaningful axis.
+const AXIS_THRESHOLD: f32 = 1.0e-6;
+
 /// Rotation quaternion.
 #[derive(Clone, Copy, Debug)]
 pub struct Quaternion
@@ -43,7 +60,6 @@ impl Quaternion
     /// * `old`: Old orientation.
     ///
     /// Returns the resulting quaternion.
-    #[cfg(not(test))]
     pub fn from_normals(old: Normal, new: Normal) -> Self
     {
         let cos = dot(old.vec, new.vec);
@@ -52,17 +68,224 @@ impl Quaternion
             // Not possible to determine the axis of rotation, so assume no rotation.
             return Self { quat: f32x4::from([0.0, 0.0, 0.0, 1.0]) };
         }
-        let angle = cos.acos() * 0.5;
+        // The length of the cross product is the sine of the angle, which keeps small
+        // angles accurate and normalizes the axis.
+        let len = sq_len(axis).sqrt();
+        let angle = len.atan2(cos) * 0.5;
         let (sin, cos) = angle.sin_cos();
-        let axis = axis * f32x4::splat(sin);
+        let axis = axis * f32x4::splat(sin / len);
         Self { quat: f32x4::from([axis[0], axis[1], axis[2], cos]) }
     }
+
+    /// Decomposes this quaternion into a rotation axis and angle.
+    ///
+    /// Returns the rotation axis and the angle in radians in the [0, pi] range,
+    /// with the axis being the X axis if the angle is too small to determine
+    /// it.
+    pub fn to_axis_angle(self) -> (Normal, Scalar)
+    {
+        let mut quat = self.normalized().quat;
+        // Pick the representation with the smallest angle.
+        if quat[3] < 0.0 {
+            quat = -quat;
+        }
+        let sin = sq_len(f32x4::from([quat[0], quat[1], quat[2], 0.0])).sqrt();
+        if sin < AXIS_THRESHOLD {
+            let axis = Normal { vec: f32x4::from([1.0, 0.0, 0.0, 0.0]),
+                                weight: f32x4::splat(1.0) };
+            return (axis, Scalar::from_val(0.0));
+        }
+        let mut vec = quat * f32x4::splat(sin.recip());
+        vec[3] = 0.0;
+        let axis = Normal { vec,
+                            weight: f32x4::splat(1.0) };
+        let angle = sin.atan2(quat[3]) * 2.0;
+        (axis, Scalar::from_val(angle))
+    }
+
+    /// Computes the magnitude of the rotation, which is useful for logging.
+    ///
+    /// Returns the angle in degrees in the [0, 180] range.
+    pub fn angle_degrees(self) -> Scalar
+    {
+        let (_, angle) = self.to_axis_angle();
+        angle * Scalar::from_val(180.0 / PI)
+    }
+
+    /// Creates and initializes a new quaternion from Euler angles in radians.
+    ///
+    /// The rotations are applied in ZYX order, meaning roll around the X axis
+    /// first, then pitch around the Y axis, and finally yaw around the Z axis.
+    ///
+    /// * `pitch`: Rotation around the Y axis.
+    /// * `yaw`: Rotation around the Z axis.
+    /// * `roll`: Rotation around the X axis.
+    ///
+    /// Returns the newly created quaternion.
+    pub fn from_euler(pitch: Scalar, yaw: Scalar, roll: Scalar) -> Self
+    {
+        let (sp, cp) = (pitch.val[0] * 0.5).sin_cos();
+        let (sy, cy) = (yaw.val[0] * 0.5).sin_cos();
+        let (sr, cr) = (roll.val[0] * 0.5).sin_cos();
+        let x = sr * cp * cy - cr * sp * sy;
+        let y = cr * sp * cy + sr * cp * sy;
+        let z = cr * cp * sy - sr * sp * cy;
+        let w = cr * cp * cy + sr * sp * sy;
+        Self { quat: normalize(f32x4::from([x, y, z, w])) }
+    }
+
+    /// Converts this quaternion to Euler angles in radians, following the same
+    /// conventions as [`Quaternion::from_euler`].
+    ///
+    /// When the pitch is close to plus or minus 90 degrees the yaw and roll
+    /// rotate around the same axis, in which case the whole rotation is
+    /// reported as yaw and the roll is zero.
+    ///
+    /// Returns the pitch, yaw, and roll.
+    pub fn to_euler(self) -> (Scalar, Scalar, Scalar)
+    {
+        let (x, y, z, w) = (self.quat[0], self.quat[1], self.quat[2], self.quat[3]);
+        let sinp = 2.0 * (w * y - z * x);
+        if !(-GIMBAL_LOCK_THRESHOLD ..= GIMBAL_LOCK_THRESHOLD).contains(&sinp) {
+            let sign = if sinp > 0.0 { 1.0 } else { -1.0 };
+            let pitch = Scalar::from_val(sign * FRAC_PI_2);
+            let yaw = Scalar::from_val(-sign * 2.0 * x.atan2(w));
+            return (pitch, yaw, Scalar::from_val(0.0));
+        }
+        let pitch = Scalar::from_val(sinp.asin());
+        let yaw = Scalar::from_val((2.0 * (w * z + x * y)).atan2(1.0 - 2.0 * (y * y + z * z)));
+        let roll = Scalar::from_val((2.0 * (w * x + y * z)).atan2(1.0 - 2.0 * (x * x + y * y)));
+        (pitch, yaw, roll)
+    }
+
+    /// Computes the dot product between this and another quaternion.
+    ///
+    /// * `other`: Other quaternion to compute the dot product with.
+    ///
+    /// Returns the computed dot product.
+    pub fn dot(self, other: Self) -> Scalar
+    {
+        Scalar { val: f32x4::splat(dot(self.quat, other.quat)) }
+    }
+
+    /// Computes a unit quaternion representing the same rotation as this
+    /// quaternion.
+    ///
+    /// Returns the computed quaternion, or the identity quaternion if this
+    /// quaternion is degenerate.
+    pub fn normalized(self) -> Self
+    {
+        if sq_len(self.quat) < DEGENERATE_SQ_LEN {
+            return Self::default();
+        }
+        Self { quat: normalize(self.quat) }
+    }
+
+    /// Computes the conjugate of this quaternion.
+    ///
+    /// Returns the computed conjugate.
+    pub fn conjugate(self) -> Self
+    {
+        Self { quat: self.quat * f32x4::from([-1.0, -1.0, -1.0, 1.0]) }
+    }
+
+    /// Computes the inverse of this quaternion.
+    ///
+    /// Returns the computed inverse, or the identity quaternion if this
+    /// quaternion is degenerate.
+    pub fn inverse(self) -> Self
+    {
+        let sq_len = sq_len(self.quat);
+        if sq_len < DEGENERATE_SQ_LEN {
+            return Self::default();
+        }
+        if (sq_len - 1.0) < UNIT_TOLERANCE && (1.0 - sq_len) < UNIT_TOLERANCE {
+            // The inverse of a unit quaternion is its conjugate.
+            return self.conjugate();
+        }
+        Self { quat: self.conjugate().quat / f32x4::splat(sq_len) }
+    }
+
+    /// Rotates a vector by this quaternion, which is normalized first so that
+    /// the vector's length is preserved.
+    ///
+    /// * `vec`: Vector to rotate.
+    ///
+    /// Returns the rotated vector.
+    pub fn rotate(self, vec: Vector) -> Vector
+    {
+        // Expansion of q*v*q^-1 for a unit quaternion q with imaginary part u and real
+        // part w: v+2w(u×v)+2u×(u×v).
+        let quat = self.normalized().quat;
+        let mut imag = quat;
+        imag[3] = 0.0;
+        let tmp = cross(imag, vec.vec) * f32x4::splat(2.0);
+        let vec = vec.vec + tmp * f32x4::splat(quat[3]) + cross(imag, tmp);
+        Vector { vec }
+    }
+
+    /// Computes the spherical linear interpolation between this and another
+    /// quaternion along the shortest path.
+    ///
+    /// * `other`: Destination quaternion.
+    /// * `bias`: The bias towards either quaternion, clamped to the [0, 1]
+    ///   range.
+    ///
+    /// Returns the computed interpolation.
+    pub fn slerp(self, other: Self, bias: Scalar) -> Self
+    {
+        let bias = bias.val[0].clamp(0.0, 1.0);
+        let mut cos = dot(self.quat, other.quat);
+        let mut other = other.quat;
+        // Both a quaternion and its negation represent the same rotation, so pick the
+        // one closest to this quaternion.
+        if cos < 0.0 {
+            cos = -cos;
+            other = -other;
+        }
+        if cos > SLERP_THRESHOLD {
+            // The angle is too small to divide by its sine.
+            let quat = self.quat + (other - self.quat) * f32x4::splat(bias);
+            return Self { quat: normalize(quat) };
+        }
+        let angle = cos.acos();
+        let sin = angle.sin();
+        let lhs = ((1.0 - bias) * angle).sin() / sin;
+        let rhs = (bias * angle).sin() / sin;
+        let quat = self.quat * f32x4::splat(lhs) + other * f32x4::splat(rhs);
+        Self { quat: normalize(quat) }
+    }
+}
+
+impl ApproxEq for Quaternion
+{
+    /// Considers a quaternion and its negation equal as they represent the same
+    /// rotation.
+    fn approx_eq(self, other: Self, epsilon: Scalar) -> bool
+    {
+        approx_eq(self.quat, other.quat, epsilon.val) || approx_eq(self.quat, -other.quat, epsilon.val)
+    }
+}
+
+impl Display for Quaternion
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FormatResult
+    {
+        let prec = fmt.precision().unwrap_or(DISPLAY_PRECISION);
+        let quat = self.quat;
+        write!(fmt,
+               "({:.*}; {:.*}, {:.*}, {:.*})",
+               prec, quat[3], prec, quat[0], prec, quat[1], prec, quat[2])
+    }
 }
 
 impl Mul<Self> for Quaternion
 {
     type Output = Self;
 
+    /// Composes two rotations using the Hamilton product, with the right hand
+    /// side rotation being applied first, so `delta * total` applies `delta`
+    /// on top of `total`.
     fn mul(self, other: Self) -> Self
     {
         let (qx, qy, qz, qw) = (self.quat[0], self.quat[1], self.quat[2], self.quat[3]);
@@ -76,6 +299,16 @@ impl Mul<Self> for Quaternion
     }
 }
 
+impl Mul<Vector> for Quaternion
+{
+    type Output = Vector;
+
+    fn mul(self, other: Vector) -> Vector
+    {
+        self.rotate(other)
+    }
+}
+
 impl MulAssign<Self> for Quaternion
 {
     fn mul_assign(&mut self, other: Self)
@@ -95,7 +328,7 @@ impl Default for Quaternion
 #[cfg(test)]
 mod tests
 {
-    use core::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+    use core::f32::consts::{FRAC_PI_2, FRAC_PI_4, FRAC_PI_8};
 
     use super::*;
 
@@ -113,4 +346,203 @@ mod tests
         let rot = Quaternion::from_axis_angle(axis, angle) * rot;
         assert!(is_roughly(rot.quat, f32x4::from([0.0, FRAC_PI_4.sin(), 0.0, FRAC_PI_4.cos()])));
     }
+
+    #[test]
+    fn quaternion_compose()
+    {
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let axis = Vector::from_components(0.0, 0.0, 1.0);
+        let yaw = Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2));
+        let axis = Vector::from_components(1.0, 0.0, 0.0);
+        let pitch = Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2));
+        // The right hand side rotation is applied first.
+        let vec = Vector::from_components(0.0, 1.0, 0.0);
+        assert!(((pitch * yaw) * vec).approx_eq(pitch * (yaw * vec), epsilon));
+        assert!(((pitch * yaw) * vec).approx_eq(Vector::from_components(-1.0, 0.0, 0.0), epsilon));
+        assert!(((yaw * pitch) * vec).approx_eq(Vector::from_components(0.0, 0.0, 1.0), epsilon));
+        assert!((Quaternion::default() * pitch).approx_eq(pitch, epsilon));
+        assert!((pitch * Quaternion::default()).approx_eq(pitch, epsilon));
+    }
+
+    #[test]
+    fn quaternion_rotate()
+    {
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let axis = Vector::from_components(0.0, 0.0, 1.0);
+        let rot = Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2));
+        let vec = Vector::from_components(1.0, 0.0, 0.0);
+        assert!(rot.rotate(vec)
+                   .approx_eq(Vector::from_components(0.0, 1.0, 0.0), epsilon));
+        let vec = Vector::from_components(0.0, 2.0, 3.0);
+        assert!((rot * vec).approx_eq(Vector::from_components(-2.0, 0.0, 3.0), epsilon));
+        // Non-unit quaternions rotate without scaling.
+        let scaled = Quaternion { quat: rot.quat * f32x4::splat(3.0) };
+        assert!((scaled * vec).approx_eq(Vector::from_components(-2.0, 0.0, 3.0), epsilon));
+        assert!((Quaternion::default() * vec).approx_eq(vec, epsilon));
+    }
+
+    #[test]
+    fn quaternion_axis_angle()
+    {
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let axis = Vector::from_components(0.0, 0.0, 2.0);
+        let rot = Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2));
+        let (axist, angle) = rot.to_axis_angle();
+        assert!(axist.approx_eq(Normal::from_vec(axis), epsilon));
+        assert!(angle.approx_eq(Scalar::from_val(FRAC_PI_2), epsilon));
+        // The negated quaternion represents the same rotation.
+        let (axist, angle) = Quaternion { quat: -rot.quat }.to_axis_angle();
+        assert!(axist.approx_eq(Normal::from_vec(axis), epsilon));
+        assert!(angle.approx_eq(Scalar::from_val(FRAC_PI_2), epsilon));
+        let (_, angle) = Quaternion::default().to_axis_angle();
+        assert_eq!(angle, Scalar::from_val(0.0));
+    }
+
+    #[test]
+    fn quaternion_angle_degrees()
+    {
+        let epsilon = Scalar::from_val(1.0 / 256.0);
+        let axis = Vector::from_components(1.0, 0.0, 0.0);
+        let rot = Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2));
+        assert!(rot.angle_degrees().approx_eq(Scalar::from_val(90.0), epsilon));
+        assert_eq!(Quaternion::default().angle_degrees(), Scalar::from_val(0.0));
+    }
+
+    #[test]
+    fn quaternion_display()
+    {
+        assert_eq!(format!("{}", Quaternion::default()), "(1.000; 0.000, 0.000, 0.000)");
+        let rot = Quaternion { quat: f32x4::from([0.5, -0.5, 0.5, -0.5]) };
+        assert_eq!(format!("{rot:.2}"), "(-0.50; 0.50, -0.50, 0.50)");
+    }
+
+    #[test]
+    fn quaternion_euler()
+    {
+        for (pitch, yaw, roll) in [(0.0, 0.0, 0.0), (0.3, -1.2, 2.5), (-1.0, 2.0, -0.5), (1.4, 3.0, 0.1)] {
+            let rot = Quaternion::from_euler(Scalar::from_val(pitch), Scalar::from_val(yaw), Scalar::from_val(roll));
+            let (pitcht, yawt, rollt) = rot.to_euler();
+            let expected = f32x4::from([pitch, yaw, roll, 0.0]);
+            assert!(is_roughly(f32x4::from([pitcht.val[0], yawt.val[0], rollt.val[0], 0.0]), expected));
+        }
+    }
+
+    #[test]
+    fn quaternion_euler_axes()
+    {
+        let axis = Vector::from_components(0.0, 1.0, 0.0);
+        let rot = Quaternion::from_euler(Scalar::from_val(FRAC_PI_2),
+                                         Scalar::from_val(0.0),
+                                         Scalar::from_val(0.0));
+        assert!(is_roughly(rot.quat,
+                           Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2)).quat));
+        let axis = Vector::from_components(0.0, 0.0, 1.0);
+        let rot = Quaternion::from_euler(Scalar::from_val(0.0),
+                                         Scalar::from_val(FRAC_PI_2),
+                                         Scalar::from_val(0.0));
+        assert!(is_roughly(rot.quat,
+                           Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2)).quat));
+        let axis = Vector::from_components(1.0, 0.0, 0.0);
+        let rot = Quaternion::from_euler(Scalar::from_val(0.0),
+                                         Scalar::from_val(0.0),
+                                         Scalar::from_val(FRAC_PI_2));
+        assert!(is_roughly(rot.quat,
+                           Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2)).quat));
+    }
+
+    #[test]
+    fn quaternion_euler_gimbal_lock()
+    {
+        let rot = Quaternion::from_euler(Scalar::from_val(FRAC_PI_2),
+                                         Scalar::from_val(0.5),
+                                         Scalar::from_val(0.2));
+        let (pitch, yaw, roll) = rot.to_euler();
+        assert!(is_roughly(f32x4::from([pitch.val[0], yaw.val[0], roll.val[0], 0.0]),
+                           f32x4::from([FRAC_PI_2, 0.3, 0.0, 0.0])));
+        let rot = Quaternion::from_euler(Scalar::from_val(-FRAC_PI_2),
+                                         Scalar::from_val(0.5),
+                                         Scalar::from_val(0.2));
+        let (pitch, yaw, roll) = rot.to_euler();
+        assert!(is_roughly(f32x4::from([pitch.val[0], yaw.val[0], roll.val[0], 0.0]),
+                           f32x4::from([-FRAC_PI_2, 0.7, 0.0, 0.0])));
+    }
+
+    #[test]
+    fn quaternion_approx_eq()
+    {
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let axis = Vector::from_components(1.0, 1.0, -1.0);
+        let rot = Quaternion::from_axis_angle(axis, Angle::from_radians(2.0));
+        assert!(rot.approx_eq(Quaternion { quat: -rot.quat }, epsilon));
+        let other = Quaternion::from_axis_angle(axis, Angle::from_radians(2.0001));
+        assert!(rot.approx_eq(other, epsilon));
+        let other = Quaternion::from_axis_angle(axis, Angle::from_radians(2.1));
+        assert!(!rot.approx_eq(other, epsilon));
+    }
+
+    #[test]
+    fn quaternion_inverse()
+    {
+        let axis = Vector::from_components(1.0, 1.0, -1.0);
+        let rot = Quaternion::from_axis_angle(axis, Angle::from_radians(2.0));
+        let res = rot * rot.inverse();
+        assert!(is_roughly(res.quat, Quaternion::default().quat));
+        let scaled = Quaternion { quat: rot.quat * f32x4::splat(3.0) };
+        let inverse = scaled.inverse();
+        assert!(is_roughly(inverse.quat, rot.conjugate().quat * f32x4::splat(1.0 / 3.0)));
+        let res = scaled * inverse;
+        assert!(is_roughly(res.quat, Quaternion::default().quat));
+        let degenerate = Quaternion { quat: f32x4::splat(0.0) };
+        assert!(is_roughly(degenerate.inverse().quat, Quaternion::default().quat));
+    }
+
+    #[test]
+    fn quaternion_normalized()
+    {
+        let quat = Quaternion { quat: f32x4::from([1.0, 2.0, 3.0, 4.0]) }.normalized();
+        assert!(is_roughly(f32x4::splat(quat.dot(quat).val[0]), f32x4::splat(1.0)));
+        let degenerate = Quaternion { quat: f32x4::splat(0.0) }.normalized();
+        assert!(is_roughly(degenerate.quat, Quaternion::default().quat));
+    }
+
+    #[test]
+    fn quaternion_slerp()
+    {
+        let axis = Vector::from_components(0.0, 0.0, 1.0);
+        let rot0 = Quaternion::default();
+        let rot1 = Quaternion::from_axis_angle(axis, Angle::from_radians(FRAC_PI_2));
+        let expected = f32x4::from([0.0, 0.0, FRAC_PI_8.sin(), FRAC_PI_8.cos()]);
+        let rot = rot0.slerp(rot1, Scalar::from_val(0.5));
+        assert!(is_roughly(rot.quat, expected));
+        // The negated quaternion represents the same rotation.
+        let rot = rot0.slerp(Quaternion { quat: -rot1.quat }, Scalar::from_val(0.5));
+        assert!(is_roughly(rot.quat, expected));
+        let rot = rot0.slerp(rot1, Scalar::from_val(2.0));
+        assert!(is_roughly(rot.quat, rot1.quat));
+        let rot = rot0.slerp(rot1, Scalar::from_val(-1.0));
+        assert!(is_roughly(rot.quat, rot0.quat));
+    }
+
+    #[test]
+    fn quaternion_slerp_tiny()
+    {
+        let axis = Vector::from_components(0.0, 0.0, 1.0);
+        let rot0 = Quaternion::default();
+        let rot1 = Quaternion::from_axis_angle(axis, Angle::from_radians(0.002));
+        let rot = rot0.slerp(rot1, Scalar::from_val(0.5));
+        assert!(is_roughly(rot.quat, f32x4::from([0.0, 0.0, 0.0005f32.sin(), 0.0005f32.cos()])));
+    }
+
+    #[test]
+    fn quaternion_from_normals()
+    {
+        let old = Normal::from_vec(Vector::from_components(1.0, 0.0, 0.0));
+        for angle in [FRAC_PI_2, 0.01] {
+            let new = Normal::from_vec(Vector::from_components(angle.cos(), angle.sin(), 0.0));
+            let (axis, anglet) = Quaternion::from_normals(old, new).to_axis_angle();
+            assert!(axis.approx_eq(Normal::from_vec(Vector::from_components(0.0, 0.0, 1.0)),
+                                   Scalar::from_val(1.0 / 4096.0)));
+            assert!(anglet.approx_eq(Scalar::from_val(angle), Scalar::from_val(angle / 1024.0)));
+        }
+    }
 }
@@ -0,0 +1,118 @@
+//! Ray math.
+//!
+//! Provides a half-line for picking objects from screen coordinates.
+
+use super::*;
+
+/// Half-line starting at an origin point.
+#[derive(Clone, Copy, Debug)]
+pub struct Ray
+{
+    /// Starting point.
+    origin: Vector,
+    /// Direction of travel.
+    direction: Normal,
+}
+
+impl Ray
+{
+    /// Creates and initializes a new ray from its origin and direction.
+    ///
+    /// * `origin`: Starting point.
+    /// * `direction`: Direction of travel.
+    ///
+    /// Returns the newly created ray.
+    pub fn new(origin: Vector, direction: Normal) -> Self
+    {
+        Self { origin, direction }
+    }
+
+    /// Creates and initializes a new ray going through a point on the screen
+    /// from the near to the far clipping plane.
+    ///
+    /// * `point`: Horizontal and vertical normalized device coordinates.
+    /// * `unproj`: Inverse of the view projection matrix, as computed by
+    ///   [`Matrix::inverse`], mapping normalized device coordinates back to
+    ///   world space.
+    ///
+    /// Returns the newly created ray.
+    pub fn from_screen(point: Vector, unproj: Matrix) -> Self
+    {
+        let mut near = point.vec;
+        near[2] = 0.0;
+        let mut far = point.vec;
+        far[2] = 1.0;
+        let mut near = unproj.project(Vector { vec: near }).vec;
+        near[3] = 0.0;
+        let mut far = unproj.project(Vector { vec: far }).vec;
+        far[3] = 0.0;
+        Self { origin: Vector { vec: near },
+               direction: Normal { vec: normalize(far - near),
+                                   weight: f32x4::splat(1.0) } }
+    }
+
+    /// Computes the point at the specified distance along this ray.
+    ///
+    /// * `distance`: Distance from the origin.
+    ///
+    /// Returns the computed point.
+    pub fn at(self, distance: Scalar) -> Vector
+    {
+        Vector { vec: self.origin.vec + self.direction.vec * distance.val }
+    }
+
+    /// Computes where this ray enters a bounding box using the slab method.
+    ///
+    /// * `aabb`: Box to intersect.
+    ///
+    /// Returns the distance from the origin to the entry point, which is zero
+    /// if the origin is inside the box, or nothing if the ray misses it.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<Scalar>
+    {
+        let recip = self.direction.vec.recip();
+        let dist0 = (aabb.min.vec - self.origin.vec) * recip;
+        let dist1 = (aabb.max.vec - self.origin.vec) * recip;
+        let mut entry = dist0.simd_min(dist1);
+        let mut exit = dist0.simd_max(dist1);
+        // The unused lane would otherwise poison the reductions.
+        entry[3] = 0.0;
+        exit[3] = f32::INFINITY;
+        let (entry, exit) = (entry.reduce_max(), exit.reduce_min());
+        if entry > exit || exit < 0.0 {
+            return None;
+        }
+        Some(Scalar::from_val(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn ray_intersect_aabb()
+    {
+        let aabb = Aabb::new(Vector::from_components(-1.0, -1.0, -1.0),
+                             Vector::from_components(1.0, 1.0, 1.0));
+        let dir = Vector::from_components(0.0, 0.0, -1.0).normalized();
+        let hit = Ray::new(Vector::from_components(0.5, 0.5, 5.0), dir);
+        let dist = hit.intersect_aabb(&aabb).unwrap();
+        assert!(dist.approx_eq(Scalar::from_val(4.0), Scalar::from_val(1.0 / 4096.0)));
+        assert!(is_roughly(hit.at(dist).vec, f32x4::from([0.5, 0.5, 1.0, 0.0])));
+        let miss = Ray::new(Vector::from_components(1.5, 0.5, 5.0), dir);
+        assert!(miss.intersect_aabb(&aabb).is_none());
+        let behind = Ray::new(Vector::from_components(0.5, 0.5, -5.0), dir);
+        assert!(behind.intersect_aabb(&aabb).is_none());
+        let inside = Ray::new(Vector::from_components(0.0, 0.0, 0.0), dir);
+        assert_eq!(inside.intersect_aabb(&aabb), Some(Scalar::from_val(0.0)));
+    }
+
+    #[test]
+    fn ray_from_screen()
+    {
+        let ray = Ray::from_screen(Vector::from_components(0.25, -0.5, 0.0), Matrix::identity());
+        assert!(is_roughly(ray.origin.vec, f32x4::from([0.25, -0.5, 0.0, 0.0])));
+        assert!(is_roughly(ray.direction.vec, f32x4::from([0.0, 0.0, 1.0, 0.0])));
+    }
+}
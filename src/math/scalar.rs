@@ -4,7 +4,8 @@
 
 use core::cmp::{Ordering, PartialOrd};
 use core::default::Default;
-use core::ops::{Mul, MulAssign, Neg};
+use core::fmt::{Display, Formatter, Result as FormatResult};
+use core::ops::{Div, Mul, MulAssign, Neg};
 
 use super::*;
 
@@ -28,6 +29,43 @@ impl Scalar
         Self { val: f32x4::splat(val) }
     }
 
+    /// Returns the value represented by this scalar.
+    pub fn to_val(self) -> f32
+    {
+        self.val[0]
+    }
+
+    /// Computes the smallest between this and another scalar.
+    ///
+    /// * `other`: Scalar to compare against.
+    ///
+    /// Returns the smallest scalar.
+    pub fn min(self, other: Self) -> Self
+    {
+        Self { val: self.val.simd_min(other.val) }
+    }
+
+    /// Computes the largest between this and another scalar.
+    ///
+    /// * `other`: Scalar to compare against.
+    ///
+    /// Returns the largest scalar.
+    pub fn max(self, other: Self) -> Self
+    {
+        Self { val: self.val.simd_max(other.val) }
+    }
+
+    /// Restricts this scalar to the specified range.
+    ///
+    /// * `lo`: Lower bound of the range.
+    /// * `hi`: Upper bound of the range, which must not be below `lo`.
+    ///
+    /// Returns the clamped scalar.
+    pub fn clamp(self, lo: Self, hi: Self) -> Self
+    {
+        self.max(lo).min(hi)
+    }
+
     /// Returns self as a reinterpreted angle in radians.
     #[cfg(not(test))]
     pub fn to_angle(self) -> Angle
@@ -36,6 +74,23 @@ impl Scalar
     }
 }
 
+impl ApproxEq for Scalar
+{
+    fn approx_eq(self, other: Self, epsilon: Scalar) -> bool
+    {
+        approx_eq(self.val, other.val, epsilon.val)
+    }
+}
+
+impl Display for Scalar
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FormatResult
+    {
+        let prec = fmt.precision().unwrap_or(DISPLAY_PRECISION);
+        write!(fmt, "{:.*}", prec, self.val[0])
+    }
+}
+
 impl PartialOrd<Self> for Scalar
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering>
@@ -44,6 +99,16 @@ impl PartialOrd<Self> for Scalar
     }
 }
 
+impl Div<Self> for Scalar
+{
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self
+    {
+        Self { val: self.val / other.val }
+    }
+}
+
 impl Mul<Self> for Scalar
 {
     type Output = Self;
@@ -89,3 +154,38 @@ impl Default for Scalar
         Self { val: f32x4::splat(1.0) }
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn scalar_approx_eq()
+    {
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let val = Scalar::from_val(1.0);
+        assert!(val.approx_eq(Scalar::from_val(1.0001), epsilon));
+        assert!(val.approx_eq(Scalar::from_val(0.9999), epsilon));
+        assert!(!val.approx_eq(Scalar::from_val(1.001), epsilon));
+        assert!(!val.approx_eq(-val, epsilon));
+    }
+
+    #[test]
+    fn scalar_clamp()
+    {
+        let (lo, hi) = (Scalar::from_val(-1.0), Scalar::from_val(1.0));
+        assert_eq!(Scalar::from_val(0.5).clamp(lo, hi), Scalar::from_val(0.5));
+        assert_eq!(Scalar::from_val(-1.5).clamp(lo, hi), lo);
+        assert_eq!(Scalar::from_val(2.0).clamp(lo, hi), hi);
+        assert_eq!(lo.min(hi), lo);
+        assert_eq!(lo.max(hi), hi);
+    }
+
+    #[test]
+    fn scalar_display()
+    {
+        assert_eq!(format!("{}", Scalar::from_val(-1.23456)), "-1.235");
+        assert_eq!(format!("{:.1}", Scalar::from_val(0.25)), "0.2");
+    }
+}
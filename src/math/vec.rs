@@ -3,6 +3,7 @@
 //! Provides a generic 3D vector with some common vector operations.
 
 use core::default::Default;
+use core::fmt::{Display, Formatter, Result as FormatResult};
 use core::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
 
 use super::*;
@@ -34,45 +35,110 @@ impl Vector
     /// * `other`: Other vector to compute the cross product with.
     ///
     /// Returns the resulting vector.
-    #[cfg(not(test))]
     pub fn cross(self, other: Self) -> Self
     {
         Self { vec: cross(self.vec, other.vec) }
     }
 
+    /// Computes the dot product between this and another vector.
+    ///
+    /// * `other`: Other vector to compute the dot product with.
+    ///
+    /// Returns the computed dot product.
+    pub fn dot(self, other: Self) -> Scalar
+    {
+        Scalar { val: f32x4::splat(dot(self.vec, other.vec)) }
+    }
+
     /// Computes the length of this vector.
     ///
     /// Returns the computed length.
-    #[cfg(not(test))]
     pub fn length(self) -> Scalar
     {
         Scalar { val: f32x4::splat(len(self.vec)) }
     }
 
+    /// Computes a normal with the same direction as this vector.
+    ///
+    /// Returns the computed normal.
+    pub fn normalized(self) -> Normal
+    {
+        Normal::from_vec(self)
+    }
+
     /// Computes the squared distance between this and another vector.
     ///
     /// * `other`: Other vector to compute the squared distance to.
     ///
     /// Returns the computed squared distance.
-    #[cfg(not(test))]
     pub fn sq_distance(self, other: Self) -> Scalar
     {
         Scalar { val: f32x4::splat(sq_dist(self.vec, other.vec)) }
     }
 
+    /// Computes the component-wise minimum between this and another vector.
+    ///
+    /// * `other`: Vector to compare against.
+    ///
+    /// Returns the resulting vector.
+    pub fn min(self, other: Self) -> Self
+    {
+        Self { vec: self.vec.simd_min(other.vec) }
+    }
+
+    /// Computes the component-wise maximum between this and another vector.
+    ///
+    /// * `other`: Vector to compare against.
+    ///
+    /// Returns the resulting vector.
+    pub fn max(self, other: Self) -> Self
+    {
+        Self { vec: self.vec.simd_max(other.vec) }
+    }
+
+    /// Restricts every component of this vector to the specified box.
+    ///
+    /// * `lo`: Lower bound of every component.
+    /// * `hi`: Upper bound of every component, none of which may be below the
+    ///   respective component of `lo`.
+    ///
+    /// Returns the clamped vector.
+    pub fn clamp(self, lo: Self, hi: Self) -> Self
+    {
+        self.max(lo).min(hi)
+    }
+
     /// Computes the linear interpolation between this and another vector.
     ///
     /// * `other`: Destination vector.
     /// * `bias`: The bias towards either vector.
     ///
     /// Returns the computed interpolation.
-    #[cfg(not(test))]
     pub fn lerp(self, other: Self, bias: Scalar) -> Self
     {
         self + (other - self) * bias
     }
 }
 
+impl ApproxEq for Vector
+{
+    fn approx_eq(self, other: Self, epsilon: Scalar) -> bool
+    {
+        approx_eq(self.vec, other.vec, epsilon.val)
+    }
+}
+
+impl Display for Vector
+{
+    fn fmt(&self, fmt: &mut Formatter) -> FormatResult
+    {
+        let prec = fmt.precision().unwrap_or(DISPLAY_PRECISION);
+        write!(fmt,
+               "({:.*}, {:.*}, {:.*})",
+               prec, self.vec[0], prec, self.vec[1], prec, self.vec[2])
+    }
+}
+
 impl Add<Self> for Vector
 {
     type Output = Self;
@@ -152,3 +218,70 @@ impl Default for Vector
         Self { vec: f32x4::splat(0.0) }
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn vector_approx_eq()
+    {
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let vec = Vector::from_components(1.0, 2.0, 3.0);
+        assert!(vec.approx_eq(Vector::from_components(1.0001, 1.9999, 3.0), epsilon));
+        assert!(!vec.approx_eq(Vector::from_components(1.0, 2.0, 3.001), epsilon));
+        assert!(!vec.approx_eq(Vector::default(), epsilon));
+    }
+
+    #[test]
+    fn vector_clamp()
+    {
+        let lo = Vector::from_components(-1.0, -1.0, -1.0);
+        let hi = Vector::from_components(1.0, 1.0, 1.0);
+        let vec = Vector::from_components(0.5, -2.0, 3.0);
+        assert!(is_roughly(vec.clamp(lo, hi).vec, f32x4::from([0.5, -1.0, 1.0, 0.0])));
+        assert!(is_roughly(vec.min(hi).vec, f32x4::from([0.5, -2.0, 1.0, 0.0])));
+        assert!(is_roughly(vec.max(lo).vec, f32x4::from([0.5, -1.0, 3.0, 0.0])));
+    }
+
+    #[test]
+    fn vector_display()
+    {
+        let vec = Vector::from_components(1.0, -0.5, 0.25);
+        assert_eq!(format!("{vec}"), "(1.000, -0.500, 0.250)");
+        assert_eq!(format!("{vec:.1}"), "(1.0, -0.5, 0.2)");
+    }
+
+    #[test]
+    fn vector_cross()
+    {
+        let x = Vector::from_components(1.0, 0.0, 0.0);
+        let y = Vector::from_components(0.0, 1.0, 0.0);
+        let z = Vector::from_components(0.0, 0.0, 1.0);
+        assert!(is_roughly(x.cross(y).vec, z.vec));
+        assert!(is_roughly(y.cross(z).vec, x.vec));
+        assert!(is_roughly(z.cross(x).vec, y.vec));
+        assert!(is_roughly(y.cross(x).vec, -z.vec));
+        assert!(is_roughly(x.cross(x).vec, f32x4::splat(0.0)));
+    }
+
+    #[test]
+    fn vector_dot()
+    {
+        let vec0 = Vector::from_components(1.0, 2.0, 3.0);
+        let vec1 = Vector::from_components(-2.0, 1.0, 0.0);
+        assert_eq!(vec0.dot(vec1), Scalar::from_val(0.0));
+        assert_eq!(vec0.dot(vec0.cross(vec1)), Scalar::from_val(0.0));
+        assert_eq!(vec0.dot(vec0), Scalar::from_val(14.0));
+    }
+
+    #[test]
+    fn vector_normalized()
+    {
+        let vec = Vector::from_components(3.0, 0.0, 4.0);
+        assert_eq!(vec.length(), Scalar::from_val(5.0));
+        let normal = vec.normalized();
+        assert!(is_roughly(normal.vec, f32x4::from([0.6, 0.0, 0.8, 0.0])));
+    }
+}
@@ -0,0 +1,140 @@
+//! Homogeneous vector math.
+//!
+//! Provides a 4D vector bridging regular vectors and projection matrices.
+
+use core::default::Default;
+use core::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+
+use super::*;
+
+/// 4D homogeneous vector.
+#[derive(Clone, Copy, Debug)]
+pub struct Vector4
+{
+    /// Internal representation.
+    pub(super) vec: f32x4,
+}
+
+impl Vector4
+{
+    /// Creates and initializes a new homogeneous vector from a regular vector
+    /// and a W component.
+    ///
+    /// * `vec`: Regular vector providing the X, Y, and Z components.
+    /// * `w`: W component.
+    ///
+    /// Returns the newly created vector.
+    pub fn from_vector(vec: Vector, w: Scalar) -> Self
+    {
+        let mut vec = vec.vec;
+        vec[3] = w.val[0];
+        Self { vec }
+    }
+
+    /// Returns the X, Y, and Z components of this vector as a regular vector.
+    pub fn xyz(self) -> Vector
+    {
+        let mut vec = self.vec;
+        vec[3] = 0.0;
+        Vector { vec }
+    }
+
+    /// Returns the W component of this vector.
+    pub fn w(self) -> Scalar
+    {
+        Scalar { val: f32x4::splat(self.vec[3]) }
+    }
+}
+
+impl Add<Self> for Vector4
+{
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self
+    {
+        Self { vec: self.vec + other.vec }
+    }
+}
+
+impl AddAssign<Self> for Vector4
+{
+    fn add_assign(&mut self, other: Self)
+    {
+        self.vec += other.vec;
+    }
+}
+
+impl Sub<Self> for Vector4
+{
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self
+    {
+        Self { vec: self.vec - other.vec }
+    }
+}
+
+impl SubAssign<Self> for Vector4
+{
+    fn sub_assign(&mut self, other: Self)
+    {
+        self.vec -= other.vec;
+    }
+}
+
+impl Mul<Scalar> for Vector4
+{
+    type Output = Self;
+
+    fn mul(self, other: Scalar) -> Self
+    {
+        Self { vec: self.vec * other.val }
+    }
+}
+
+impl MulAssign<Scalar> for Vector4
+{
+    fn mul_assign(&mut self, other: Scalar)
+    {
+        self.vec *= other.val;
+    }
+}
+
+impl Default for Vector4
+{
+    fn default() -> Self
+    {
+        Self { vec: f32x4::from([0.0, 0.0, 0.0, 1.0]) }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use core::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn vector4_components()
+    {
+        let vec = Vector4::from_vector(Vector::from_components(1.0, 2.0, 3.0), Scalar::from_val(4.0));
+        assert!(is_roughly(vec.xyz().vec, f32x4::from([1.0, 2.0, 3.0, 0.0])));
+        assert_eq!(vec.w(), Scalar::from_val(4.0));
+        let vec = (vec + vec - vec) * Scalar::from_val(0.5);
+        assert!(is_roughly(vec.vec, f32x4::from([0.5, 1.0, 1.5, 2.0])));
+    }
+
+    #[test]
+    fn vector4_perspective_divide()
+    {
+        let fov = Scalar::from_val(FRAC_PI_2);
+        let aspect = Scalar::from_val(1.0);
+        let proj = Matrix::perspective(fov, aspect, Scalar::from_val(0.5), Scalar::from_val(2.0));
+        let point = Vector4::from_vector(Vector::from_components(1.0, -1.0, -2.0), Scalar::from_val(1.0));
+        let clip = proj * point;
+        assert_eq!(clip.w(), Scalar::from_val(2.0));
+        let ndc = clip.xyz() * (Scalar::from_val(1.0) / clip.w());
+        assert!(is_roughly(ndc.vec, f32x4::from([0.5, -0.5, 1.0, 0.0])));
+    }
+}
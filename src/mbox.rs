@@ -10,36 +10,78 @@
 
 extern crate alloc;
 
+#[cfg(not(test))]
 use alloc::boxed::Box;
+#[cfg(not(test))]
 use core::hint::spin_loop;
+use core::ptr::null_mut;
+#[cfg(not(test))]
 use core::sync::atomic::{fence, Ordering};
 
+#[cfg(not(test))]
 use crate::alloc::{Shell as Allocator, DMA};
+use crate::edid::EDID_BLOCK_SIZE;
+#[cfg(not(test))]
 use crate::sync::{Lazy, Lock};
-use crate::{DMA_RANGE, PERRY_RANGE, VC_RANGE};
+#[cfg(not(test))]
+use crate::PERRY_RANGE;
+use crate::{DMA_RANGE, VC_RANGE};
 
 /// Offset of the physical RAM from the perspective of the video core.
 const VC_OFFSET: usize = 0xC0000000;
 /// Base address of the video core mailbox registers.
+#[cfg(not(test))]
 const BASE: usize = 0x200B880 + PERRY_RANGE.start;
 /// Pointer to the inbox data register.
+#[cfg(not(test))]
 const INBOX_DATA: *const u32 = BASE as _;
 /// Pointer to the inbox status register.
+#[cfg(not(test))]
 const INBOX_STATUS: *const u32 = (BASE + 0x18) as _;
 /// Pointer to the outbox data register.
+#[cfg(not(test))]
 const OUTBOX_DATA: *mut u32 = (BASE + 0x20) as _;
 /// Pointer to the outbox status register.
+#[cfg(not(test))]
 const OUTBOX_STATUS: *const u32 = (BASE + 0x38) as _;
 /// Mailbox full status value.
+#[cfg(not(test))]
 const FULL_STATUS: u32 = 0x80000000;
 /// Mailbox empty status value.
+#[cfg(not(test))]
 const EMPTY_STATUS: u32 = 0x40000000;
 /// Delivery request code.
 const REQUEST_CODE: u32 = 0x0;
 /// Success delivery code.
 const SUCCESS_CODE: u32 = 0x80000000;
+/// Flag set in the length of every property processed by the video core.
+const RESPONSE_FLAG: u32 = 0x80000000;
+/// Property channel.
+const PROP_CHANNEL: u32 = 0x8;
 /// End tag.
 const END_TAG: u32 = 0x0;
+/// Get the firmware revision.
+const GET_FW_REV_TAG: u32 = 0x1;
+/// Get the board model.
+const GET_BOARD_MODEL_TAG: u32 = 0x10001;
+/// Get the board revision.
+const GET_BOARD_REV_TAG: u32 = 0x10002;
+/// Get the board serial number.
+const GET_BOARD_SERIAL_TAG: u32 = 0x10004;
+/// Get the memory region assigned to the ARM cores.
+const GET_ARM_MEM_TAG: u32 = 0x10005;
+/// Get the memory region assigned to the video core.
+const GET_VC_MEM_TAG: u32 = 0x10006;
+/// Get the power state of a device.
+const GET_POWER_STATE_TAG: u32 = 0x20001;
+/// Set the power state of a device.
+const SET_POWER_STATE_TAG: u32 = 0x28001;
+/// Power state flag indicating that the device is on.
+const POWER_ON_FLAG: u32 = 0x1;
+/// Power state flag indicating that the device does not exist.
+const POWER_MISSING_FLAG: u32 = 0x2;
+/// Power state flag requesting to wait for the power to become stable.
+const POWER_WAIT_FLAG: u32 = 0x2;
 /// Frame buffer allocation.
 const ALLOC_TAG: u32 = 0x40001;
 /// Set the physical size of the display.
@@ -48,17 +90,95 @@ const SET_PHYS_SIZE_TAG: u32 = 0x48003;
 const SET_VIRT_SIZE_TAG: u32 = 0x48004;
 /// Set the pixel depth.
 const SET_DEPTH_TAG: u32 = 0x48005;
+/// Set the pixel order.
+const SET_PIXEL_ORDER_TAG: u32 = 0x48006;
 /// Get the horizontal pitch.
 const GET_PITCH_TAG: u32 = 0x40008;
 /// Set the position of the physical display inside the buffer.
 const SET_POS_TAG: u32 = 0x48009;
 /// Set the touchscreen DMA buffer.
 const SET_TOUCH_BUF_TAG: u32 = 0x4801F;
+/// Get the backlight brightness of the official display.
+const GET_BACKLIGHT_TAG: u32 = 0x4000F;
+/// Set the backlight brightness of the official display.
+const SET_BACKLIGHT_TAG: u32 = 0x4800F;
+/// Maximum backlight brightness of the official display.
+pub const MAX_BACKLIGHT: u32 = 0xFF;
+/// Get the temperature of the SoC.
+const GET_TEMP_TAG: u32 = 0x30006;
+/// Get the maximum safe temperature of the SoC.
+const GET_MAX_TEMP_TAG: u32 = 0x3000A;
+/// Get a block of the EDID of the attached display.
+const GET_EDID_BLOCK_TAG: u32 = 0x30020;
+/// Get the rate of a clock.
+const GET_CLOCK_RATE_TAG: u32 = 0x30002;
+/// Set the rate of a clock.
+const SET_CLOCK_RATE_TAG: u32 = 0x38002;
+/// Get the maximum rate of a clock.
+const GET_MAX_CLOCK_RATE_TAG: u32 = 0x30004;
+/// Get the minimum rate of a clock.
+const GET_MIN_CLOCK_RATE_TAG: u32 = 0x30007;
+/// Get the state of a clock.
+const GET_CLOCK_STATE_TAG: u32 = 0x30001;
+/// Set the state of a clock.
+const SET_CLOCK_STATE_TAG: u32 = 0x38001;
+/// Clock state flag indicating that the clock is on.
+const CLOCK_ON_FLAG: u32 = 0x1;
+/// Clock state flag indicating that the clock does not exist.
+const CLOCK_MISSING_FLAG: u32 = 0x2;
+/// EMMC clock identifier.
+pub const EMMC_CLOCK: u32 = 0x1;
+/// UART clock identifier.
+pub const UART_CLOCK: u32 = 0x2;
+/// ARM clock identifier.
+pub const ARM_CLOCK: u32 = 0x3;
+/// Core clock identifier.
+pub const CORE_CLOCK: u32 = 0x4;
+/// V3D clock identifier.
+pub const V3D_CLOCK: u32 = 0x5;
+/// H264 clock identifier.
+pub const H264_CLOCK: u32 = 0x6;
+/// ISP clock identifier.
+pub const ISP_CLOCK: u32 = 0x7;
+/// SDRAM clock identifier.
+pub const SDRAM_CLOCK: u32 = 0x8;
+/// Pixel clock identifier.
+pub const PIXEL_CLOCK: u32 = 0x9;
+/// PWM clock identifier.
+pub const PWM_CLOCK: u32 = 0xA;
+/// HEVC clock identifier.
+pub const HEVC_CLOCK: u32 = 0xB;
+/// EMMC2 clock identifier.
+pub const EMMC2_CLOCK: u32 = 0xC;
+/// M2MC clock identifier.
+pub const M2MC_CLOCK: u32 = 0xD;
+/// Pixel BVB clock identifier.
+pub const PIXEL_BVB_CLOCK: u32 = 0xE;
+/// SD card power domain identifier.
+pub const SD_CARD_POWER: u32 = 0x0;
+/// UART 0 power domain identifier.
+pub const UART0_POWER: u32 = 0x1;
+/// UART 1 power domain identifier.
+pub const UART1_POWER: u32 = 0x2;
+/// USB host controller power domain identifier.
+pub const USB_HCD_POWER: u32 = 0x3;
+/// I2C 0 power domain identifier.
+pub const I2C0_POWER: u32 = 0x4;
+/// I2C 1 power domain identifier.
+pub const I2C1_POWER: u32 = 0x5;
+/// I2C 2 power domain identifier.
+pub const I2C2_POWER: u32 = 0x6;
+/// SPI power domain identifier.
+pub const SPI_POWER: u32 = 0x7;
+/// CCP2TX power domain identifier.
+pub const CCP2TX_POWER: u32 = 0x8;
 
 /// Global video core mailbox interface driver instance.
+#[cfg(not(test))]
 pub static MBOX: Lazy<Mailbox> = Lazy::new(Mailbox::new);
 
 /// Mailbox interface driver.
+#[cfg(not(test))]
 #[derive(Debug)]
 pub struct Mailbox
 {
@@ -76,6 +196,30 @@ pub struct Request
     len: usize,
 }
 
+/// Frame buffer allocated by the video core.
+#[derive(Clone, Copy, Debug)]
+pub struct Framebuffer
+{
+    /// Base address.
+    pub base: *mut u8,
+    /// Size in bytes.
+    pub size: usize,
+    /// Display width in pixels.
+    pub width: usize,
+    /// Display height in pixels.
+    pub height: usize,
+    /// Horizontal pitch in bytes.
+    pub pitch: usize,
+}
+
+/// Handle to the response to a property pushed into a request.
+#[derive(Clone, Copy, Debug)]
+pub struct Token
+{
+    /// Index of the property in the request.
+    idx: usize,
+}
+
 /// Response iterator.
 #[derive(Debug)]
 pub struct ResponseIterator
@@ -96,6 +240,27 @@ pub struct Response
     len: usize,
 }
 
+/// Mailbox exchange error.
+#[derive(Clone, Copy, Debug)]
+pub enum MboxError
+{
+    /// The reply was delivered through the wrong channel.
+    ChannelMismatch
+    {
+        channel: u32
+    },
+    /// The video core could not parse the request.
+    Unparsed
+    {
+        code: u32
+    },
+    /// The video core did not process a property.
+    Rejected
+    {
+        tag: u32
+    },
+}
+
 /// Mailbox property request..
 #[derive(Clone, Copy, Debug)]
 pub enum RequestProperty
@@ -122,6 +287,11 @@ pub enum RequestProperty
     {
         bits: usize
     },
+    /// Set the pixel order to either RGB or BGR.
+    SetPixelOrder
+    {
+        rgb: bool
+    },
     /// Get the horizontal pitch.
     GetPitch,
     /// Set the physical display position inside the buffer.
@@ -129,11 +299,91 @@ pub enum RequestProperty
     {
         x: usize, y: usize
     },
-    /// Set the touchscreen DMA buffer.
+    /// Set the touchscreen DMA buffer, or stop the video core from writing to
+    /// it if null.
     SetTouchBuffer
     {
         buf: *mut u8
     },
+    /// Get the backlight brightness of the official display.
+    GetBacklight,
+    /// Set the backlight brightness of the official display from 0 to
+    /// [`MAX_BACKLIGHT`], with larger values clamped.
+    SetBacklight
+    {
+        brightness: u32
+    },
+    /// Get the temperature of the SoC.
+    GetTemperature
+    {
+        id: u32
+    },
+    /// Get the maximum safe temperature of the SoC.
+    GetMaxTemperature
+    {
+        id: u32
+    },
+    /// Get the rate of a clock in Hz.
+    GetClockRate
+    {
+        clock_id: u32
+    },
+    /// Get the lowest rate that a clock can be set to in Hz.
+    GetMinClockRate
+    {
+        clock_id: u32
+    },
+    /// Get the highest rate that a clock can be set to in Hz.
+    GetMaxClockRate
+    {
+        clock_id: u32
+    },
+    /// Get a 128 byte block of the EDID of the attached display, with block 0
+    /// reporting the number of extension blocks that follow.
+    GetEdidBlock
+    {
+        block: u32
+    },
+    /// Get the firmware revision.
+    GetFirmwareRevision,
+    /// Get the board model.
+    GetBoardModel,
+    /// Get the board revision.
+    GetBoardRevision,
+    /// Get the board serial number.
+    GetBoardSerial,
+    /// Get the memory region assigned to the ARM cores.
+    GetArmMemory,
+    /// Get the memory region assigned to the video core.
+    GetVcMemory,
+    /// Get the power state of a device.
+    GetPowerState
+    {
+        device_id: u32
+    },
+    /// Turn a device on or off, optionally waiting for the power to become
+    /// stable.
+    SetPowerState
+    {
+        device_id: u32, on: bool, wait: bool
+    },
+    /// Set the rate of a clock in Hz, optionally preventing the firmware from
+    /// changing other clocks to turbo settings.
+    SetClockRate
+    {
+        clock_id: u32, rate: u32, skip_turbo: bool
+    },
+    /// Get the state of a clock.
+    GetClockState
+    {
+        clock_id: u32
+    },
+    /// Turn a clock on or off, which some peripherals need before their
+    /// registers can be accessed.
+    SetClockState
+    {
+        clock_id: u32, on: bool
+    },
 }
 
 /// Mailbox property response..
@@ -162,6 +412,11 @@ pub enum ResponseProperty
     {
         bits: usize
     },
+    /// Set the pixel order to either RGB or BGR.
+    SetPixelOrder
+    {
+        rgb: bool
+    },
     /// Get the horizontal pitch.
     GetPitch
     {
@@ -174,6 +429,112 @@ pub enum ResponseProperty
     },
     /// Set the touchscreen DMA buffer.
     SetTouchBuffer,
+    /// Get the backlight brightness of the official display from 0 to
+    /// [`MAX_BACKLIGHT`].
+    GetBacklight
+    {
+        brightness: u32
+    },
+    /// Set the backlight brightness of the official display, returning the
+    /// brightness actually applied.
+    SetBacklight
+    {
+        brightness: u32
+    },
+    /// Get the temperature of the SoC in thousandths of a degree Celsius.
+    GetTemperature
+    {
+        id: u32, temp: u32
+    },
+    /// Get the maximum safe temperature of the SoC in thousandths of a degree
+    /// Celsius.
+    GetMaxTemperature
+    {
+        id: u32, temp: u32
+    },
+    /// Get the rate of a clock in Hz.
+    GetClockRate
+    {
+        clock_id: u32, rate: u32
+    },
+    /// Get the lowest rate that a clock can be set to in Hz.
+    GetMinClockRate
+    {
+        clock_id: u32, rate: u32
+    },
+    /// Get the highest rate that a clock can be set to in Hz.
+    GetMaxClockRate
+    {
+        clock_id: u32, rate: u32
+    },
+    /// Get a block of the EDID of the attached display, with a non-zero
+    /// status if the block could not be read.
+    GetEdidBlock
+    {
+        block: u32,
+        status: u32,
+        data: [u8; EDID_BLOCK_SIZE],
+    },
+    /// Get the firmware revision.
+    GetFirmwareRevision
+    {
+        rev: u32
+    },
+    /// Get the board model.
+    GetBoardModel
+    {
+        model: u32
+    },
+    /// Get the board revision.
+    GetBoardRevision
+    {
+        rev: u32
+    },
+    /// Get the board serial number.
+    GetBoardSerial
+    {
+        serial: u64
+    },
+    /// Get the physical base address and size of the memory region assigned
+    /// to the ARM cores.
+    GetArmMemory
+    {
+        base: usize, size: usize
+    },
+    /// Get the physical base address and size of the memory region assigned
+    /// to the video core.
+    GetVcMemory
+    {
+        base: usize, size: usize
+    },
+    /// Get the power state of a device, which is never on if the device does
+    /// not exist.
+    GetPowerState
+    {
+        device_id: u32, on: bool, exists: bool
+    },
+    /// Set the power state of a device, returning the resulting state.
+    SetPowerState
+    {
+        device_id: u32, on: bool, exists: bool
+    },
+    /// Set the rate of a clock, returning the rate in Hz actually applied by
+    /// the firmware.
+    SetClockRate
+    {
+        clock_id: u32, rate: u32
+    },
+    /// Get the state of a clock, which is never on if the clock does not
+    /// exist.
+    GetClockState
+    {
+        clock_id: u32, on: bool, exists: bool
+    },
+    /// Set the state of a clock, returning the resulting state.
+    SetClockState
+    {
+        clock_id: u32, on: bool, exists: bool
+    },
 }
 
 /// Aligned message buffer.
@@ -185,6 +546,7 @@ struct Buffer
     content: [u32; 80], // Enough room for 16 properties of average size.
 }
 
+#[cfg(not(test))]
 impl Mailbox
 {
     /// Creates and initializes a new mailbox.
@@ -199,8 +561,9 @@ impl Mailbox
     ///
     /// * `req`: Request.
     ///
-    /// Returns a response object from which properties can be extracted.
-    pub fn exchange(&self, req: Request) -> Response
+    /// Returns a response object from which properties can be extracted, or
+    /// an error if the video core failed to process the request.
+    pub fn exchange(&self, req: Request) -> Result<Response, MboxError>
     {
         let mut buf = req.into_buffer();
         let mut dma_buf = self.buf.lock();
@@ -208,16 +571,95 @@ impl Mailbox
         while unsafe { OUTBOX_STATUS.read_volatile() } & FULL_STATUS != 0 {
             spin_loop()
         }
-        let data = unsafe { map_to_vc(dma_buf.content.as_mut_ptr().cast()) } | 0x8; // Channel.
+        let data = unsafe { map_to_vc(dma_buf.content.as_mut_ptr().cast()) } | PROP_CHANNEL;
         fence(Ordering::Release);
         unsafe { OUTBOX_DATA.write_volatile(data) };
         while unsafe { INBOX_STATUS.read_volatile() } & EMPTY_STATUS != 0 {
             spin_loop()
         }
-        unsafe { INBOX_DATA.read_volatile() }; // Don't care about this value, just reading it to empty the inbox.
+        // Reading the data also empties the inbox.
+        let data = unsafe { INBOX_DATA.read_volatile() };
         fence(Ordering::Acquire);
         buf = **dma_buf;
-        Response::from_buffer(buf)
+        Response::from_reply(data, buf)
+    }
+}
+
+impl Framebuffer
+{
+    /// Creates and initializes a new frame buffer by asking the video core to
+    /// allocate it.
+    ///
+    /// * `width`: Display width in pixels.
+    /// * `height`: Display height in pixels.
+    /// * `virt_height`: Height of the buffer in pixels, which can be larger
+    ///   than the display to hold multiple frames.
+    /// * `depth`: Pixel bit depth.
+    /// * `align`: Alignment of the buffer.
+    ///
+    /// Returns the newly created frame buffer.
+    #[cfg(not(test))]
+    pub fn new(width: usize, height: usize, virt_height: usize, depth: usize, align: usize) -> Self
+    {
+        let req = Self::request(width, height, virt_height, depth, align);
+        let resp = MBOX.exchange(req).expect("Failed to allocate a frame buffer");
+        Self::from_response(resp)
+    }
+
+    /// Composes the request to allocate a frame buffer.
+    ///
+    /// * `width`: Display width in pixels.
+    /// * `height`: Display height in pixels.
+    /// * `virt_height`: Height of the buffer in pixels.
+    /// * `depth`: Pixel bit depth.
+    /// * `align`: Alignment of the buffer.
+    ///
+    /// Returns the composed request.
+    fn request(width: usize, height: usize, virt_height: usize, depth: usize, align: usize) -> Request
+    {
+        // These properties must be sent together, as the video core only allocates
+        // the frame buffer as configured by the same request.
+        let mut req = Request::new();
+        req.push(RequestProperty::SetPhysicalSize { width, height });
+        req.push(RequestProperty::SetVirtualSize { width,
+                                                   height: virt_height });
+        req.push(RequestProperty::SetDepth { bits: depth });
+        req.push(RequestProperty::GetPitch);
+        req.push(RequestProperty::Allocate { align });
+        req
+    }
+
+    /// Creates and initializes a new frame buffer from the response to its
+    /// allocation request.
+    ///
+    /// * `resp`: Response to the request composed by [`Self::request`].
+    ///
+    /// Returns the newly created frame buffer.
+    ///
+    /// Panics if the video core did not allocate a frame buffer.
+    fn from_response(resp: Response) -> Self
+    {
+        let mut this = Self { base: null_mut(),
+                              size: 0,
+                              width: 0,
+                              height: 0,
+                              pitch: 0 };
+        for prop in resp {
+            match prop {
+                ResponseProperty::Allocate { base, size } => {
+                    this.base = base;
+                    this.size = size;
+                }
+                ResponseProperty::SetPhysicalSize { width, height } => {
+                    this.width = width;
+                    this.height = height;
+                }
+                ResponseProperty::GetPitch { pitch } => this.pitch = pitch,
+                _ => continue,
+            }
+        }
+        assert!(!this.base.is_null(), "The VC did not allocate a frame buffer");
+        this
     }
 }
 
@@ -233,14 +675,21 @@ impl Request
     }
 
     /// Pushes a new property into the request.
-    pub fn push(&mut self, prop: RequestProperty)
+    ///
+    /// * `prop`: Property to push.
+    ///
+    /// Returns a token to retrieve the response to the property with
+    /// [`Response::get`].
+    pub fn push(&mut self, prop: RequestProperty) -> Token
     {
         if let RequestProperty::None = prop {
-            return;
+            // Empty properties are never sent, so their token matches nothing.
+            return Token { idx: usize::MAX };
         }
         assert!(self.len < self.props.len(), "Too many properties in request");
         self.props[self.len] = prop;
         self.len += 1;
+        Token { idx: self.len - 1 }
     }
 
     /// Creates and initializes a new buffer by consuming this request.
@@ -290,6 +739,14 @@ impl Request
                     buf[idx + 3] = bits as _;
                     idx += 4;
                 }
+                RequestProperty::SetPixelOrder { rgb } => {
+                    assert!(idx + 4 < buf.len(), "Buffer overflow");
+                    buf[idx] = SET_PIXEL_ORDER_TAG;
+                    buf[idx + 1] = 4; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = rgb as _;
+                    idx += 4;
+                }
                 RequestProperty::GetPitch => {
                     assert!(idx + 4 < buf.len(), "Buffer overflow");
                     buf[idx] = GET_PITCH_TAG;
@@ -308,9 +765,14 @@ impl Request
                     idx += 5;
                 }
                 RequestProperty::SetTouchBuffer { buf: touchbuf } => {
-                    assert!(DMA_RANGE.contains(&(touchbuf as usize)),
-                            "Provided touch buffer is not in a DMA region");
-                    let touchbuf = unsafe { map_to_vc(touchbuf) };
+                    // A null address disables the buffer.
+                    let touchbuf = if touchbuf.is_null() {
+                        0
+                    } else {
+                        assert!(DMA_RANGE.contains(&(touchbuf as usize)),
+                                "Provided touch buffer is not in a DMA region");
+                        unsafe { map_to_vc(touchbuf) }
+                    };
                     assert!(idx + 4 < buf.len(), "Buffer overflow");
                     buf[idx] = SET_TOUCH_BUF_TAG;
                     buf[idx + 1] = 4; // Request and response size.
@@ -318,6 +780,175 @@ impl Request
                     buf[idx + 3] = touchbuf;
                     idx += 4;
                 }
+                RequestProperty::GetBacklight => {
+                    assert!(idx + 4 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_BACKLIGHT_TAG;
+                    buf[idx + 1] = 4; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = 0; // Reserved for response.
+                    idx += 4;
+                }
+                RequestProperty::SetBacklight { brightness } => {
+                    assert!(idx + 4 < buf.len(), "Buffer overflow");
+                    buf[idx] = SET_BACKLIGHT_TAG;
+                    buf[idx + 1] = 4; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = brightness.min(MAX_BACKLIGHT);
+                    idx += 4;
+                }
+                RequestProperty::GetTemperature { id } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_TEMP_TAG;
+                    buf[idx + 1] = 8; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = id;
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::GetMaxTemperature { id } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_MAX_TEMP_TAG;
+                    buf[idx + 1] = 8; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = id;
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::GetClockRate { clock_id } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_CLOCK_RATE_TAG;
+                    buf[idx + 1] = 8; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = clock_id;
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::GetMinClockRate { clock_id } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_MIN_CLOCK_RATE_TAG;
+                    buf[idx + 1] = 8; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = clock_id;
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::GetMaxClockRate { clock_id } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_MAX_CLOCK_RATE_TAG;
+                    buf[idx + 1] = 8; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = clock_id;
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::GetEdidBlock { block } => {
+                    assert!(idx + 37 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_EDID_BLOCK_TAG;
+                    buf[idx + 1] = 136; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = block;
+                    buf[idx + 4 .. idx + 37].fill(0); // Reserved for response.
+                    idx += 37;
+                }
+                RequestProperty::GetFirmwareRevision => {
+                    assert!(idx + 4 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_FW_REV_TAG;
+                    buf[idx + 1] = 4; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = 0; // Reserved for response.
+                    idx += 4;
+                }
+                RequestProperty::GetBoardModel => {
+                    assert!(idx + 4 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_BOARD_MODEL_TAG;
+                    buf[idx + 1] = 4; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = 0; // Reserved for response.
+                    idx += 4;
+                }
+                RequestProperty::GetBoardRevision => {
+                    assert!(idx + 4 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_BOARD_REV_TAG;
+                    buf[idx + 1] = 4; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = 0; // Reserved for response.
+                    idx += 4;
+                }
+                RequestProperty::GetBoardSerial => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_BOARD_SERIAL_TAG;
+                    buf[idx + 1] = 8; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = 0; // Reserved for response.
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::GetArmMemory => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_ARM_MEM_TAG;
+                    buf[idx + 1] = 8; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = 0; // Reserved for response.
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::GetVcMemory => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_VC_MEM_TAG;
+                    buf[idx + 1] = 8; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = 0; // Reserved for response.
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::GetPowerState { device_id } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_POWER_STATE_TAG;
+                    buf[idx + 1] = 8; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = device_id;
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::SetPowerState { device_id, on, wait } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = SET_POWER_STATE_TAG;
+                    buf[idx + 1] = 8; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = device_id;
+                    buf[idx + 4] = if on { POWER_ON_FLAG } else { 0 } | if wait { POWER_WAIT_FLAG } else { 0 };
+                    idx += 5;
+                }
+                RequestProperty::SetClockRate { clock_id,
+                                                rate,
+                                                skip_turbo, } => {
+                    assert!(idx + 6 < buf.len(), "Buffer overflow");
+                    buf[idx] = SET_CLOCK_RATE_TAG;
+                    buf[idx + 1] = 12; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = clock_id;
+                    buf[idx + 4] = rate;
+                    buf[idx + 5] = skip_turbo as _;
+                    idx += 6;
+                }
+                RequestProperty::GetClockState { clock_id } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = GET_CLOCK_STATE_TAG;
+                    buf[idx + 1] = 8; // Size reserved for request and response.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = clock_id;
+                    buf[idx + 4] = 0; // Reserved for response.
+                    idx += 5;
+                }
+                RequestProperty::SetClockState { clock_id, on } => {
+                    assert!(idx + 5 < buf.len(), "Buffer overflow");
+                    buf[idx] = SET_CLOCK_STATE_TAG;
+                    buf[idx + 1] = 8; // Request and response size.
+                    buf[idx + 2] = 0; // Reserved for response length.
+                    buf[idx + 3] = clock_id;
+                    buf[idx + 4] = if on { CLOCK_ON_FLAG } else { 0 };
+                    idx += 5;
+                }
             }
         }
         buf[idx] = END_TAG as _;
@@ -327,21 +958,47 @@ impl Request
 
 impl Response
 {
+    /// Creates and initializes a new Response from a reply of the video core.
+    ///
+    /// * `data`: Data read from the inbox.
+    /// * `buf`: The buffer from which to initialize the response.
+    ///
+    /// Returns the newly created Response, or an error if the reply was
+    /// delivered through the wrong channel or the video core did not process
+    /// the request.
+    fn from_reply(data: u32, buf: Buffer) -> Result<Self, MboxError>
+    {
+        let channel = data & 0xF;
+        if channel != PROP_CHANNEL {
+            return Err(MboxError::ChannelMismatch { channel });
+        }
+        Self::from_buffer(buf)
+    }
+
     /// Creates and initializes a new Response from a buffer.
     ///
     /// * `buf`: The buffer from which to initialize the response.
     ///
-    /// Returns the newly created Response.
-    fn from_buffer(buf: Buffer) -> Self
+    /// Returns the newly created Response, or an error if the video core did
+    /// not process the request.
+    fn from_buffer(buf: Buffer) -> Result<Self, MboxError>
     {
         let buf = &buf.content;
         let mut props = [ResponseProperty::None; 16];
         let code = buf[1];
-        assert_eq!(code, SUCCESS_CODE, "Error code returned by the VC: 0x{code:X}");
+        if code != SUCCESS_CODE {
+            return Err(MboxError::Unparsed { code });
+        }
         let mut len = 0;
         let mut idx = 2;
         while idx + 3 < buf.len() {
             let tag = buf[idx];
+            if tag == END_TAG {
+                break;
+            }
+            if buf[idx + 2] & RESPONSE_FLAG == 0 {
+                return Err(MboxError::Rejected { tag });
+            }
             idx += 3;
             let prop = match tag {
                 ALLOC_TAG => {
@@ -371,6 +1028,12 @@ impl Response
                     idx += 1;
                     ResponseProperty::SetDepth { bits }
                 }
+                SET_PIXEL_ORDER_TAG => {
+                    assert!(idx + 1 < buf.len(), "Truncated response from VC");
+                    let rgb = buf[idx] != 0;
+                    idx += 1;
+                    ResponseProperty::SetPixelOrder { rgb }
+                }
                 GET_PITCH_TAG => {
                     assert!(idx + 1 < buf.len(), "Truncated response from VC");
                     let pitch = buf[idx] as usize;
@@ -386,16 +1049,171 @@ impl Response
                 }
                 SET_TOUCH_BUF_TAG => {
                     assert!(idx + 1 < buf.len(), "Truncated response from VC");
+                    idx += 1;
                     ResponseProperty::SetTouchBuffer
                 }
-                END_TAG => break,
+                GET_BACKLIGHT_TAG => {
+                    assert!(idx + 1 < buf.len(), "Truncated response from VC");
+                    let brightness = buf[idx];
+                    idx += 1;
+                    ResponseProperty::GetBacklight { brightness }
+                }
+                SET_BACKLIGHT_TAG => {
+                    assert!(idx + 1 < buf.len(), "Truncated response from VC");
+                    let brightness = buf[idx];
+                    idx += 1;
+                    ResponseProperty::SetBacklight { brightness }
+                }
+                GET_TEMP_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let id = buf[idx];
+                    let temp = buf[idx + 1];
+                    idx += 2;
+                    ResponseProperty::GetTemperature { id, temp }
+                }
+                GET_MAX_TEMP_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let id = buf[idx];
+                    let temp = buf[idx + 1];
+                    idx += 2;
+                    ResponseProperty::GetMaxTemperature { id, temp }
+                }
+                GET_CLOCK_RATE_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let clock_id = buf[idx];
+                    let rate = buf[idx + 1];
+                    idx += 2;
+                    ResponseProperty::GetClockRate { clock_id, rate }
+                }
+                GET_MIN_CLOCK_RATE_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let clock_id = buf[idx];
+                    let rate = buf[idx + 1];
+                    idx += 2;
+                    ResponseProperty::GetMinClockRate { clock_id, rate }
+                }
+                GET_MAX_CLOCK_RATE_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let clock_id = buf[idx];
+                    let rate = buf[idx + 1];
+                    idx += 2;
+                    ResponseProperty::GetMaxClockRate { clock_id, rate }
+                }
+                GET_EDID_BLOCK_TAG => {
+                    assert!(idx + 34 < buf.len(), "Truncated response from VC");
+                    let block = buf[idx];
+                    let status = buf[idx + 1];
+                    let mut data = [0; EDID_BLOCK_SIZE];
+                    // The block is sent as raw bytes, which match the memory layout of the words.
+                    data.chunks_exact_mut(4)
+                        .zip(&buf[idx + 2 .. idx + 34])
+                        .for_each(|(bytes, word)| bytes.copy_from_slice(&word.to_le_bytes()));
+                    idx += 34;
+                    ResponseProperty::GetEdidBlock { block, status, data }
+                }
+                GET_FW_REV_TAG => {
+                    assert!(idx + 1 < buf.len(), "Truncated response from VC");
+                    let rev = buf[idx];
+                    idx += 1;
+                    ResponseProperty::GetFirmwareRevision { rev }
+                }
+                GET_BOARD_MODEL_TAG => {
+                    assert!(idx + 1 < buf.len(), "Truncated response from VC");
+                    let model = buf[idx];
+                    idx += 1;
+                    ResponseProperty::GetBoardModel { model }
+                }
+                GET_BOARD_REV_TAG => {
+                    assert!(idx + 1 < buf.len(), "Truncated response from VC");
+                    let rev = buf[idx];
+                    idx += 1;
+                    ResponseProperty::GetBoardRevision { rev }
+                }
+                GET_BOARD_SERIAL_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let serial = buf[idx] as u64 | (buf[idx + 1] as u64) << 32;
+                    idx += 2;
+                    ResponseProperty::GetBoardSerial { serial }
+                }
+                GET_ARM_MEM_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let base = buf[idx] as usize;
+                    let size = buf[idx + 1] as usize;
+                    idx += 2;
+                    ResponseProperty::GetArmMemory { base, size }
+                }
+                GET_VC_MEM_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let base = buf[idx] as usize;
+                    let size = buf[idx + 1] as usize;
+                    idx += 2;
+                    ResponseProperty::GetVcMemory { base, size }
+                }
+                GET_POWER_STATE_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let device_id = buf[idx];
+                    let on = buf[idx + 1] & POWER_ON_FLAG != 0;
+                    let exists = buf[idx + 1] & POWER_MISSING_FLAG == 0;
+                    idx += 2;
+                    ResponseProperty::GetPowerState { device_id, on, exists }
+                }
+                SET_POWER_STATE_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let device_id = buf[idx];
+                    let on = buf[idx + 1] & POWER_ON_FLAG != 0;
+                    let exists = buf[idx + 1] & POWER_MISSING_FLAG == 0;
+                    idx += 2;
+                    ResponseProperty::SetPowerState { device_id, on, exists }
+                }
+                SET_CLOCK_RATE_TAG => {
+                    assert!(idx + 3 < buf.len(), "Truncated response from VC");
+                    let clock_id = buf[idx];
+                    let rate = buf[idx + 1];
+                    idx += 3; // Skip the space reserved for the request.
+                    ResponseProperty::SetClockRate { clock_id, rate }
+                }
+                GET_CLOCK_STATE_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let clock_id = buf[idx];
+                    let on = buf[idx + 1] & CLOCK_ON_FLAG != 0;
+                    let exists = buf[idx + 1] & CLOCK_MISSING_FLAG == 0;
+                    idx += 2;
+                    ResponseProperty::GetClockState { clock_id, on, exists }
+                }
+                SET_CLOCK_STATE_TAG => {
+                    assert!(idx + 2 < buf.len(), "Truncated response from VC");
+                    let clock_id = buf[idx];
+                    let on = buf[idx + 1] & CLOCK_ON_FLAG != 0;
+                    let exists = buf[idx + 1] & CLOCK_MISSING_FLAG == 0;
+                    idx += 2;
+                    ResponseProperty::SetClockState { clock_id, on, exists }
+                }
                 _ => panic!("Unknown property tag returned by the VC: 0x{tag:X}"),
             };
             assert!(len < props.len(), "Too many properties in response from VC");
             props[len] = prop;
             len += 1;
         }
-        Self { props, len }
+        Ok(Self { props, len })
+    }
+}
+
+impl Response
+{
+    /// Retrieves the response to a specific property of the request.
+    ///
+    /// * `token`: Token returned when the property was pushed.
+    ///
+    /// Returns the response to the property, or [`ResponseProperty::None`] if
+    /// there is none.
+    pub fn get(&self, token: Token) -> ResponseProperty
+    {
+        // The video core responds to the properties in the same order in which they
+        // were requested.
+        if token.idx >= self.len {
+            return ResponseProperty::None;
+        }
+        self.props[token.idx]
     }
 }
 
@@ -477,3 +1295,344 @@ fn map_from_vc(data: u32) -> *mut u8
             "Physical address 0x{phys:X} is not mapped to the DMA or VC regions");
     (phys - vc_phys_range.start + VC_RANGE.start) as _
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn reply_errors()
+    {
+        let mut req = Request::new();
+        req.push(RequestProperty::GetBoardModel);
+        let buf = reply(req, &[&[0x11]]);
+        let resp = Response::from_reply(PROP_CHANNEL, buf).unwrap();
+        assert!(matches!(resp.get(Token { idx: 0 }),
+                         ResponseProperty::GetBoardModel { model: 0x11 }));
+        // Replies through other channels are refused regardless of the content of the
+        // buffer.
+        let err = Response::from_reply(0x1000 | 0x9, buf).unwrap_err();
+        assert!(matches!(err, MboxError::ChannelMismatch { channel: 0x9 }));
+        // The video core failed to parse the request.
+        let mut unparsed = buf;
+        unparsed.content[1] = 0x80000001;
+        let err = Response::from_reply(PROP_CHANNEL, unparsed).unwrap_err();
+        assert!(matches!(err, MboxError::Unparsed { code: 0x80000001 }));
+        // The video core parsed the request but skipped the second property.
+        let mut req = Request::new();
+        req.push(RequestProperty::GetBoardModel);
+        req.push(RequestProperty::GetBoardRevision);
+        let mut rejected = reply(req, &[&[0x11], &[0xC03111]]);
+        rejected.content[8] = 0;
+        let err = Response::from_reply(PROP_CHANNEL, rejected).unwrap_err();
+        assert!(matches!(err, MboxError::Rejected { tag: GET_BOARD_REV_TAG }));
+    }
+
+    #[test]
+    fn response_tokens()
+    {
+        let mut req = Request::new();
+        let temp = req.push(RequestProperty::GetTemperature { id: 0 });
+        let none = req.push(RequestProperty::None);
+        let rate = req.push(RequestProperty::GetClockRate { clock_id: ARM_CLOCK });
+        let resp = Response::from_buffer(reply(req, &[&[0, 45000], &[ARM_CLOCK, 1500000000]])).unwrap();
+        assert!(matches!(resp.get(rate),
+                         ResponseProperty::GetClockRate { clock_id: ARM_CLOCK,
+                                                          rate: 1500000000 }));
+        assert!(matches!(resp.get(temp), ResponseProperty::GetTemperature { id: 0, temp: 45000 }));
+        assert!(matches!(resp.get(none), ResponseProperty::None));
+    }
+
+    #[test]
+    fn framebuffer_request()
+    {
+        let req = Framebuffer::request(800, 480, 960, 32, 0x1000);
+        let buf = req.into_buffer();
+        assert_eq!(buf.content[.. 2], [320, REQUEST_CODE]);
+        assert_eq!(buf.content[2 .. 7], [SET_PHYS_SIZE_TAG, 8, 0, 800, 480]);
+        assert_eq!(buf.content[7 .. 12], [SET_VIRT_SIZE_TAG, 8, 0, 800, 960]);
+        assert_eq!(buf.content[12 .. 16], [SET_DEPTH_TAG, 4, 0, 32]);
+        assert_eq!(buf.content[16 .. 20], [GET_PITCH_TAG, 4, 0, 0]);
+        assert_eq!(buf.content[20 .. 25], [ALLOC_TAG, 8, 0, 0x1000, 0]);
+        assert_eq!(buf.content[25], END_TAG);
+        let req = Framebuffer::request(800, 480, 960, 32, 0x1000);
+        // The frame buffer is allocated at the start of the memory reserved for the VC.
+        let vals: [&[u32]; 5] = [&[800, 480], &[800, 960], &[32], &[3200], &[0xFE000000, 0x177000]];
+        let resp = Response::from_buffer(reply(req, &vals)).unwrap();
+        let fb = Framebuffer::from_response(resp);
+        assert_eq!(fb.base as usize, VC_RANGE.start);
+        assert_eq!(fb.size, 0x177000);
+        assert_eq!((fb.width, fb.height, fb.pitch), (800, 480, 3200));
+    }
+
+    #[test]
+    fn temperature_encoding()
+    {
+        let content = encode(RequestProperty::GetTemperature { id: 0 });
+        assert_eq!(content[2 .. 8], [GET_TEMP_TAG, 8, 0, 0, 0, END_TAG]);
+        let content = encode(RequestProperty::GetMaxTemperature { id: 0 });
+        assert_eq!(content[2 .. 8], [GET_MAX_TEMP_TAG, 8, 0, 0, 0, END_TAG]);
+        assert!(matches!(decode(RequestProperty::GetTemperature { id: 0 }, &[0, 52300]),
+                         ResponseProperty::GetTemperature { id: 0, temp: 52300 }));
+        assert!(matches!(decode(RequestProperty::GetMaxTemperature { id: 0 }, &[0, 85000]),
+                         ResponseProperty::GetMaxTemperature { id: 0, temp: 85000 }));
+    }
+
+    #[test]
+    fn clock_rate_encoding()
+    {
+        let content = encode(RequestProperty::SetClockRate { clock_id: ARM_CLOCK,
+                                                             rate: 1800000000,
+                                                             skip_turbo: true });
+        assert_eq!(content[2 .. 9],
+                   [SET_CLOCK_RATE_TAG, 12, 0, ARM_CLOCK, 1800000000, 1, END_TAG]);
+        let content = encode(RequestProperty::GetClockRate { clock_id: CORE_CLOCK });
+        assert_eq!(content[2 .. 8], [GET_CLOCK_RATE_TAG, 8, 0, CORE_CLOCK, 0, END_TAG]);
+        let prop = RequestProperty::SetClockRate { clock_id: ARM_CLOCK,
+                                                   rate: 1800000000,
+                                                   skip_turbo: false };
+        assert!(matches!(decode(prop, &[ARM_CLOCK, 1500000000]),
+                         ResponseProperty::SetClockRate { clock_id: ARM_CLOCK,
+                                                          rate: 1500000000 }));
+    }
+
+    #[test]
+    fn board_encoding()
+    {
+        let content = encode(RequestProperty::GetBoardModel);
+        assert_eq!(content[2 .. 7], [GET_BOARD_MODEL_TAG, 4, 0, 0, END_TAG]);
+        let content = encode(RequestProperty::GetBoardRevision);
+        assert_eq!(content[2 .. 7], [GET_BOARD_REV_TAG, 4, 0, 0, END_TAG]);
+        let content = encode(RequestProperty::GetBoardSerial);
+        assert_eq!(content[2 .. 8], [GET_BOARD_SERIAL_TAG, 8, 0, 0, 0, END_TAG]);
+        let content = encode(RequestProperty::GetFirmwareRevision);
+        assert_eq!(content[2 .. 7], [GET_FW_REV_TAG, 4, 0, 0, END_TAG]);
+        assert!(matches!(decode(RequestProperty::GetBoardRevision, &[0xC03111]),
+                         ResponseProperty::GetBoardRevision { rev: 0xC03111 }));
+        assert!(matches!(decode(RequestProperty::GetFirmwareRevision, &[0x5F3C2B8A]),
+                         ResponseProperty::GetFirmwareRevision { rev: 0x5F3C2B8A }));
+        // The serial number is sent least significant word first.
+        assert!(matches!(decode(RequestProperty::GetBoardSerial, &[0x89ABCDEF, 0x10000000]),
+                         ResponseProperty::GetBoardSerial { serial: 0x1000000089ABCDEF }));
+    }
+
+    #[test]
+    fn power_state_encoding()
+    {
+        let content = encode(RequestProperty::SetPowerState { device_id: USB_HCD_POWER,
+                                                              on: true,
+                                                              wait: true });
+        assert_eq!(content[2 .. 8],
+                   [SET_POWER_STATE_TAG, 8, 0, USB_HCD_POWER, 0x3, END_TAG]);
+        let content = encode(RequestProperty::SetPowerState { device_id: SD_CARD_POWER,
+                                                              on: false,
+                                                              wait: true });
+        assert_eq!(content[2 .. 8],
+                   [SET_POWER_STATE_TAG, 8, 0, SD_CARD_POWER, 0x2, END_TAG]);
+        let content = encode(RequestProperty::GetPowerState { device_id: I2C1_POWER });
+        assert_eq!(content[2 .. 8], [GET_POWER_STATE_TAG, 8, 0, I2C1_POWER, 0, END_TAG]);
+        let prop = RequestProperty::SetPowerState { device_id: USB_HCD_POWER,
+                                                    on: true,
+                                                    wait: false };
+        assert!(matches!(decode(prop, &[USB_HCD_POWER, 0x1]),
+                         ResponseProperty::SetPowerState { device_id: USB_HCD_POWER,
+                                                           on: true,
+                                                           exists: true }));
+        assert!(matches!(decode(prop, &[USB_HCD_POWER, 0x2]),
+                         ResponseProperty::SetPowerState { device_id: USB_HCD_POWER,
+                                                           on: false,
+                                                           exists: false }));
+        assert!(matches!(decode(RequestProperty::GetPowerState { device_id: I2C1_POWER },
+                                &[I2C1_POWER, 0x0]),
+                         ResponseProperty::GetPowerState { device_id: I2C1_POWER,
+                                                           on: false,
+                                                           exists: true }));
+    }
+
+    #[test]
+    fn memory_encoding()
+    {
+        let content = encode(RequestProperty::GetArmMemory);
+        assert_eq!(content[2 .. 8], [GET_ARM_MEM_TAG, 8, 0, 0, 0, END_TAG]);
+        let content = encode(RequestProperty::GetVcMemory);
+        assert_eq!(content[2 .. 8], [GET_VC_MEM_TAG, 8, 0, 0, 0, END_TAG]);
+        assert!(matches!(decode(RequestProperty::GetArmMemory, &[0x0, 0x3B400000]),
+                         ResponseProperty::GetArmMemory { base: 0x0,
+                                                          size: 0x3B400000 }));
+        assert!(matches!(decode(RequestProperty::GetVcMemory, &[0x3EC00000, 0x1400000]),
+                         ResponseProperty::GetVcMemory { base: 0x3EC00000,
+                                                         size: 0x1400000 }));
+    }
+
+    #[test]
+    fn touch_buffer_encoding()
+    {
+        // A null buffer disables the touch screen buffer instead of being mapped.
+        let content = encode(RequestProperty::SetTouchBuffer { buf: null_mut() });
+        assert_eq!(content[2 .. 7], [SET_TOUCH_BUF_TAG, 4, 0, 0, END_TAG]);
+        let content = encode(RequestProperty::SetTouchBuffer { buf: DMA_RANGE.start as _ });
+        assert_eq!(content[2 .. 7],
+                   [SET_TOUCH_BUF_TAG, 4, 0, (DMA_RANGE.start | VC_OFFSET) as u32, END_TAG]);
+        assert!(matches!(decode(RequestProperty::SetTouchBuffer { buf: null_mut() }, &[0]),
+                         ResponseProperty::SetTouchBuffer));
+    }
+
+    #[test]
+    fn backlight_encoding()
+    {
+        let content = encode(RequestProperty::SetBacklight { brightness: 0x80 });
+        assert_eq!(content[2 .. 7], [SET_BACKLIGHT_TAG, 4, 0, 0x80, END_TAG]);
+        // Values beyond the maximum brightness are clamped.
+        let content = encode(RequestProperty::SetBacklight { brightness: 0x1000 });
+        assert_eq!(content[2 .. 7], [SET_BACKLIGHT_TAG, 4, 0, MAX_BACKLIGHT, END_TAG]);
+        let content = encode(RequestProperty::GetBacklight);
+        assert_eq!(content[2 .. 7], [GET_BACKLIGHT_TAG, 4, 0, 0, END_TAG]);
+        assert!(matches!(decode(RequestProperty::SetBacklight { brightness: 0x1000 }, &[MAX_BACKLIGHT]),
+                         ResponseProperty::SetBacklight { brightness: MAX_BACKLIGHT }));
+        assert!(matches!(decode(RequestProperty::GetBacklight, &[0x40]),
+                         ResponseProperty::GetBacklight { brightness: 0x40 }));
+    }
+
+    #[test]
+    fn clock_state_encoding()
+    {
+        let content = encode(RequestProperty::SetClockState { clock_id: PWM_CLOCK,
+                                                              on: true });
+        assert_eq!(content[2 .. 8], [SET_CLOCK_STATE_TAG, 8, 0, PWM_CLOCK, 0x1, END_TAG]);
+        let content = encode(RequestProperty::SetClockState { clock_id: PWM_CLOCK,
+                                                              on: false });
+        assert_eq!(content[2 .. 8], [SET_CLOCK_STATE_TAG, 8, 0, PWM_CLOCK, 0x0, END_TAG]);
+        let content = encode(RequestProperty::GetClockState { clock_id: EMMC2_CLOCK });
+        assert_eq!(content[2 .. 8], [GET_CLOCK_STATE_TAG, 8, 0, EMMC2_CLOCK, 0, END_TAG]);
+        let prop = RequestProperty::SetClockState { clock_id: PWM_CLOCK,
+                                                    on: true };
+        assert!(matches!(decode(prop, &[PWM_CLOCK, 0x1]),
+                         ResponseProperty::SetClockState { clock_id: PWM_CLOCK,
+                                                           on: true,
+                                                           exists: true }));
+        assert!(matches!(decode(prop, &[PWM_CLOCK, 0x2]),
+                         ResponseProperty::SetClockState { clock_id: PWM_CLOCK,
+                                                           on: false,
+                                                           exists: false }));
+        assert!(matches!(decode(RequestProperty::GetClockState { clock_id: EMMC2_CLOCK },
+                                &[EMMC2_CLOCK, 0x0]),
+                         ResponseProperty::GetClockState { clock_id: EMMC2_CLOCK,
+                                                           on: false,
+                                                           exists: true }));
+    }
+
+    #[test]
+    fn clock_rate_limits_encoding()
+    {
+        let content = encode(RequestProperty::GetMaxClockRate { clock_id: ARM_CLOCK });
+        assert_eq!(content[2 .. 8], [GET_MAX_CLOCK_RATE_TAG, 8, 0, ARM_CLOCK, 0, END_TAG]);
+        let content = encode(RequestProperty::GetMinClockRate { clock_id: ARM_CLOCK });
+        assert_eq!(content[2 .. 8], [GET_MIN_CLOCK_RATE_TAG, 8, 0, ARM_CLOCK, 0, END_TAG]);
+        assert!(matches!(decode(RequestProperty::GetMaxClockRate { clock_id: ARM_CLOCK },
+                                &[ARM_CLOCK, 1800000000]),
+                         ResponseProperty::GetMaxClockRate { clock_id: ARM_CLOCK,
+                                                             rate: 1800000000 }));
+        assert!(matches!(decode(RequestProperty::GetMinClockRate { clock_id: ARM_CLOCK },
+                                &[ARM_CLOCK, 600000000]),
+                         ResponseProperty::GetMinClockRate { clock_id: ARM_CLOCK,
+                                                             rate: 600000000 }));
+    }
+
+    #[test]
+    fn display_encoding()
+    {
+        let content = encode(RequestProperty::SetPixelOrder { rgb: true });
+        assert_eq!(content[2 .. 7], [SET_PIXEL_ORDER_TAG, 4, 0, 1, END_TAG]);
+        let content = encode(RequestProperty::SetPosition { x: 0, y: 480 });
+        assert_eq!(content[2 .. 8], [SET_POS_TAG, 8, 0, 0, 480, END_TAG]);
+        assert!(matches!(decode(RequestProperty::SetPixelOrder { rgb: true }, &[0]),
+                         ResponseProperty::SetPixelOrder { rgb: false }));
+        assert!(matches!(decode(RequestProperty::SetPosition { x: 0, y: 480 }, &[0, 480]),
+                         ResponseProperty::SetPosition { x: 0, y: 480 }));
+        assert!(matches!(decode(RequestProperty::SetDepth { bits: 32 }, &[16]),
+                         ResponseProperty::SetDepth { bits: 16 }));
+        assert!(matches!(decode(RequestProperty::SetVirtualSize { width: 800,
+                                                                  height: 960 },
+                                &[800, 960]),
+                         ResponseProperty::SetVirtualSize { width: 800,
+                                                            height: 960 }));
+    }
+
+    #[test]
+    fn edid_encoding()
+    {
+        let content = encode(RequestProperty::GetEdidBlock { block: 1 });
+        assert_eq!(content[2 .. 6], [GET_EDID_BLOCK_TAG, 136, 0, 1]);
+        assert!(content[6 .. 40].iter().all(|word| *word == END_TAG));
+        // The block is returned as raw bytes after the block number and status.
+        let mut vals = [0; 34];
+        vals[0] = 1;
+        vals[2] = 0xFFFFFF00;
+        vals[3] = 0x00FFFFFF;
+        vals[33] = 0x12345678;
+        let resp = decode(RequestProperty::GetEdidBlock { block: 1 }, &vals);
+        let (block, status, data) = match resp {
+            ResponseProperty::GetEdidBlock { block, status, data } => (block, status, data),
+            _ => panic!("Unexpected response: {resp:?}"),
+        };
+        assert_eq!((block, status), (1, 0));
+        assert_eq!(data[.. 8], [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+        assert_eq!(data[EDID_BLOCK_SIZE - 4 ..], [0x78, 0x56, 0x34, 0x12]);
+    }
+
+    /// Encodes a request with a single property.
+    ///
+    /// * `prop`: Property to encode.
+    ///
+    /// Returns the content of the encoded buffer.
+    fn encode(prop: RequestProperty) -> [u32; 80]
+    {
+        let mut req = Request::new();
+        req.push(prop);
+        let buf = req.into_buffer();
+        assert_eq!(buf.content[.. 2], [320, REQUEST_CODE]);
+        buf.content
+    }
+
+    /// Decodes the simulated response to a request with a single property.
+    ///
+    /// * `prop`: Property to request.
+    /// * `vals`: Values returned for the property.
+    ///
+    /// Returns the decoded response.
+    fn decode(prop: RequestProperty, vals: &[u32]) -> ResponseProperty
+    {
+        let mut req = Request::new();
+        let token = req.push(prop);
+        let resp = Response::from_buffer(reply(req, &[vals])).unwrap();
+        resp.get(token)
+    }
+
+    /// Simulates the reply of the video core to a request.
+    ///
+    /// * `req`: Request to reply to.
+    /// * `vals`: Values returned for each property, in order.
+    ///
+    /// Returns the content of the buffer after the reply.
+    fn reply(req: Request, vals: &[&[u32]]) -> Buffer
+    {
+        let mut buf = req.into_buffer();
+        let content = &mut buf.content;
+        content[1] = SUCCESS_CODE;
+        let mut idx = 2;
+        for vals in vals {
+            let size = content[idx + 1] as usize;
+            assert!(vals.len() * 4 <= size,
+                    "Reply doesn't fit property 0x{:X}",
+                    content[idx]);
+            content[idx + 2] = RESPONSE_FLAG | (vals.len() * 4) as u32;
+            content[idx + 3 .. idx + 3 + vals.len()].copy_from_slice(vals);
+            idx += 3 + size / 4;
+        }
+        assert!(content[idx] == END_TAG,
+                "Missing values for property 0x{:X}",
+                content[idx]);
+        buf
+    }
+}
@@ -0,0 +1,82 @@
+//! Multi-core bookkeeping.
+//!
+//! Identifies the current core and keeps track of which cores have booted, so
+//! that primitives shared by several cores can tell how many participants to
+//! expect.
+
+#[cfg(not(test))]
+use core::arch::asm;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Bit mask of the cores that have booted.
+static ONLINE: AtomicU8 = AtomicU8::new(0);
+
+/// Reads the affinity register of the current core.
+///
+/// Returns the ID of the current core.
+#[cfg(not(test))]
+pub fn current_core() -> u8
+{
+    let mpidr: u64;
+    unsafe { asm!("mrs {mpidr}, mpidr_el1", mpidr = out (reg) mpidr, options (nomem, nostack, preserves_flags)) };
+    core_from_mpidr(mpidr)
+}
+
+/// Marks the current core as online, which each core must do once it boots.
+#[cfg(not(test))]
+pub fn set_online()
+{
+    mark_online(current_core());
+}
+
+/// Counts the cores that have booted.
+///
+/// Returns the number of online cores.
+pub fn core_count() -> usize
+{
+    ONLINE.load(Ordering::Acquire).count_ones() as usize
+}
+
+/// Extracts the core ID from the value of the affinity register.
+///
+/// * `mpidr`: Value of `MPIDR_EL1`.
+///
+/// Returns the affinity level 0 field, which is the ID of the core within its
+/// cluster, and the only level in use on the BCM2711.
+fn core_from_mpidr(mpidr: u64) -> u8
+{
+    (mpidr & 0xFF) as u8
+}
+
+/// Marks a core as online.
+///
+/// * `core`: ID of the core.
+fn mark_online(core: u8)
+{
+    ONLINE.fetch_or(1 << core, Ordering::Release);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn smp_core_from_mpidr()
+    {
+        // The multiprocessor and uniprocessor bits are set on a Cortex-A72.
+        assert_eq!(core_from_mpidr(0x80000000), 0);
+        assert_eq!(core_from_mpidr(0x80000003), 3);
+        assert_eq!(core_from_mpidr(0xC1000102), 2);
+    }
+
+    #[test]
+    fn smp_core_count()
+    {
+        assert_eq!(core_count(), 0);
+        mark_online(0);
+        mark_online(2);
+        mark_online(2);
+        assert_eq!(core_count(), 2);
+    }
+}
@@ -0,0 +1,86 @@
+//! Synchronization barriers.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{send_event, wait_for_event};
+
+/// Barrier that blocks cores until a given number of them reach it.
+#[derive(Debug)]
+pub struct Barrier
+{
+    /// Number of cores to wait for.
+    count: usize,
+    /// Number of cores that reached the barrier in the current generation.
+    arrived: AtomicUsize,
+    /// Number of times the barrier was released, which tells waiting cores
+    /// apart from those already waiting for the next release.
+    generation: AtomicUsize,
+}
+
+impl Barrier
+{
+    /// Creates and initializes a new barrier.
+    ///
+    /// * `count`: Number of cores to wait for.
+    ///
+    /// Returns the newly created barrier.
+    ///
+    /// Panics if `count` is zero.
+    pub const fn new(count: usize) -> Self
+    {
+        assert!(count > 0, "A barrier must wait for at least one core");
+        Self { count,
+               arrived: AtomicUsize::new(0),
+               generation: AtomicUsize::new(0) }
+    }
+
+    /// Puts the core to sleep until the configured number of cores reach the
+    /// barrier, after which it is reset for reuse.
+    pub fn wait(&self)
+    {
+        let generation = self.generation.load(Ordering::SeqCst);
+        if self.arrived.fetch_add(1, Ordering::SeqCst) + 1 == self.count {
+            // Reset the counter before releasing the other cores so that those that
+            // come back immediately are counted towards the next generation.
+            self.arrived.store(0, Ordering::SeqCst);
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            send_event();
+            return;
+        }
+        while self.generation.load(Ordering::SeqCst) == generation {
+            wait_for_event();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn barrier_wait()
+    {
+        const COUNT: usize = 4;
+        const ROUNDS: usize = 3;
+        let barrier = Barrier::new(COUNT);
+        let arrived = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0 .. COUNT {
+                scope.spawn(|| {
+                         for round in 1 ..= ROUNDS {
+                             arrived.fetch_add(1, Ordering::SeqCst);
+                             barrier.wait();
+                             assert_eq!(arrived.load(Ordering::SeqCst), COUNT * round);
+                             // Wait again so that no core starts the next round before the
+                             // others have checked the counter.
+                             barrier.wait();
+                         }
+                     });
+            }
+        });
+        assert_eq!(arrived.load(Ordering::SeqCst), COUNT * ROUNDS);
+    }
+}
@@ -0,0 +1,141 @@
+//! Condition variables.
+//!
+//! [`Condvar`] allows cores to sleep while holding no lock until another core
+//! signals that the state protected by a [`Lock`] might have changed, and is
+//! loosely modeled after `std::sync::Condvar`.
+
+use core::cmp::min;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{send_event, wait_for_event, Lock, LockGuard};
+
+/// Condition variable.
+#[derive(Debug)]
+pub struct Condvar
+{
+    /// Number of cores waiting for a notification.
+    waiters: AtomicUsize,
+    /// Number of notifications yet to be consumed by waiting cores.
+    tokens: AtomicUsize,
+}
+
+impl Condvar
+{
+    /// Creates and initializes a new condition variable.
+    ///
+    /// Returns the newly created condition variable.
+    pub const fn new() -> Self
+    {
+        Self { waiters: AtomicUsize::new(0),
+               tokens: AtomicUsize::new(0) }
+    }
+
+    /// Releases the lock held by the guard and puts the core to sleep until
+    /// notified, reacquiring the lock before returning.
+    ///
+    /// Other cores may change the protected state between the notification and
+    /// the reacquisition of the lock, so callers must always check their
+    /// condition again in a loop.
+    ///
+    /// * `guard`: Guard holding the lock that protects the condition.
+    ///
+    /// Returns a new guard holding the same lock.
+    pub fn wait<'a, T: ?Sized>(&self, guard: LockGuard<'a, T>) -> LockGuard<'a, T>
+    {
+        // Register as a waiter before releasing the lock so that notifications sent
+        // right after the condition changes are not lost.
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        let lock: &'a Lock<T> = guard.unlock();
+        loop {
+            let tokens = self.tokens.load(Ordering::SeqCst);
+            if tokens > 0
+               && self.tokens
+                      .compare_exchange_weak(tokens, tokens - 1, Ordering::SeqCst, Ordering::Relaxed)
+                      .is_ok()
+            {
+                break;
+            }
+            wait_for_event();
+        }
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+        lock.lock()
+    }
+
+    /// Wakes up one of the cores waiting on this condition variable, if any.
+    pub fn notify_one(&self)
+    {
+        self.notify(1);
+    }
+
+    /// Wakes up all the cores waiting on this condition variable.
+    pub fn notify_all(&self)
+    {
+        self.notify(usize::MAX);
+    }
+
+    /// Hands out notifications to waiting cores.
+    ///
+    /// * `count`: Maximum number of cores to wake up.
+    fn notify(&self, count: usize)
+    {
+        let mut tokens = self.tokens.load(Ordering::SeqCst);
+        loop {
+            // Never hand out more notifications than there are waiters, so that cores
+            // which start waiting afterwards don't wake up immediately.
+            let waiters = self.waiters.load(Ordering::SeqCst);
+            let new = min(tokens.saturating_add(count), waiters);
+            if new <= tokens {
+                return;
+            }
+            match self.tokens
+                      .compare_exchange_weak(tokens, new, Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => break,
+                Err(actual) => tokens = actual,
+            }
+        }
+        send_event();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn condvar_handoff()
+    {
+        let lock = Lock::new(());
+        let cvar = Condvar::new();
+        let value = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                     while cvar.waiters.load(Ordering::SeqCst) == 0 {
+                         thread::yield_now();
+                     }
+                     value.store(42, Ordering::SeqCst);
+                     cvar.notify_one();
+                 });
+            let mut guard = lock.lock();
+            while value.load(Ordering::SeqCst) == 0 {
+                guard = cvar.wait(guard);
+            }
+            drop(guard);
+        });
+        assert_eq!(value.load(Ordering::SeqCst), 42);
+        assert_eq!(cvar.waiters.load(Ordering::SeqCst), 0);
+        assert_eq!(cvar.tokens.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn condvar_notify_without_waiters()
+    {
+        let cvar = Condvar::new();
+        cvar.notify_one();
+        cvar.notify_all();
+        assert_eq!(cvar.tokens.load(Ordering::SeqCst), 0);
+    }
+}
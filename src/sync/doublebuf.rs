@@ -0,0 +1,225 @@
+//! Double buffer.
+//!
+//! [`DoubleBuffer`] keeps two copies of a small value so that readers can
+//! always copy the last completed update while a writer, possibly running in
+//! an interrupt handler, prepares the next one.
+
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
+/// Front and back buffers for values that can be copied.
+///
+/// Writers fill the back buffer and then flip the buffers atomically, so
+/// readers copying the front buffer only have to retry if another update
+/// completes and starts overwriting the buffer that they were copying, which
+/// requires them to be preempted for longer than an entire update.  Like with
+/// [`SeqLock`](super::SeqLock), readers may observe a torn value that is
+/// immediately discarded, so `T` must be [`Copy`].
+#[derive(Debug)]
+pub struct DoubleBuffer<T: Copy>
+{
+    /// Update counter, odd while an update is in progress, whose second bit
+    /// selects the front buffer.
+    seq: AtomicUsize,
+    /// Front and back buffers.
+    bufs: [UnsafeCell<T>; 2],
+}
+
+impl<T: Copy> DoubleBuffer<T>
+{
+    /// Creates and initializes a new double buffer.
+    ///
+    /// * `content`: Initial value.
+    ///
+    /// Returns the newly created double buffer.
+    pub const fn new(content: T) -> Self
+    {
+        Self { seq: AtomicUsize::new(0),
+               bufs: [UnsafeCell::new(content), UnsafeCell::new(content)] }
+    }
+
+    /// Copies the last completed value, retrying only if the buffer being
+    /// copied is overwritten in the meantime.
+    ///
+    /// Returns the copied value.
+    pub fn read(&self) -> T
+    {
+        loop {
+            if let Some(content) = self.try_read() {
+                return content;
+            }
+            spin_loop();
+        }
+    }
+
+    /// Writes a new value to the back buffer and makes it the front buffer,
+    /// waiting for other writers to finish.
+    ///
+    /// * `content`: New value.
+    pub fn write(&self, content: T)
+    {
+        let seq = self.begin_write();
+        unsafe { write_volatile(self.bufs[Self::back(seq)].get(), content) };
+        self.end_write(seq);
+    }
+
+    /// Copies the front buffer once.
+    ///
+    /// Returns the copied value, or nothing if the buffer was overwritten
+    /// while being copied.
+    fn try_read(&self) -> Option<T>
+    {
+        let seq = self.seq.load(Ordering::Acquire);
+        let content = unsafe { read_volatile(self.bufs[Self::front(seq)].get()) };
+        // Order the copy before checking the counter again.
+        fence(Ordering::Acquire);
+        // The buffer that was at the front only starts being overwritten once the
+        // update after the one that flips it to the back begins.
+        let flips = self.seq.load(Ordering::Relaxed).wrapping_sub(seq & !0x1);
+        if flips > 2 {
+            return None;
+        }
+        Some(content)
+    }
+
+    /// Marks the start of an update, waiting for other writers to finish.
+    ///
+    /// Returns the new, odd, value of the update counter.
+    fn begin_write(&self) -> usize
+    {
+        loop {
+            let seq = self.seq.load(Ordering::Relaxed);
+            if seq & 0x1 == 0
+               && self.seq
+                      .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+                      .is_ok()
+            {
+                // Order the counter update before the writes to the back buffer, so
+                // that readers still copying it from before the last flip notice them.
+                fence(Ordering::Release);
+                return seq + 1;
+            }
+            spin_loop();
+        }
+    }
+
+    /// Marks the end of an update, flipping the buffers.
+    ///
+    /// * `seq`: Value returned by [`Self::begin_write`].
+    fn end_write(&self, seq: usize)
+    {
+        // Releasing the counter publishes the writes to the back buffer before it
+        // becomes the front buffer.
+        self.seq.store(seq.wrapping_add(1), Ordering::Release);
+    }
+
+    /// Computes the index of the front buffer.
+    ///
+    /// * `seq`: Value of the update counter.
+    ///
+    /// Returns the computed index.
+    fn front(seq: usize) -> usize
+    {
+        seq >> 1 & 0x1
+    }
+
+    /// Computes the index of the back buffer.
+    ///
+    /// * `seq`: Value of the update counter.
+    ///
+    /// Returns the computed index.
+    fn back(seq: usize) -> usize
+    {
+        Self::front(seq) ^ 0x1
+    }
+}
+
+unsafe impl<T: Copy + Send> Send for DoubleBuffer<T> {}
+
+unsafe impl<T: Copy + Send> Sync for DoubleBuffer<T> {}
+
+#[cfg(test)]
+mod tests
+{
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn double_buffer_flip()
+    {
+        let buf = DoubleBuffer::new((1, 1));
+        assert_eq!(buf.try_read(), Some((1, 1)));
+        let seq = buf.begin_write();
+        unsafe { *buf.bufs[DoubleBuffer::<(i32, i32)>::back(seq)].get() = (2, 1) };
+        // Readers keep seeing the last completed value during an update.
+        assert_eq!(buf.try_read(), Some((1, 1)));
+        unsafe { *buf.bufs[DoubleBuffer::<(i32, i32)>::back(seq)].get() = (2, 2) };
+        buf.end_write(seq);
+        assert_eq!(buf.try_read(), Some((2, 2)));
+        let seq = buf.begin_write();
+        assert_eq!(buf.try_read(), Some((2, 2)));
+        unsafe { *buf.bufs[DoubleBuffer::<(i32, i32)>::back(seq)].get() = (3, 3) };
+        buf.end_write(seq);
+        assert_eq!(buf.read(), (3, 3));
+    }
+
+    #[test]
+    fn double_buffer_latest()
+    {
+        let buf = DoubleBuffer::new(0);
+        for val in 1 ..= 5 {
+            buf.write(val);
+            // The most recently completed update always ends up at the front.
+            assert_eq!(buf.read(), val);
+            assert_eq!(unsafe { *buf.bufs[DoubleBuffer::<i32>::front(buf.seq.load(Ordering::Relaxed))].get() },
+                       val);
+        }
+        let seq = buf.begin_write();
+        unsafe { *buf.bufs[DoubleBuffer::<i32>::back(seq)].get() = 6 };
+        // An update in progress doesn't replace the last completed one.
+        assert_eq!(buf.read(), 5);
+        buf.end_write(seq);
+        assert_eq!(buf.read(), 6);
+    }
+
+    #[test]
+    fn double_buffer_overwritten()
+    {
+        let buf = DoubleBuffer::new(0);
+        let start = buf.seq.load(Ordering::Relaxed);
+        buf.write(1);
+        // A reader that started before the update still copied a completed value.
+        assert!(buf.seq.load(Ordering::Relaxed).wrapping_sub(start) <= 2);
+        let seq = buf.begin_write();
+        // The next update overwrites the buffer that the reader was copying, which
+        // the reader must notice.
+        assert_eq!(DoubleBuffer::<i32>::back(seq), DoubleBuffer::<i32>::front(start));
+        assert!(seq.wrapping_sub(start) > 2);
+        buf.end_write(seq);
+        assert_eq!(buf.read(), 0);
+    }
+
+    #[test]
+    fn double_buffer_concurrent()
+    {
+        const COUNT: u64 = 10000;
+        let buf = DoubleBuffer::new([0u64; 8]);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                     for val in 1 ..= COUNT {
+                         buf.write([val; 8]);
+                     }
+                 });
+            let mut last = 0;
+            while last < COUNT {
+                let content = buf.read();
+                assert!(content.iter().all(|val| *val == content[0]));
+                assert!(content[0] >= last);
+                last = content[0];
+            }
+        });
+    }
+}
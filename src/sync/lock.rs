@@ -1,13 +1,11 @@
 //! Locking primitives.
 
 use core::cell::UnsafeCell;
-#[cfg(not(test))]
 use core::hint::spin_loop;
 use core::marker::PhantomData;
 use core::ops::{Deref, DerefMut};
 #[cfg(test)]
-use core::sync::atomic::{AtomicBool, Ordering};
-#[cfg(not(test))]
+use core::sync::atomic::AtomicBool;
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 #[cfg(not(test))]
@@ -39,10 +37,24 @@ pub struct Lock<T: ?Sized>
 #[repr(align(64))] // Take up an entire cache line.
 pub struct Advisor
 {
+    /// Tickets granting the lock in the order in which it was requested.
+    tickets: Tickets,
     /// The Logical CPU that currently holds the lock.
     affinity: AtomicUsize,
 }
 
+/// Ticket dispenser which grants a hold in first come, first served order, so
+/// that no logical CPU can be starved by others repeatedly taking the same
+/// lock.
+#[derive(Debug)]
+struct Tickets
+{
+    /// Ticket to hand out to the next logical CPU requesting a hold.
+    next: AtomicUsize,
+    /// Ticket of the logical CPU currently entitled to the hold.
+    serving: AtomicUsize,
+}
+
 /// Dummy lock advisor implementation for tests.
 #[cfg(test)]
 #[derive(Debug)]
@@ -64,6 +76,50 @@ impl<'a, T: ?Sized> Guard<'a, T>
         Self { lock,
                _data: PhantomData }
     }
+
+    /// Creates and initializes a new guard if the lock is not already held.
+    ///
+    /// * `lock`: Lock to be released when this guard is dropped.
+    ///
+    /// Returns the newly created guard, or [`None`] if the lock is contended.
+    fn try_new(lock: &'a Lock<T>) -> Option<Self>
+    {
+        if !unsafe { lock.advisor.try_lock() } {
+            return None;
+        }
+        Some(Self { lock,
+                    _data: PhantomData })
+    }
+
+    /// Creates and initializes a new guard, giving up if the lock remains
+    /// held for too long.
+    ///
+    /// * `lock`: Lock to be released when this guard is dropped.
+    /// * `max_spins`: Maximum number of times to spin waiting for the lock.
+    ///
+    /// Returns the newly created guard, or [`None`] if the lock remained
+    /// contended.
+    fn new_timeout(lock: &'a Lock<T>, max_spins: u32) -> Option<Self>
+    {
+        if !unsafe { lock.advisor.lock_timeout(max_spins) } {
+            return None;
+        }
+        Some(Self { lock,
+                    _data: PhantomData })
+    }
+}
+
+impl<'a, T: ?Sized> Guard<'a, T>
+{
+    /// Releases the lock held by this guard.
+    ///
+    /// Returns the lock that was released.
+    pub(super) fn unlock(self) -> &'a Lock<T>
+    {
+        let lock = self.lock;
+        drop(self);
+        lock
+    }
 }
 
 impl<'a, T: ?Sized> Deref for Guard<'a, T>
@@ -115,6 +171,27 @@ impl<T: ?Sized> Lock<T>
     {
         Guard::new(self)
     }
+
+    /// Attempts to lock access to the content without blocking.
+    ///
+    /// Returns a [`Guard`] which allows access to the content and holds the
+    /// lock until dropped, or [`None`] if the lock is already held.
+    pub fn try_lock(&self) -> Option<Guard<T>>
+    {
+        Guard::try_new(self)
+    }
+
+    /// Locks access to the content, blocking execution for a bounded amount of
+    /// time if another core is already accessing it.
+    ///
+    /// * `max_spins`: Maximum number of times to spin waiting for the lock.
+    ///
+    /// Returns a [`Guard`] which allows access to the content and holds the
+    /// lock until dropped, or [`None`] if the lock remained held.
+    pub fn lock_timeout(&self, max_spins: u32) -> Option<Guard<T>>
+    {
+        Guard::new_timeout(self, max_spins)
+    }
 }
 
 #[cfg(not(test))]
@@ -125,24 +202,63 @@ impl Advisor
     /// Returns the newly created lock advisor.
     pub const fn new() -> Self
     {
-        Self { affinity: AtomicUsize::new(CPU_COUNT) }
+        Self { tickets: Tickets::new(),
+               affinity: AtomicUsize::new(CPU_COUNT) }
     }
 
     /// Places a hold on the lock, blocking the logical CPU if another logical
     /// CPU is already holding it.
     ///
+    /// Logical CPUs that block are granted the hold in the order in which they
+    /// called this function, so an interrupt handler must not call this on a
+    /// lock that the interrupted code might be waiting for, as its ticket
+    /// would never be served.
+    ///
     /// The caller must ensure that this is called before a critical section.
     pub unsafe fn lock(&self)
     {
         let affinity = cpu_id();
         assert!(self.affinity.load(Ordering::Relaxed) != affinity,
                 "Deadlock detected on core #{affinity}");
-        while self.affinity
-                  .compare_exchange_weak(CPU_COUNT, affinity, Ordering::SeqCst, Ordering::Relaxed)
-                  .is_err()
-        {
-            spin_loop()
+        self.tickets.acquire();
+        self.affinity.store(affinity, Ordering::Relaxed);
+    }
+
+    /// Attempts to place a hold on the lock without blocking.
+    ///
+    /// Returns whether the hold was placed, which fails if any logical CPU,
+    /// including this one, is already holding or waiting for the lock.
+    ///
+    /// The caller must ensure that the lock is released if this succeeds.
+    pub unsafe fn try_lock(&self) -> bool
+    {
+        if !self.tickets.try_acquire() {
+            return false;
         }
+        self.affinity.store(cpu_id(), Ordering::Relaxed);
+        true
+    }
+
+    /// Attempts to place a hold on the lock, blocking the logical CPU for a
+    /// bounded amount of time if another logical CPU is already holding it.
+    ///
+    /// Unlike [`Self::lock`], this does not wait in line, since a ticket can't
+    /// be given up once taken.
+    ///
+    /// * `max_spins`: Maximum number of times to spin waiting for the lock.
+    ///
+    /// Returns whether the hold was placed.
+    ///
+    /// The caller must ensure that the lock is released if this succeeds.
+    pub unsafe fn lock_timeout(&self, max_spins: u32) -> bool
+    {
+        for _ in 0 .. max_spins {
+            if self.try_lock() {
+                return true;
+            }
+            spin_loop();
+        }
+        self.try_lock()
     }
 
     /// Relinquishes the hold on a lock, unblocking another logical CPU that
@@ -157,7 +273,49 @@ impl Advisor
         let affinity = cpu_id();
         assert!(affinity == self.affinity.load(Ordering::Relaxed),
                 "Core #{affinity} attempted to relinquish a lock that it doesn't hold");
-        self.affinity.store(CPU_COUNT, Ordering::SeqCst);
+        self.affinity.store(CPU_COUNT, Ordering::Relaxed);
+        self.tickets.release();
+    }
+}
+
+impl Tickets
+{
+    /// Creates and initializes a new ticket dispenser.
+    ///
+    /// Returns the newly created dispenser.
+    const fn new() -> Self
+    {
+        Self { next: AtomicUsize::new(0),
+               serving: AtomicUsize::new(0) }
+    }
+
+    /// Takes a ticket and spins until it is served.
+    ///
+    /// Returns the ticket that was served.
+    fn acquire(&self) -> usize
+    {
+        let ticket = self.next.fetch_add(1, Ordering::Relaxed);
+        while self.serving.load(Ordering::Acquire) != ticket {
+            spin_loop()
+        }
+        ticket
+    }
+
+    /// Takes a ticket only if it would be served immediately.
+    ///
+    /// Returns whether a ticket was taken.
+    fn try_acquire(&self) -> bool
+    {
+        let serving = self.serving.load(Ordering::Relaxed);
+        self.next
+            .compare_exchange(serving, serving.wrapping_add(1), Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Serves the next ticket in line.
+    fn release(&self)
+    {
+        self.serving.fetch_add(1, Ordering::Release);
     }
 }
 
@@ -175,6 +333,18 @@ impl Advisor
         self.is_locked.store(true, Ordering::Relaxed);
     }
 
+    pub unsafe fn try_lock(&self) -> bool
+    {
+        self.is_locked
+            .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    pub unsafe fn lock_timeout(&self, max_spins: u32) -> bool
+    {
+        (0 ..= max_spins).any(|_| self.try_lock())
+    }
+
     pub unsafe fn unlock(&self)
     {
         assert!(self.is_locked.load(Ordering::Relaxed),
@@ -186,3 +356,70 @@ impl Advisor
 unsafe impl<T: ?Sized> Send for Lock<T> {}
 
 unsafe impl<T: ?Sized> Sync for Lock<T> {}
+
+#[cfg(test)]
+mod tests
+{
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn lock_try_lock()
+    {
+        let lock = Lock::new(0);
+        let mut guard = lock.try_lock().unwrap();
+        *guard += 1;
+        assert!(lock.try_lock().is_none());
+        drop(guard);
+        let guard = lock.try_lock().unwrap();
+        assert_eq!(*guard, 1);
+    }
+
+    #[test]
+    fn lock_timeout()
+    {
+        let lock = Lock::new(0);
+        let guard = lock.lock_timeout(0).unwrap();
+        assert!(lock.lock_timeout(100).is_none());
+        drop(guard);
+        assert!(lock.lock_timeout(100).is_some());
+    }
+
+    #[test]
+    fn tickets_try_acquire()
+    {
+        let tickets = Tickets::new();
+        assert!(tickets.try_acquire());
+        assert!(!tickets.try_acquire());
+        tickets.release();
+        assert_eq!(tickets.acquire(), 1);
+        assert!(!tickets.try_acquire());
+        tickets.release();
+        assert!(tickets.try_acquire());
+    }
+
+    #[test]
+    fn tickets_fifo()
+    {
+        let tickets = Tickets::new();
+        let order = Lock::new(Vec::new());
+        assert_eq!(tickets.acquire(), 0);
+        thread::scope(|scope| {
+            for waiter in 1 ..= 2 {
+                let (tickets, order) = (&tickets, &order);
+                scope.spawn(move || {
+                         let ticket = tickets.acquire();
+                         order.try_lock().unwrap().push((waiter, ticket));
+                         tickets.release();
+                     });
+                // Wait for the waiter to take its ticket before spawning the next one.
+                while tickets.next.load(Ordering::Relaxed) <= waiter {
+                    thread::yield_now();
+                }
+            }
+            tickets.release();
+        });
+        assert_eq!(*order.try_lock().unwrap(), [(1, 1), (2, 2)]);
+    }
+}
@@ -1,13 +1,238 @@
 //! Synchronization primitives.
 
+mod barrier;
+mod condvar;
+mod doublebuf;
 #[cfg(not(test))]
 mod lazy;
 mod lock;
-#[cfg(not(test))]
+mod mpsc;
+mod once;
+mod resettable;
 mod rwlock;
+mod scalar;
+mod sem;
+mod seqlock;
+mod spsc;
+
+#[cfg(not(test))]
+use core::arch::asm;
+#[cfg(test)]
+use core::cell::Cell;
+#[cfg(test)]
+use core::hint::spin_loop;
 
+#[cfg(not(test))]
+pub use self::barrier::Barrier;
+#[cfg(not(test))]
+pub use self::condvar::Condvar;
+#[cfg(not(test))]
+pub use self::doublebuf::DoubleBuffer;
 #[cfg(not(test))]
 pub use self::lazy::Lazy;
 pub use self::lock::{Guard as LockGuard, Lock};
 #[cfg(not(test))]
-pub use self::rwlock::{ReadGuard as ReadLockGuard, RwLock, WriteGuard as WriteLockGuard};
+pub use self::mpsc::MpscQueue;
+#[cfg(not(test))]
+pub use self::once::Once;
+#[cfg(not(test))]
+pub use self::resettable::{Ref as ResettableLazyRef, ResettableLazy};
+pub use self::rwlock::RwLock;
+#[cfg(not(test))]
+pub use self::rwlock::{ReadGuard as ReadLockGuard, UpgradeableGuard as UpgradeableReadLockGuard,
+                       WriteGuard as WriteLockGuard};
+#[cfg(not(test))]
+pub use self::scalar::AtomicScalar;
+#[cfg(not(test))]
+pub use self::sem::Semaphore;
+#[cfg(not(test))]
+pub use self::seqlock::SeqLock;
+#[cfg(not(test))]
+pub use self::spsc::SpscQueue;
+
+/// IRQ and FIQ mask bits in the DAIF register.
+#[cfg(test)]
+const DAIF_IF: u64 = 0xC0;
+
+#[cfg(test)]
+std::thread_local! {
+    /// Simulated DAIF register for tests.
+    static DAIF: Cell<u64> = const { Cell::new(0) };
+    /// Simulated event register for tests.
+    static EVENT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs the provided function with IRQs and FIQs masked on the current core,
+/// restoring the previous mask state afterwards so that calls can be nested.
+///
+/// This is required around code that takes a lock which is also taken by an
+/// interrupt handler, as the handler would otherwise spin forever if it
+/// interrupted the lock holder on the same core.  The function must not wait
+/// for interrupts, as the core would never wake up.
+///
+/// * `f`: Function to run.
+///
+/// Returns the value returned by the function.
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R
+{
+    let daif = read_daif();
+    mask_interrupts();
+    let res = f();
+    write_daif(daif);
+    res
+}
+
+/// Reads the interrupt mask state of the current core.
+///
+/// Returns the content of the DAIF register.
+#[cfg(not(test))]
+fn read_daif() -> u64
+{
+    let daif: u64;
+    unsafe { asm!("mrs {daif}, daif", daif = out (reg) daif, options(nomem, nostack, preserves_flags)) };
+    daif
+}
+
+/// Reads the simulated interrupt mask state for tests.
+#[cfg(test)]
+fn read_daif() -> u64
+{
+    DAIF.with(Cell::get)
+}
+
+/// Restores the interrupt mask state of the current core.
+///
+/// * `daif`: Content of the DAIF register to restore.
+#[cfg(not(test))]
+fn write_daif(daif: u64)
+{
+    // Not declared as nomem so that the compiler doesn't move memory accesses
+    // out of the critical section.
+    unsafe { asm!("msr daif, {daif}", daif = in (reg) daif, options(nostack, preserves_flags)) };
+}
+
+/// Restores the simulated interrupt mask state for tests.
+#[cfg(test)]
+fn write_daif(daif: u64)
+{
+    DAIF.with(|reg| reg.set(daif));
+}
+
+/// Masks IRQs and FIQs on the current core.
+#[cfg(not(test))]
+fn mask_interrupts()
+{
+    unsafe { asm!("msr daifset, #0x3", options(nostack, preserves_flags)) };
+}
+
+/// Masks the simulated IRQs and FIQs for tests.
+#[cfg(test)]
+fn mask_interrupts()
+{
+    DAIF.with(|reg| reg.set(reg.get() | DAIF_IF));
+}
+
+/// Puts the current core in a low power state until an event is signaled by
+/// [`send_event`] from any core.
+///
+/// Every core has a one-bit event register which is set by events and
+/// cleared by this function.  If the register is already set, this function
+/// clears it and returns immediately, so an event signaled between checking a
+/// condition and calling this function is never lost.  The core may also wake
+/// up for unrelated reasons, such as interrupts, so callers must check their
+/// condition again afterwards.
+#[cfg(not(test))]
+pub fn wait_for_event()
+{
+    unsafe { asm!("wfe", options(nomem, nostack, preserves_flags)) };
+}
+
+/// Consumes the simulated event register for tests, spinning instead of
+/// sleeping when it isn't set so that waits never block.
+#[cfg(test)]
+pub fn wait_for_event()
+{
+    if !EVENT.with(|reg| reg.replace(false)) {
+        spin_loop();
+    }
+}
+
+/// Signals an event to all cores, setting their event registers and waking
+/// those waiting in [`wait_for_event`].
+///
+/// Must be issued after the store that the waiters are checking for, with at
+/// least release ordering, so that woken cores observe it.
+#[cfg(not(test))]
+pub fn send_event()
+{
+    unsafe { asm!("sev", options(nomem, nostack, preserves_flags)) };
+}
+
+/// Sets the simulated event register of the current thread for tests.
+#[cfg(test)]
+pub fn send_event()
+{
+    EVENT.with(|reg| reg.set(true));
+}
+
+/// Signals an event to the current core only, setting its event register
+/// without waking any other core.
+///
+/// This is mostly useful to make the first [`wait_for_event`] of a polling
+/// loop return immediately.
+#[cfg(not(test))]
+pub fn send_event_local()
+{
+    unsafe { asm!("sevl", options(nomem, nostack, preserves_flags)) };
+}
+
+/// Sets the simulated event register of the current thread for tests.
+#[cfg(test)]
+pub fn send_event_local()
+{
+    EVENT.with(|reg| reg.set(true));
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn without_interrupts_nested()
+    {
+        write_daif(0);
+        let res = without_interrupts(|| {
+            assert_eq!(read_daif() & DAIF_IF, DAIF_IF);
+            without_interrupts(|| assert_eq!(read_daif() & DAIF_IF, DAIF_IF));
+            // The inner call must not unmask what the outer call masked.
+            assert_eq!(read_daif() & DAIF_IF, DAIF_IF);
+            42
+        });
+        assert_eq!(res, 42);
+        assert_eq!(read_daif() & DAIF_IF, 0);
+    }
+
+    #[test]
+    fn without_interrupts_masked()
+    {
+        write_daif(DAIF_IF);
+        without_interrupts(|| ());
+        assert_eq!(read_daif() & DAIF_IF, DAIF_IF);
+    }
+
+    #[test]
+    fn event_register()
+    {
+        // A pending event is consumed by exactly one wait.
+        send_event_local();
+        wait_for_event();
+        assert!(!EVENT.with(Cell::get));
+        send_event();
+        assert!(EVENT.with(Cell::get));
+        wait_for_event();
+        assert!(!EVENT.with(Cell::get));
+        wait_for_event();
+        assert!(!EVENT.with(Cell::get));
+    }
+}
@@ -0,0 +1,165 @@
+//! Multiple-producer single-consumer queue.
+//!
+//! [`MpscQueue`] lets any number of cores submit values to one consumer, which
+//! is meant to be paired with a Software Generated Interrupt raised through
+//! [`Irq::trigger_core`](crate::irq::Irq::trigger_core) to wake the consumer
+//! once there is work for it.
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// Unbounded lock-free queue with many producers and one consumer.
+#[derive(Debug)]
+pub struct MpscQueue<T>
+{
+    /// Most recently pushed node, linked to the ones pushed before it.
+    head: AtomicPtr<Node<T>>,
+}
+
+/// Queued value.
+#[derive(Debug)]
+struct Node<T>
+{
+    /// Value carried by this node.
+    val: T,
+    /// Node pushed right before this one.
+    next: *mut Node<T>,
+}
+
+impl<T> MpscQueue<T>
+{
+    /// Creates and initializes a new empty queue.
+    ///
+    /// Returns the newly created queue.
+    pub const fn new() -> Self
+    {
+        Self { head: AtomicPtr::new(null_mut()) }
+    }
+
+    /// Appends a value to the end of the queue.
+    ///
+    /// Nodes are never removed individually, only by detaching the whole list,
+    /// so a node can't be freed and reused while a producer is trying to link
+    /// to it, which rules out the ABA problem.
+    ///
+    /// * `val`: Value to append.
+    pub fn push(&self, val: T)
+    {
+        let node = Box::into_raw(Box::new(Node { val, next: null_mut() }));
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            unsafe { (*node).next = head };
+            match self.head
+                      .compare_exchange_weak(head, node, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Removes every value from the queue, passing them to the provided
+    /// function in the order in which they were pushed.
+    ///
+    /// Values pushed while draining are left for a later call.
+    ///
+    /// * `f`: Function to call with each value.
+    pub fn drain(&self, mut f: impl FnMut(T))
+    {
+        let mut node = self.head.swap(null_mut(), Ordering::Acquire);
+        // The list links each node to the one pushed before it, so reverse it first.
+        let mut prev = null_mut();
+        while !node.is_null() {
+            let next = unsafe { (*node).next };
+            unsafe { (*node).next = prev };
+            prev = node;
+            node = next;
+        }
+        let mut node = prev;
+        while !node.is_null() {
+            let this = unsafe { Box::from_raw(node) };
+            node = this.next;
+            f(this.val);
+        }
+    }
+
+    /// Checks whether the queue is empty, which may already be stale when
+    /// producers are running concurrently.
+    ///
+    /// Returns whether there are no queued values.
+    pub fn is_empty(&self) -> bool
+    {
+        self.head.load(Ordering::Relaxed).is_null()
+    }
+}
+
+impl<T> Drop for MpscQueue<T>
+{
+    fn drop(&mut self)
+    {
+        self.drain(drop);
+    }
+}
+
+unsafe impl<T: Send> Send for MpscQueue<T> {}
+
+unsafe impl<T: Send> Sync for MpscQueue<T> {}
+
+#[cfg(test)]
+mod tests
+{
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn mpsc_order()
+    {
+        let queue = MpscQueue::new();
+        assert!(queue.is_empty());
+        queue.push(1);
+        queue.push(2);
+        queue.push(3);
+        assert!(!queue.is_empty());
+        let mut vals = Vec::new();
+        queue.drain(|val| vals.push(val));
+        assert_eq!(vals, [1, 2, 3]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn mpsc_producers()
+    {
+        const COUNT: usize = 10000;
+        let queue = MpscQueue::new();
+        let done = [AtomicBool::new(false), AtomicBool::new(false)];
+        let mut seen = [Vec::new(), Vec::new()];
+        thread::scope(|scope| {
+            for (producer, done) in done.iter().enumerate() {
+                let queue = &queue;
+                scope.spawn(move || {
+                         for val in 0 .. COUNT {
+                             queue.push((producer, val));
+                         }
+                         done.store(true, Ordering::Release);
+                     });
+            }
+            loop {
+                let finished = done.iter().all(|done| done.load(Ordering::Acquire));
+                queue.drain(|(producer, val)| seen[producer].push(val));
+                if finished {
+                    break;
+                }
+                thread::yield_now();
+            }
+        });
+        let expected = (0 .. COUNT).collect::<Vec<_>>();
+        assert_eq!(seen[0], expected);
+        assert_eq!(seen[1], expected);
+    }
+}
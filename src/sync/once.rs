@@ -0,0 +1,97 @@
+//! One-time initialization.
+//!
+//! [`Once`] runs a side-effecting initialization exactly once, which is useful
+//! for things like programming registers that must only be set up a single
+//! time but produce no value worth storing in a [`Lazy`](super::Lazy).
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use super::{send_event, wait_for_event};
+
+/// The initialization has not started yet.
+const INCOMPLETE: u8 = 0;
+/// The initialization is running on some core.
+const RUNNING: u8 = 1;
+/// The initialization has finished.
+const DONE: u8 = 2;
+
+/// One-time initializer.
+#[derive(Debug)]
+pub struct Once
+{
+    /// Initialization state.
+    state: AtomicU8,
+}
+
+impl Once
+{
+    /// Creates and initializes a new one-time initializer.
+    ///
+    /// Returns the newly created initializer.
+    pub const fn new() -> Self
+    {
+        Self { state: AtomicU8::new(INCOMPLETE) }
+    }
+
+    /// Calls the provided function if no other call has been made through
+    /// this initializer, putting the core to sleep until the first call
+    /// completes otherwise.
+    ///
+    /// * `init`: Initialization function.
+    pub fn call_once(&self, init: impl FnOnce())
+    {
+        match self.state
+                  .compare_exchange(INCOMPLETE, RUNNING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                init();
+                self.state.store(DONE, Ordering::Release);
+                send_event();
+            }
+            Err(_) => {
+                while self.state.load(Ordering::Acquire) != DONE {
+                    wait_for_event();
+                }
+            }
+        }
+    }
+
+    /// Checks whether the initialization has finished.
+    ///
+    /// Returns whether the initialization function has already returned.
+    pub fn is_completed(&self) -> bool
+    {
+        self.state.load(Ordering::Acquire) == DONE
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn once_contention()
+    {
+        const COUNT: usize = 8;
+        let once = Once::new();
+        let calls = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0 .. COUNT {
+                scope.spawn(|| {
+                         once.call_once(|| {
+                                 thread::yield_now();
+                                 calls.fetch_add(1, Ordering::SeqCst);
+                             });
+                         // Every caller must observe the completed initialization.
+                         assert!(once.is_completed());
+                         assert_eq!(calls.load(Ordering::SeqCst), 1);
+                     });
+            }
+        });
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
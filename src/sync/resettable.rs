@@ -0,0 +1,113 @@
+//! Lazy initialization for static objects that can be initialized again.
+//!
+//! [`ResettableLazy`] works like [`Lazy`](super::Lazy) except that its value
+//! can be dropped at any time, with the next access calling the initializer
+//! again, which is useful to start drivers over after a soft reset or a change
+//! to settings that can only be applied during initialization.
+
+use core::ops::Deref;
+
+use super::rwlock::{ReadGuard, RwLock};
+
+/// Lazy initializer for static values that can be reset.
+#[derive(Debug)]
+pub struct ResettableLazy<T: Send + Sync + 'static>
+{
+    /// Initialization function.
+    init: fn() -> T,
+    /// Actual object to be lazily initialized.
+    content: RwLock<Option<T>>,
+}
+
+/// Shared access to the value of a [`ResettableLazy`], preventing it from
+/// being reset for as long as it is held.
+#[derive(Debug)]
+pub struct Ref<'a, T: Send + Sync + 'static>
+{
+    /// Read guard on the initialized content.
+    guard: ReadGuard<'a, Option<T>>,
+}
+
+impl<T: Send + Sync + 'static> ResettableLazy<T>
+{
+    /// Creates and initializes a resettable lazy initializer.
+    ///
+    /// `init`: Initialization function to be called at first access and at
+    /// the first access after every reset.
+    ///
+    /// Returns the newly created lazy initializer.
+    pub const fn new(init: fn() -> T) -> Self
+    {
+        Self { init,
+               content: RwLock::new(None) }
+    }
+
+    /// Grants shared access to the value, initializing it first if this is the
+    /// first access since creation or the last reset.
+    ///
+    /// Returns a [`Ref`] which allows access to the value and blocks resets
+    /// until dropped.
+    pub fn get(&self) -> Ref<T>
+    {
+        let guard = self.content.rlock();
+        if guard.is_some() {
+            return Ref { guard };
+        }
+        drop(guard);
+        // Another core may have initialized the value in the meantime.
+        let mut guard = self.content.wlock();
+        guard.get_or_insert_with(self.init);
+        Ref { guard: guard.downgrade() }
+    }
+
+    /// Drops the current value, if any, so that the next access calls the
+    /// initializer again, blocking until nobody is accessing the value.
+    pub fn reset(&self)
+    {
+        let old = self.content.wlock().take();
+        // Drop the value without holding the lock in case its destructor
+        // accesses this initializer.
+        drop(old);
+    }
+}
+
+impl<'a, T: Send + Sync + 'static> Deref for Ref<'a, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &T
+    {
+        self.guard
+            .as_ref()
+            .expect("Resettable lazy value accessed before initialization")
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn init() -> usize
+    {
+        CALLS.fetch_add(1, Ordering::Relaxed)
+    }
+
+    #[test]
+    fn resettable_lazy_reset()
+    {
+        let lazy = ResettableLazy::new(init);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 0);
+        assert_eq!(*lazy.get(), 0);
+        assert_eq!(*lazy.get(), 0);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        lazy.reset();
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        assert_eq!(*lazy.get(), 1);
+        assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    }
+}
@@ -3,6 +3,7 @@
 use core::cell::UnsafeCell;
 use core::hint::spin_loop;
 use core::marker::PhantomData;
+use core::mem;
 use core::ops::{Deref, DerefMut};
 use core::sync::atomic::{AtomicUsize, Ordering};
 
@@ -28,12 +29,24 @@ pub struct WriteGuard<'a, T: Send + Sync + ?Sized>
     _data: PhantomData<*mut ()>,
 }
 
+/// Read grant on the lock that can be promoted to a write grant.
+#[derive(Debug)]
+pub struct UpgradeableGuard<'a, T: Send + Sync + ?Sized>
+{
+    /// Lock to which this guard grants shared access to.
+    lock: &'a RwLock<T>,
+    /// Zero-sized field to remove the Send trait.
+    _data: PhantomData<*mut ()>,
+}
+
 /// Read-write lock.
 #[derive(Debug)]
 pub struct RwLock<T: Send + Sync + ?Sized>
 {
     /// Spin-lock.
     advisor: Advisor,
+    /// Spin-lock held by writers and upgradeable readers.
+    upgrade_advisor: Advisor,
     /// Reader count.
     share_count: AtomicUsize,
     /// Protected content.
@@ -55,6 +68,42 @@ impl<'a, T: Send + Sync + ?Sized> ReadGuard<'a, T>
         Self { lock,
                _data: PhantomData }
     }
+
+    /// Creates and initializes a new read guard if the lock is not exclusively
+    /// held.
+    ///
+    /// * `lock`: Lock to grant shared access to.
+    ///
+    /// Returns the newly created guard, or [`None`] if the lock is contended.
+    fn try_new(lock: &'a RwLock<T>) -> Option<Self>
+    {
+        if !unsafe { lock.advisor.try_lock() } {
+            return None;
+        }
+        lock.share_count.fetch_add(1, Ordering::Relaxed);
+        unsafe { lock.advisor.unlock() };
+        Some(Self { lock,
+                    _data: PhantomData })
+    }
+
+    /// Creates and initializes a new read guard, giving up if the lock
+    /// remains exclusively held for too long.
+    ///
+    /// * `lock`: Lock to grant shared access to.
+    /// * `max_spins`: Maximum number of times to spin waiting for the lock.
+    ///
+    /// Returns the newly created guard, or [`None`] if the lock remained
+    /// contended.
+    fn new_timeout(lock: &'a RwLock<T>, max_spins: u32) -> Option<Self>
+    {
+        if !unsafe { lock.advisor.lock_timeout(max_spins) } {
+            return None;
+        }
+        lock.share_count.fetch_add(1, Ordering::Relaxed);
+        unsafe { lock.advisor.unlock() };
+        Some(Self { lock,
+                    _data: PhantomData })
+    }
 }
 
 impl<'a, T: Send + Sync + ?Sized> Deref for ReadGuard<'a, T>
@@ -84,13 +133,97 @@ impl<'a, T: Send + Sync + ?Sized> WriteGuard<'a, T>
     /// Returns the newly created guard.
     fn new(lock: &'a RwLock<T>) -> Self
     {
-        while lock.share_count.load(Ordering::Relaxed) != 0 {
+        unsafe {
+            lock.upgrade_advisor.lock();
+            lock.advisor.lock();
+        }
+        // Readers register while holding the advisor, so no new readers can appear
+        // while waiting for the existing ones to leave.
+        while lock.share_count.load(Ordering::SeqCst) != 0 {
             spin_loop();
         }
-        unsafe { lock.advisor.lock() };
         Self { lock,
                _data: PhantomData }
     }
+
+    /// Creates and initializes a new write guard if the lock is not held.
+    ///
+    /// * `lock`: Lock to grant exclusive access to.
+    ///
+    /// Returns the newly created guard, or [`None`] if the lock is contended.
+    fn try_new(lock: &'a RwLock<T>) -> Option<Self>
+    {
+        if !unsafe { lock.upgrade_advisor.try_lock() } {
+            return None;
+        }
+        if !unsafe { lock.advisor.try_lock() } {
+            unsafe { lock.upgrade_advisor.unlock() };
+            return None;
+        }
+        if lock.share_count.load(Ordering::SeqCst) != 0 {
+            unsafe {
+                lock.advisor.unlock();
+                lock.upgrade_advisor.unlock();
+            }
+            return None;
+        }
+        Some(Self { lock,
+                    _data: PhantomData })
+    }
+
+    /// Creates and initializes a new write guard, giving up if the lock
+    /// remains held for too long.
+    ///
+    /// * `lock`: Lock to grant exclusive access to.
+    /// * `max_spins`: Maximum number of times to spin waiting for each stage of
+    ///   the acquisition.
+    ///
+    /// Returns the newly created guard, or [`None`] if the lock remained
+    /// contended.
+    fn new_timeout(lock: &'a RwLock<T>, max_spins: u32) -> Option<Self>
+    {
+        if !unsafe { lock.upgrade_advisor.lock_timeout(max_spins) } {
+            return None;
+        }
+        if !unsafe { lock.advisor.lock_timeout(max_spins) } {
+            unsafe { lock.upgrade_advisor.unlock() };
+            return None;
+        }
+        let mut spins = 0;
+        while lock.share_count.load(Ordering::SeqCst) != 0 {
+            if spins == max_spins {
+                unsafe {
+                    lock.advisor.unlock();
+                    lock.upgrade_advisor.unlock();
+                }
+                return None;
+            }
+            spins += 1;
+            spin_loop();
+        }
+        Some(Self { lock,
+                    _data: PhantomData })
+    }
+
+    /// Atomically demotes this guard to shared access, without letting any
+    /// writer in between.
+    ///
+    /// Returns a [`ReadGuard`] which allows shared immutable access to the
+    /// content and holds the lock until dropped.
+    pub fn downgrade(self) -> ReadGuard<'a, T>
+    {
+        let lock = self.lock;
+        mem::forget(self);
+        // Register as a reader before letting anyone else in, so that writers keep
+        // waiting.
+        lock.share_count.fetch_add(1, Ordering::Relaxed);
+        unsafe {
+            lock.advisor.unlock();
+            lock.upgrade_advisor.unlock();
+        }
+        ReadGuard { lock,
+                    _data: PhantomData }
+    }
 }
 
 impl<'a, T: Send + Sync + ?Sized> Deref for WriteGuard<'a, T>
@@ -115,7 +248,68 @@ impl<'a, T: Send + Sync + ?Sized> Drop for WriteGuard<'a, T>
 {
     fn drop(&mut self)
     {
-        unsafe { self.lock.advisor.unlock() };
+        unsafe {
+            self.lock.advisor.unlock();
+            self.lock.upgrade_advisor.unlock();
+        }
+    }
+}
+
+impl<'a, T: Send + Sync + ?Sized> UpgradeableGuard<'a, T>
+{
+    /// Creates and initializes a new upgradeable read guard.
+    ///
+    /// * `lock`: Lock to grant upgradeable shared access to.
+    ///
+    /// Returns the newly created guard.
+    fn new(lock: &'a RwLock<T>) -> Self
+    {
+        unsafe {
+            lock.upgrade_advisor.lock();
+            lock.advisor.lock();
+        }
+        lock.share_count.fetch_add(1, Ordering::Relaxed);
+        unsafe { lock.advisor.unlock() };
+        Self { lock,
+               _data: PhantomData }
+    }
+
+    /// Atomically promotes this guard to exclusive access, blocking execution
+    /// until all the other readers are done.
+    ///
+    /// Returns a [`WriteGuard`] which allows exclusive mutable access to the
+    /// content and holds the lock until dropped.
+    pub fn upgrade(self) -> WriteGuard<'a, T>
+    {
+        let lock = self.lock;
+        // Keep holding the upgrade advisor, which now belongs to the write guard.
+        mem::forget(self);
+        unsafe { lock.advisor.lock() };
+        lock.share_count.fetch_sub(1, Ordering::SeqCst);
+        while lock.share_count.load(Ordering::SeqCst) != 0 {
+            spin_loop();
+        }
+        WriteGuard { lock,
+                     _data: PhantomData }
+    }
+}
+
+impl<'a, T: Send + Sync + ?Sized> Deref for UpgradeableGuard<'a, T>
+{
+    type Target = T;
+
+    fn deref(&self) -> &'a Self::Target
+    {
+        unsafe { &*self.lock.content.get() }
+    }
+}
+
+impl<'a, T: Send + Sync + ?Sized> Drop for UpgradeableGuard<'a, T>
+{
+    fn drop(&mut self)
+    {
+        self.lock.share_count.fetch_sub(1, Ordering::SeqCst);
+        unsafe { self.lock.upgrade_advisor.unlock() };
     }
 }
 
@@ -130,6 +324,7 @@ impl<T: Send + Sync + ?Sized> RwLock<T>
         where T: Sized
     {
         Self { advisor: Advisor::new(),
+               upgrade_advisor: Advisor::new(),
                share_count: AtomicUsize::new(0),
                content: UnsafeCell::new(content) }
     }
@@ -153,8 +348,140 @@ impl<T: Send + Sync + ?Sized> RwLock<T>
     {
         WriteGuard::new(self)
     }
+
+    /// Non-exclusively locks access to the content with the option to upgrade
+    /// to exclusive access later, blocking execution if another core is
+    /// already exclusively accessing it or holds an upgradeable guard.
+    ///
+    /// Regular readers can still acquire the lock alongside the upgradeable
+    /// reader, but only one upgradeable reader may exist at a time, so
+    /// acquiring two upgradeable guards on the same core deadlocks.
+    ///
+    /// Returns an [`UpgradeableGuard`] which allows shared immutable access to
+    /// the content and holds the lock until dropped or upgraded.
+    pub fn upgradeable_rlock(&self) -> UpgradeableGuard<T>
+    {
+        UpgradeableGuard::new(self)
+    }
+
+    /// Attempts to non-exclusively lock access to the content without
+    /// blocking.
+    ///
+    /// Returns a [`ReadGuard`] which allows shared immutable access to the
+    /// content and holds the lock until dropped, or [`None`] if the content is
+    /// being exclusively accessed.
+    pub fn try_rlock(&self) -> Option<ReadGuard<T>>
+    {
+        ReadGuard::try_new(self)
+    }
+
+    /// Attempts to exclusively lock access to the content without blocking.
+    ///
+    /// Returns a [`WriteGuard`] which allows exclusive mutable access to the
+    /// content and holds the lock until dropped, or [`None`] if the content is
+    /// being accessed.
+    pub fn try_wlock(&self) -> Option<WriteGuard<T>>
+    {
+        WriteGuard::try_new(self)
+    }
+
+    /// Non-exclusively locks access to the content, blocking execution for a
+    /// bounded amount of time if another core is already exclusively accessing
+    /// it.
+    ///
+    /// * `max_spins`: Maximum number of times to spin waiting for the lock.
+    ///
+    /// Returns a [`ReadGuard`] which allows shared immutable access to the
+    /// content and holds the lock until dropped, or [`None`] if the content
+    /// remained exclusively accessed.
+    pub fn rlock_timeout(&self, max_spins: u32) -> Option<ReadGuard<T>>
+    {
+        ReadGuard::new_timeout(self, max_spins)
+    }
+
+    /// Exclusively locks access to the content, blocking execution for a
+    /// bounded amount of time if another core is already accessing it.
+    ///
+    /// * `max_spins`: Maximum number of times to spin waiting for each stage of
+    ///   the acquisition.
+    ///
+    /// Returns a [`WriteGuard`] which allows exclusive mutable access to the
+    /// content and holds the lock until dropped, or [`None`] if the content
+    /// remained accessed.
+    pub fn wlock_timeout(&self, max_spins: u32) -> Option<WriteGuard<T>>
+    {
+        WriteGuard::new_timeout(self, max_spins)
+    }
 }
 
 unsafe impl<T: Send + Sync + ?Sized> Send for RwLock<T> {}
 
 unsafe impl<T: Send + Sync + ?Sized> Sync for RwLock<T> {}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn rwlock_shared()
+    {
+        let lock = RwLock::new(0);
+        let guard0 = lock.rlock();
+        let guard1 = lock.try_rlock().unwrap();
+        assert!(lock.try_wlock().is_none());
+        assert_eq!(*guard0 + *guard1, 0);
+        drop(guard0);
+        drop(guard1);
+        *lock.wlock() += 1;
+        let guard = lock.try_wlock().unwrap();
+        assert!(lock.try_rlock().is_none());
+        assert_eq!(*guard, 1);
+    }
+
+    #[test]
+    fn rwlock_upgrade()
+    {
+        let lock = RwLock::new(0);
+        let guard = lock.upgradeable_rlock();
+        // Regular readers can coexist with the upgradeable reader, but writers can't.
+        assert_eq!(*lock.rlock(), 0);
+        assert!(lock.try_wlock().is_none());
+        assert_eq!(*guard, 0);
+        let mut guard = guard.upgrade();
+        assert!(lock.try_rlock().is_none());
+        *guard += 1;
+        drop(guard);
+        assert_eq!(*lock.rlock(), 1);
+        assert!(lock.try_wlock().is_some());
+    }
+
+    #[test]
+    fn rwlock_downgrade()
+    {
+        let lock = RwLock::new(0);
+        let mut guard = lock.wlock();
+        *guard += 1;
+        let guard = guard.downgrade();
+        assert_eq!(*lock.try_rlock().unwrap(), 1);
+        assert!(lock.try_wlock().is_none());
+        assert_eq!(*guard, 1);
+        drop(guard);
+        assert!(lock.try_wlock().is_some());
+    }
+
+    #[test]
+    fn rwlock_timeout()
+    {
+        let lock = RwLock::new(0);
+        let guard = lock.rlock_timeout(0).unwrap();
+        assert!(lock.wlock_timeout(100).is_none());
+        assert!(lock.rlock_timeout(100).is_some());
+        drop(guard);
+        let guard = lock.wlock_timeout(100).unwrap();
+        assert!(lock.rlock_timeout(100).is_none());
+        assert!(lock.wlock_timeout(100).is_none());
+        drop(guard);
+        assert!(lock.wlock_timeout(0).is_some());
+    }
+}
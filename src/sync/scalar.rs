@@ -0,0 +1,98 @@
+//! Atomic scalars.
+//!
+//! [`AtomicScalar`] stores the bit pattern of a [`Scalar`] in an atomic integer
+//! so that tunables can be written from regular code while interrupt handlers
+//! read them without taking a lock.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::math::Scalar;
+
+/// Scalar that can be shared between contexts.
+#[derive(Debug)]
+pub struct AtomicScalar
+{
+    /// Bit pattern of the value.
+    bits: AtomicU32,
+}
+
+impl AtomicScalar
+{
+    /// Creates and initializes a new atomic scalar.
+    ///
+    /// * `val`: Initial value.
+    ///
+    /// Returns the newly created atomic scalar.
+    pub const fn new(val: f32) -> Self
+    {
+        Self { bits: AtomicU32::new(val.to_bits()) }
+    }
+
+    /// Loads the value.
+    ///
+    /// * `order`: Memory ordering of the load.
+    ///
+    /// Returns the loaded value.
+    pub fn load(&self, order: Ordering) -> Scalar
+    {
+        Scalar::from_val(f32::from_bits(self.bits.load(order)))
+    }
+
+    /// Stores a new value.
+    ///
+    /// * `val`: Value to store.
+    /// * `order`: Memory ordering of the store.
+    pub fn store(&self, val: Scalar, order: Ordering)
+    {
+        self.bits.store(val.to_val().to_bits(), order);
+    }
+
+    /// Stores a new value if the current value is the expected one.
+    ///
+    /// Values are compared by bit pattern, so zero and negative zero are
+    /// different whereas a NaN matches an identical NaN.
+    ///
+    /// * `current`: Expected value.
+    /// * `new`: Value to store.
+    /// * `success`: Memory ordering if the value is stored.
+    /// * `failure`: Memory ordering of the load if the value is not stored.
+    ///
+    /// Returns the previous value, as an error if it was not the expected one.
+    pub fn compare_exchange(&self, current: Scalar, new: Scalar, success: Ordering, failure: Ordering)
+                            -> Result<Scalar, Scalar>
+    {
+        self.bits
+            .compare_exchange(current.to_val().to_bits(), new.to_val().to_bits(), success, failure)
+            .map(|bits| Scalar::from_val(f32::from_bits(bits)))
+            .map_err(|bits| Scalar::from_val(f32::from_bits(bits)))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn atomic_scalar_round_trip()
+    {
+        let scalar = AtomicScalar::new(0.5);
+        assert_eq!(scalar.load(Ordering::Relaxed), Scalar::from_val(0.5));
+        for val in [-0.0, f32::MIN_POSITIVE, f32::MIN_POSITIVE / 2.0, -1.0e-30, f32::MAX] {
+            scalar.store(Scalar::from_val(val), Ordering::Relaxed);
+            assert_eq!(scalar.load(Ordering::Relaxed).to_val().to_bits(), val.to_bits());
+        }
+    }
+
+    #[test]
+    fn atomic_scalar_compare_exchange()
+    {
+        let scalar = AtomicScalar::new(0.0);
+        let (zero, neg_zero, one) = (Scalar::from_val(0.0), Scalar::from_val(-0.0), Scalar::from_val(1.0));
+        assert!(scalar.compare_exchange(neg_zero, one, Ordering::Relaxed, Ordering::Relaxed)
+                      .is_err());
+        assert_eq!(scalar.compare_exchange(zero, one, Ordering::Relaxed, Ordering::Relaxed),
+                   Ok(zero));
+        assert_eq!(scalar.load(Ordering::Relaxed), one);
+    }
+}
@@ -0,0 +1,100 @@
+//! Counting semaphores.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{send_event, wait_for_event};
+
+/// Counting semaphore.
+#[derive(Debug)]
+pub struct Semaphore
+{
+    /// Number of permits available.
+    permits: AtomicUsize,
+}
+
+impl Semaphore
+{
+    /// Creates and initializes a new semaphore.
+    ///
+    /// * `count`: Number of permits initially available.
+    ///
+    /// Returns the newly created semaphore.
+    pub const fn new(count: usize) -> Self
+    {
+        Self { permits: AtomicUsize::new(count) }
+    }
+
+    /// Takes a permit, putting the core to sleep until one becomes available.
+    pub fn acquire(&self)
+    {
+        while !self.try_acquire() {
+            wait_for_event();
+        }
+    }
+
+    /// Attempts to take a permit without blocking.
+    ///
+    /// Returns whether a permit was taken.
+    pub fn try_acquire(&self) -> bool
+    {
+        self.permits
+            .fetch_update(Ordering::Acquire, Ordering::Relaxed, |permits| permits.checked_sub(1))
+            .is_ok()
+    }
+
+    /// Returns a permit, waking up any cores waiting for one.
+    pub fn release(&self)
+    {
+        self.permits.fetch_add(1, Ordering::Release);
+        send_event();
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available(&self) -> usize
+    {
+        self.permits.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn semaphore_try_acquire()
+    {
+        let sem = Semaphore::new(2);
+        assert!(sem.try_acquire());
+        assert!(sem.try_acquire());
+        assert!(!sem.try_acquire());
+        assert_eq!(sem.available(), 0);
+        sem.release();
+        assert_eq!(sem.available(), 1);
+        assert!(sem.try_acquire());
+    }
+
+    #[test]
+    fn semaphore_contention()
+    {
+        let sem = Semaphore::new(1);
+        let order = AtomicUsize::new(0);
+        sem.acquire();
+        thread::scope(|scope| {
+            let waiter = scope.spawn(|| {
+                                  sem.acquire();
+                                  // The holder must have finished before this point.
+                                  assert_eq!(order.fetch_add(1, Ordering::SeqCst), 1);
+                                  sem.release();
+                              });
+            thread::yield_now();
+            assert_eq!(order.fetch_add(1, Ordering::SeqCst), 0);
+            sem.release();
+            waiter.join().unwrap();
+        });
+        assert_eq!(order.load(Ordering::SeqCst), 2);
+        assert_eq!(sem.available(), 1);
+    }
+}
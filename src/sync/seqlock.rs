@@ -0,0 +1,157 @@
+//! Sequence lock.
+//!
+//! [`SeqLock`] lets any number of readers copy a small value while a writer,
+//! possibly running in an interrupt handler, updates it without ever having
+//! to wait for the readers.
+
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
+/// Read-mostly lock for values that can be copied.
+///
+/// The value is protected by a sequence counter that is odd while a write is
+/// in progress.  Readers copy the value without locking and retry if the
+/// counter changed in the meantime, which means that they may observe a torn
+/// value that is immediately discarded, so `T` must be [`Copy`] to rule out
+/// types whose copies can't just be thrown away.
+#[derive(Debug)]
+pub struct SeqLock<T: Copy>
+{
+    /// Sequence counter, odd while a write is in progress.
+    seq: AtomicUsize,
+    /// Protected value.
+    content: UnsafeCell<T>,
+}
+
+impl<T: Copy> SeqLock<T>
+{
+    /// Creates and initializes a new sequence lock.
+    ///
+    /// * `content`: Initial value.
+    ///
+    /// Returns the newly created lock.
+    pub const fn new(content: T) -> Self
+    {
+        Self { seq: AtomicUsize::new(0),
+               content: UnsafeCell::new(content) }
+    }
+
+    /// Copies the protected value, retrying for as long as writes interfere.
+    ///
+    /// Returns the copied value.
+    pub fn read(&self) -> T
+    {
+        loop {
+            if let Some(content) = self.try_read() {
+                return content;
+            }
+            spin_loop();
+        }
+    }
+
+    /// Replaces the protected value, waiting for other writers to finish.
+    ///
+    /// * `content`: New value.
+    pub fn write(&self, content: T)
+    {
+        let seq = self.begin_write();
+        unsafe { write_volatile(self.content.get(), content) };
+        self.end_write(seq);
+    }
+
+    /// Copies the protected value once.
+    ///
+    /// Returns the copied value, or nothing if a write was in progress.
+    fn try_read(&self) -> Option<T>
+    {
+        let seq = self.seq.load(Ordering::Acquire);
+        if seq & 0x1 != 0 {
+            return None;
+        }
+        let content = unsafe { read_volatile(self.content.get()) };
+        // Order the copy before checking the counter again.
+        fence(Ordering::Acquire);
+        if self.seq.load(Ordering::Relaxed) != seq {
+            return None;
+        }
+        Some(content)
+    }
+
+    /// Marks the start of a write, waiting for other writers to finish.
+    ///
+    /// Returns the new, odd, value of the sequence counter.
+    fn begin_write(&self) -> usize
+    {
+        loop {
+            let seq = self.seq.load(Ordering::Relaxed);
+            if seq & 0x1 == 0
+               && self.seq
+                      .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+                      .is_ok()
+            {
+                // Order the counter update before the write to the content.
+                fence(Ordering::Release);
+                return seq + 1;
+            }
+            spin_loop();
+        }
+    }
+
+    /// Marks the end of a write.
+    ///
+    /// * `seq`: Value returned by [`Self::begin_write`].
+    fn end_write(&self, seq: usize)
+    {
+        self.seq.store(seq.wrapping_add(1), Ordering::Release);
+    }
+}
+
+unsafe impl<T: Copy + Send> Send for SeqLock<T> {}
+
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+#[cfg(test)]
+mod tests
+{
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn seqlock_retry()
+    {
+        let lock = SeqLock::new((1, 1));
+        assert_eq!(lock.try_read(), Some((1, 1)));
+        let seq = lock.begin_write();
+        unsafe { *lock.content.get() = (2, 1) };
+        // The reader must not see the half written value.
+        assert_eq!(lock.try_read(), None);
+        unsafe { *lock.content.get() = (2, 2) };
+        lock.end_write(seq);
+        assert_eq!(lock.try_read(), Some((2, 2)));
+        assert_eq!(lock.read(), (2, 2));
+    }
+
+    #[test]
+    fn seqlock_concurrent()
+    {
+        const COUNT: u64 = 10000;
+        let lock = SeqLock::new([0u64; 8]);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                     for val in 1 ..= COUNT {
+                         lock.write([val; 8]);
+                     }
+                 });
+            let mut last = 0;
+            while last < COUNT {
+                let content = lock.read();
+                assert!(content.iter().all(|val| *val == content[0]));
+                assert!(content[0] >= last);
+                last = content[0];
+            }
+        });
+    }
+}
@@ -0,0 +1,197 @@
+//! Single-producer single-consumer queue.
+//!
+//! [`SpscQueue`] passes values from one context to another without locking,
+//! which makes it suitable for handing data from an interrupt handler to the
+//! main loop, as the handler can never end up spinning on a lock held by the
+//! code that it interrupted.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Bounded lock-free queue with one producer and one consumer.
+pub struct SpscQueue<T, const N: usize>
+{
+    /// Storage for the queued values.
+    buf: UnsafeCell<MaybeUninit<[T; N]>>,
+    /// Number of values popped since creation, wrapping around.
+    head: AtomicUsize,
+    /// Number of values pushed since creation, wrapping around.
+    tail: AtomicUsize,
+    /// Whether some context is currently pushing.
+    pushing: AtomicBool,
+    /// Whether some context is currently popping.
+    popping: AtomicBool,
+}
+
+impl<T, const N: usize> SpscQueue<T, N>
+{
+    /// Creates and initializes a new empty queue.
+    ///
+    /// Returns the newly created queue.
+    pub const fn new() -> Self
+    {
+        assert!(N > 0, "Queue capacity must not be zero");
+        Self { buf: UnsafeCell::new(MaybeUninit::uninit()),
+               head: AtomicUsize::new(0),
+               tail: AtomicUsize::new(0),
+               pushing: AtomicBool::new(false),
+               popping: AtomicBool::new(false) }
+    }
+
+    /// Appends a value to the end of the queue.
+    ///
+    /// Only one context may push at a time, so a push that interrupts another
+    /// push on the same queue fails as if the queue was full.
+    ///
+    /// * `val`: Value to append.
+    ///
+    /// Returns the value back as an error if the queue is full.
+    pub fn push(&self, val: T) -> Result<(), T>
+    {
+        if self.pushing.swap(true, Ordering::Acquire) {
+            return Err(val);
+        }
+        let tail = self.tail.load(Ordering::Relaxed);
+        // Acquire pairs with the release in pop so that the slot is no longer being
+        // read.
+        let head = self.head.load(Ordering::Acquire);
+        let res = if tail.wrapping_sub(head) == N {
+            Err(val)
+        } else {
+            unsafe { self.slot(tail).write(val) };
+            self.tail.store(tail.wrapping_add(1), Ordering::Release);
+            Ok(())
+        };
+        self.pushing.store(false, Ordering::Release);
+        res
+    }
+
+    /// Removes the value at the front of the queue.
+    ///
+    /// Only one context may pop at a time, so a pop that interrupts another pop
+    /// on the same queue fails as if the queue was empty.
+    ///
+    /// Returns the removed value, or nothing if the queue is empty.
+    pub fn pop(&self) -> Option<T>
+    {
+        if self.popping.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        let head = self.head.load(Ordering::Relaxed);
+        // Acquire pairs with the release in push so that the value is fully written.
+        let tail = self.tail.load(Ordering::Acquire);
+        let res = if head == tail {
+            None
+        } else {
+            let val = unsafe { self.slot(head).read() };
+            self.head.store(head.wrapping_add(1), Ordering::Release);
+            Some(val)
+        };
+        self.popping.store(false, Ordering::Release);
+        res
+    }
+
+    /// Counts the values in the queue, which may already be stale when
+    /// either side is running concurrently.
+    ///
+    /// Returns the number of queued values.
+    pub fn len(&self) -> usize
+    {
+        let head = self.head.load(Ordering::Acquire);
+        self.tail.load(Ordering::Acquire).wrapping_sub(head)
+    }
+
+    /// Checks whether the queue is empty, which may already be stale when
+    /// either side is running concurrently.
+    ///
+    /// Returns whether there are no queued values.
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+
+    /// Computes the location of the slot for the specified index.
+    ///
+    /// * `idx`: Wrapping index of the value.
+    ///
+    /// Returns a pointer to the slot.
+    fn slot(&self, idx: usize) -> *mut T
+    {
+        unsafe { (self.buf.get() as *mut T).add(idx % N) }
+    }
+}
+
+impl<T, const N: usize> Drop for SpscQueue<T, N>
+{
+    fn drop(&mut self)
+    {
+        while self.pop().is_some() {}
+    }
+}
+
+unsafe impl<T: Send, const N: usize> Send for SpscQueue<T, N> {}
+
+unsafe impl<T: Send, const N: usize> Sync for SpscQueue<T, N> {}
+
+#[cfg(test)]
+mod tests
+{
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn spsc_full_empty()
+    {
+        let queue = SpscQueue::<u32, 2>::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.push(1), Ok(()));
+        assert_eq!(queue.push(2), Ok(()));
+        assert_eq!(queue.push(3), Err(3));
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn spsc_wraparound()
+    {
+        let queue = SpscQueue::<usize, 3>::new();
+        for val in 0 .. 10 {
+            assert_eq!(queue.push(val), Ok(()));
+            assert_eq!(queue.push(val + 100), Ok(()));
+            assert_eq!(queue.pop(), Some(val));
+            assert_eq!(queue.pop(), Some(val + 100));
+        }
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn spsc_threads()
+    {
+        const COUNT: usize = 10000;
+        let queue = SpscQueue::<usize, 16>::new();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                     for val in 0 .. COUNT {
+                         while queue.push(val).is_err() {
+                             thread::yield_now();
+                         }
+                     }
+                 });
+            let mut expected = 0;
+            while expected < COUNT {
+                match queue.pop() {
+                    Some(val) => {
+                        assert_eq!(val, expected);
+                        expected += 1;
+                    }
+                    None => thread::yield_now(),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,360 @@
+//! Monotonic clock backed by the ARM generic timer.
+//!
+//! The physical counter runs at a fixed frequency, reported by the
+//! `CNTFRQ_EL0` register, from the moment the system is powered on, and is
+//! shared by all the cores, making it suitable to measure the time between
+//! events regardless of where they happen.
+//!
+//! Each core also has its own physical timer which raises a PPI when the
+//! counter reaches a programmed value, which [`Timer`] uses to run one-shot
+//! callbacks.
+
+#[cfg(not(test))]
+extern crate alloc;
+
+#[cfg(not(test))]
+use alloc::vec::Vec;
+#[cfg(not(test))]
+use core::arch::asm;
+#[cfg(not(test))]
+use core::hint::spin_loop;
+use core::ops::{Add, AddAssign, Sub};
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+#[cfg(not(test))]
+use crate::irq::IRQ;
+#[cfg(not(test))]
+use crate::sync::{without_interrupts, Lazy, Lock};
+#[cfg(not(test))]
+use crate::{cpu_id, CPU_COUNT};
+
+/// Non-secure physical timer PPI.
+#[cfg(not(test))]
+const TIMER_IRQ: u32 = 30;
+
+/// Global software timer instance.
+#[cfg(not(test))]
+pub static TIMER: Lazy<Timer> = Lazy::new(Timer::new);
+
+/// One-shot callback scheduler driven by the physical timer of each core.
+#[cfg(not(test))]
+#[derive(Debug)]
+pub struct Timer
+{
+    /// Counter values at which to call each pending callback, per core.
+    #[allow(clippy::type_complexity)]
+    pending: [Lock<Vec<(u64, fn())>>; CPU_COUNT],
+}
+
+/// Point in time since the counter started.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Instant
+{
+    /// Time since the counter started.
+    since_boot: Duration,
+}
+
+impl Instant
+{
+    /// Creates and initializes a new instant from raw counter ticks.
+    ///
+    /// * `ticks`: Value of the counter.
+    /// * `freq`: Frequency of the counter in Hertz.
+    ///
+    /// Returns the newly created instant.
+    ///
+    /// Panics if the frequency is zero.
+    fn from_ticks(ticks: u64, freq: u64) -> Self
+    {
+        assert!(freq != 0, "Timer frequency not set");
+        let micros = ticks as u128 * 1000000 / freq as u128;
+        Self { since_boot: Duration::from_micros(micros as u64) }
+    }
+
+    /// Computes the time elapsed since this instant.
+    ///
+    /// Returns the elapsed time.
+    #[cfg(not(test))]
+    pub fn elapsed(self) -> Duration
+    {
+        now() - self
+    }
+
+    /// Computes the time elapsed between an earlier instant and this one.
+    ///
+    /// * `earlier`: Earlier instant.
+    ///
+    /// Returns the elapsed time, or zero if `earlier` is actually later.
+    pub fn duration_since(self, earlier: Self) -> Duration
+    {
+        self.since_boot.saturating_sub(earlier.since_boot)
+    }
+}
+
+impl Add<Duration> for Instant
+{
+    type Output = Self;
+
+    fn add(self, other: Duration) -> Self
+    {
+        Self { since_boot: self.since_boot + other }
+    }
+}
+
+impl AddAssign<Duration> for Instant
+{
+    fn add_assign(&mut self, other: Duration)
+    {
+        self.since_boot += other;
+    }
+}
+
+impl Sub<Self> for Instant
+{
+    type Output = Duration;
+
+    fn sub(self, other: Self) -> Duration
+    {
+        self.duration_since(other)
+    }
+}
+
+/// Cumulative and maximum execution time of a recurring task, which can be
+/// updated from any core without locking.
+#[derive(Debug, Default)]
+pub struct TimeStats
+{
+    /// Total execution time in nanoseconds.
+    total: AtomicU64,
+    /// Longest execution time in nanoseconds.
+    max: AtomicU64,
+}
+
+impl TimeStats
+{
+    /// Creates and initializes new empty execution time statistics.
+    ///
+    /// Returns the newly created statistics.
+    pub const fn new() -> Self
+    {
+        Self { total: AtomicU64::new(0),
+               max: AtomicU64::new(0) }
+    }
+
+    /// Accounts for one execution of the task.
+    ///
+    /// * `start`: Instant at which the execution started.
+    /// * `end`: Instant at which the execution finished.
+    pub fn record(&self, start: Instant, end: Instant)
+    {
+        let nanos = (end - start).as_nanos().try_into().unwrap_or(u64::MAX);
+        self.total.fetch_add(nanos, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    /// Returns the total execution time.
+    pub fn total(&self) -> Duration
+    {
+        Duration::from_nanos(self.total.load(Ordering::Relaxed))
+    }
+
+    /// Returns the longest execution time.
+    pub fn max(&self) -> Duration
+    {
+        Duration::from_nanos(self.max.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(not(test))]
+impl Timer
+{
+    /// Creates and initializes a new software timer.
+    ///
+    /// Returns the newly created timer.
+    fn new() -> Self
+    {
+        IRQ.register(TIMER_IRQ, Self::expire);
+        Self { pending: [(); CPU_COUNT].map(|_| Lock::new(Vec::new())) }
+    }
+
+    /// Schedules a handler to be called once after the specified delay.
+    ///
+    /// The handler is called from the IRQ handler of the timer on the core
+    /// that scheduled it, so it is subject to the same restrictions as any
+    /// other IRQ handler.
+    ///
+    /// * `duration`: Delay after which to call the handler.
+    /// * `handler`: Handler function to call.
+    pub fn after(&self, duration: Duration, handler: fn())
+    {
+        let (ticks, freq) = counter();
+        let deadline = deadline(ticks, freq, duration);
+        // The timer IRQ takes the same lock on this core.
+        without_interrupts(|| {
+            let mut pending = self.pending[cpu_id()].lock();
+            pending.push((deadline, handler));
+            program(pending.iter().map(|(deadline, _)| *deadline).min());
+        });
+        // PPIs are banked, so they must be enabled on every core that uses them.
+        IRQ.enable(TIMER_IRQ);
+    }
+
+    /// Timer IRQ handler that calls the expired handlers and reprograms the
+    /// timer for the next deadline.
+    fn expire()
+    {
+        let (now, _) = counter();
+        let mut expired = Vec::new();
+        let mut pending = TIMER.pending[cpu_id()].lock();
+        pending.retain(|&(deadline, handler)| {
+                   if deadline > now {
+                       return true;
+                   }
+                   expired.push(handler);
+                   false
+               });
+        // The IRQ is level triggered, so the timer must stop asserting it before
+        // the dispatcher signals its end.
+        program(pending.iter().map(|(deadline, _)| *deadline).min());
+        drop(pending);
+        expired.iter().for_each(|handler| handler());
+    }
+}
+
+/// Reads the physical counter.
+///
+/// Returns the current instant.
+#[cfg(not(test))]
+pub fn now() -> Instant
+{
+    let (ticks, freq) = counter();
+    Instant::from_ticks(ticks, freq)
+}
+
+/// Returns the start of the counter in tests, which have no counter to read.
+#[cfg(test)]
+pub fn now() -> Instant
+{
+    Instant::default()
+}
+
+/// Spins the current core for at least the specified amount of time.
+///
+/// * `us`: Time to spin in microseconds.
+#[cfg(not(test))]
+pub fn delay_us(us: u64)
+{
+    let (ticks, freq) = counter();
+    let deadline = deadline(ticks, freq, Duration::from_micros(us));
+    while counter().0 < deadline {
+        spin_loop();
+    }
+}
+
+/// Computes the counter value after the specified amount of time.
+///
+/// * `ticks`: Current value of the counter.
+/// * `freq`: Frequency of the counter in Hertz.
+/// * `duration`: Amount of time to add.
+///
+/// Returns the computed counter value, saturating on overflow.
+fn deadline(ticks: u64, freq: u64, duration: Duration) -> u64
+{
+    let delta = duration.as_nanos() * freq as u128 / 1000000000;
+    ticks.saturating_add(delta.try_into().unwrap_or(u64::MAX))
+}
+
+/// Reads the physical counter and its frequency.
+///
+/// Returns the value of the counter and its frequency in Hertz.
+#[cfg(not(test))]
+fn counter() -> (u64, u64)
+{
+    let ticks: u64;
+    let freq: u64;
+    unsafe {
+        asm!(
+            "isb",
+            "mrs {ticks}, cntpct_el0",
+            "mrs {freq}, cntfrq_el0",
+            ticks = out (reg) ticks,
+            freq = out (reg) freq,
+            options (nomem, nostack, preserves_flags));
+    }
+    (ticks, freq)
+}
+
+/// Programs the physical timer of the current core.
+///
+/// * `deadline`: Counter value at which to raise the IRQ, or [`None`] to
+///   disable the timer.
+#[cfg(not(test))]
+fn program(deadline: Option<u64>)
+{
+    unsafe {
+        match deadline {
+            Some(deadline) => asm!(
+                    "msr cntp_cval_el0, {deadline}",
+                    "msr cntp_ctl_el0, {enable}",
+                    deadline = in (reg) deadline,
+                    enable = in (reg) 0x1u64,
+                    options (nomem, nostack, preserves_flags)),
+            None => asm!("msr cntp_ctl_el0, xzr", options(nomem, nostack, preserves_flags)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn instant_from_ticks()
+    {
+        let instant = Instant::from_ticks(54000000, 54000000);
+        assert_eq!(instant - Instant::default(), Duration::from_secs(1));
+        let instant = Instant::from_ticks(27, 54000000);
+        assert_eq!(instant - Instant::default(), Duration::ZERO);
+        let instant = Instant::from_ticks(u64::MAX, 54000000);
+        assert!(instant > Instant::from_ticks(u64::MAX - 54000000, 54000000));
+    }
+
+    #[test]
+    fn deadline_ticks()
+    {
+        assert_eq!(deadline(1000, 54000000, Duration::from_micros(1)), 1054);
+        assert_eq!(deadline(0, 54000000, Duration::from_secs(2)), 108000000);
+        assert_eq!(deadline(5, 1000000, Duration::from_nanos(999)), 5);
+        assert_eq!(deadline(u64::MAX - 1, 54000000, Duration::from_secs(1)), u64::MAX);
+    }
+
+    #[test]
+    fn instant_arithmetic()
+    {
+        let start = Instant::from_ticks(1000, 1000000);
+        let mut end = start + Duration::from_millis(16);
+        assert_eq!(end - start, Duration::from_millis(16));
+        assert_eq!(start - end, Duration::ZERO);
+        end += Duration::from_micros(667);
+        assert_eq!(end.duration_since(start), Duration::from_micros(16667));
+    }
+
+    #[test]
+    fn time_stats_record()
+    {
+        let stats = TimeStats::new();
+        assert_eq!(stats.total(), Duration::ZERO);
+        assert_eq!(stats.max(), Duration::ZERO);
+        let start = Instant::from_ticks(54000, 54000000);
+        stats.record(start, start + Duration::from_micros(30));
+        stats.record(start, start + Duration::from_micros(120));
+        stats.record(start, start + Duration::from_micros(50));
+        assert_eq!(stats.total(), Duration::from_micros(200));
+        assert_eq!(stats.max(), Duration::from_micros(120));
+        // Out of order instants count as no time at all.
+        stats.record(start + Duration::from_micros(10), start);
+        assert_eq!(stats.total(), Duration::from_micros(200));
+    }
+}
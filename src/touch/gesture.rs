@@ -0,0 +1,1369 @@
+//! Gesture recognition.
+//!
+//! Turns the touch points captured by the driver into translation, rotation,
+//! and scale deltas as well as discrete tap gestures.
+
+use core::time::Duration;
+
+#[cfg(not(test))]
+use super::TOUCH;
+use super::{Contacts, TouchPoint, MAX_POINTS};
+use crate::math::{Matrix, Normal, Quaternion, Scalar, Vector};
+
+/// Maximum distance that a finger can travel and still be considered a tap.
+const TAP_DISTANCE: f32 = 0.05;
+/// Default time that a finger can stay down and still be considered a tap.
+const TAP_DURATION: Duration = Duration::from_millis(250);
+/// Minimum distance between two fingers for a change in their distance to be
+/// considered a pinch.
+const MIN_PINCH_DISTANCE: f32 = 0.001;
+/// Maximum relative change in the distance between two fingers for their
+/// touch to still be considered a two finger tap instead of a pinch.
+const TWO_FINGER_TAP_SCALE: f32 = 0.2;
+/// Maximum distance between two taps for them to be considered a double tap.
+const DOUBLE_TAP_DISTANCE: f32 = 0.1;
+/// Default time between two taps for them to be considered a double tap.
+const DOUBLE_TAP_DURATION: Duration = Duration::from_millis(300);
+/// Default time that a finger must stay down without moving for the press to
+/// be considered a long press.
+const LONG_PRESS_DURATION: Duration = Duration::from_millis(500);
+/// Minimum speed, in normalized units per second, that a finger must be moving
+/// at when lifted for the drag to be considered a fling.
+const FLING_SPEED: f32 = 1.0;
+/// Default distance that the pivot must move for the translation to not be
+/// considered jitter.
+const TRANSLATION_DEADZONE: f32 = 0.002;
+/// Default angle in radians that the fingers must rotate for the rotation to
+/// not be considered jitter.
+const ROTATION_DEADZONE: f32 = 0.005;
+/// Default deviation from 1 that the scale must have to not be considered
+/// jitter.
+const SCALE_DEADZONE: f32 = 0.002;
+/// Largest distance that a prediction can extrapolate the contacts by, which
+/// keeps sudden velocity spikes from overshooting wildly.
+const MAX_PREDICTION: f32 = 0.1;
+/// Speed, in normalized units per second, below which the momentum carried
+/// after the fingers lift is considered to have stopped.
+const MIN_INERTIA_SPEED: f32 = 0.01;
+/// Minimum number of fingers that must move together for the movement to be
+/// considered a swipe.
+const SWIPE_FINGERS: usize = 3;
+/// Minimum distance that the fingers must travel on average between two
+/// samples for the movement to be considered a swipe.
+const MIN_SWIPE_DISTANCE: f32 = 0.001;
+/// Minimum fraction of the average displacement that every finger must travel
+/// along its direction for the fingers to be considered moving together.
+const SWIPE_COHERENCE: f32 = 0.5;
+
+/// Input changes since the last poll.
+#[derive(Clone, Copy, Debug)]
+pub struct Recognizer
+{
+    /// Last saved sample.
+    saved: Contacts<TouchPoint>,
+    /// Single finger press that might turn into a tap.
+    press: Option<Press>,
+    /// Two finger press that might turn into a two finger tap.
+    pair: Option<PairPress>,
+    /// Time that a finger must stay down without moving for the press to be
+    /// considered a long press.
+    long_press_duration: Duration,
+    /// Longest time that a finger can stay down for the press to be
+    /// considered a tap.
+    tap_duration: Duration,
+    /// Longest time between two taps for them to be considered a double tap.
+    double_tap_duration: Duration,
+    /// Position of and time since the last tap.
+    last_tap: Option<(Vector, Duration)>,
+    /// Last sampled single finger contact.
+    drag: Option<TouchPoint>,
+    /// Largest translation reported as no translation.
+    trans_deadzone: f32,
+    /// Largest rotation angle in radians reported as no rotation.
+    rot_deadzone: f32,
+    /// Largest deviation from 1 of the scale reported as no scale.
+    scale_deadzone: f32,
+    /// Translation accumulated since the two finger manipulation began, or
+    /// nothing once it has left the deadzone.
+    pending_trans: Option<Vector>,
+    /// Rotation accumulated since the two finger manipulation began, or
+    /// nothing once it has left the deadzone.
+    pending_rot: Option<Quaternion>,
+    /// Scale accumulated since the two finger manipulation began, or nothing
+    /// once it has left the deadzone.
+    pending_scale: Option<Scalar>,
+    /// Fraction of the velocity kept from one sample to the next after the
+    /// fingers lift.
+    inertia: Scalar,
+    /// Velocity carried after the fingers lifted.
+    momentum: Vector,
+    /// Amount moved since the last poll.
+    pub trans: Vector,
+    /// Amount rotated since the last poll.
+    pub rot: Quaternion,
+    /// Amount scaled since the last poll.
+    pub scale: Scalar,
+    /// Point between the two fingers in the previous poll about which the
+    /// rotation and scale are applied.
+    pub pivot: Vector,
+    /// Speed and direction of the contacts in normalized units per second.
+    pub velocity: Vector,
+    /// Velocity of the single finger drag that ended in the last poll, if fast
+    /// enough to be a fling.
+    pub fling: Option<Vector>,
+    /// Position of the tap that ended in the last poll.
+    pub tap: Option<Vector>,
+    /// Position of the double tap that ended in the last poll.
+    pub double_tap: Option<Vector>,
+    /// Point between the two fingers of the two finger tap that ended in the
+    /// last poll.
+    pub two_finger_tap: Option<Vector>,
+    /// Position of the press that became a long press in the last poll.
+    pub long_press: Option<Vector>,
+    /// Average amount moved since the last poll by three or more fingers
+    /// moving together in the same direction.
+    pub swipe: Option<Vector>,
+    /// Stage of the two finger manipulation as of the last poll.
+    pub phase: GesturePhase,
+}
+
+/// Stage of a two finger manipulation.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum GesturePhase
+{
+    /// No manipulation is in progress.
+    #[default]
+    Idle,
+    /// Two fingers started touching in the last poll.
+    Began,
+    /// Two fingers kept touching in the last poll.
+    Changed,
+    /// The fingers stopped being exactly two in the last poll.
+    Ended,
+}
+
+/// Single finger press.
+#[derive(Clone, Copy, Debug)]
+struct Press
+{
+    /// Position where the finger touched down.
+    origin: Vector,
+    /// Time since the finger touched down.
+    held: Duration,
+    /// Whether the press has already been reported as a long press.
+    is_long: bool,
+    /// Whether the finger has stayed close to where it touched down.
+    is_still: bool,
+}
+
+/// Two finger press.
+#[derive(Clone, Copy, Debug)]
+struct PairPress
+{
+    /// Tracking identifiers and positions where the fingers touched down.
+    origins: [(u32, Vector); 2],
+    /// Time since the second finger touched down.
+    held: Duration,
+    /// Whether the fingers have stayed close to where they touched down.
+    is_still: bool,
+}
+
+impl Recognizer
+{
+    /// Creates and initializes a new gesture recognizer.
+    ///
+    /// Returns the newly created recognizer.
+    pub fn new() -> Self
+    {
+        Self { saved: Contacts::default(),
+               press: None,
+               pair: None,
+               long_press_duration: LONG_PRESS_DURATION,
+               tap_duration: TAP_DURATION,
+               double_tap_duration: DOUBLE_TAP_DURATION,
+               last_tap: None,
+               drag: None,
+               trans_deadzone: TRANSLATION_DEADZONE,
+               rot_deadzone: ROTATION_DEADZONE,
+               scale_deadzone: SCALE_DEADZONE,
+               pending_trans: None,
+               pending_rot: None,
+               pending_scale: None,
+               inertia: Scalar::from_val(0.0),
+               momentum: Vector::default(),
+               trans: Vector::default(),
+               rot: Quaternion::default(),
+               scale: Scalar::default(),
+               pivot: Vector::default(),
+               velocity: Vector::default(),
+               fling: None,
+               tap: None,
+               double_tap: None,
+               two_finger_tap: None,
+               long_press: None,
+               swipe: None,
+               phase: GesturePhase::Idle }
+    }
+
+    /// Forgets the last sample and every pending gesture so that the next
+    /// sample is treated as the start of a new gesture, keeping the settings.
+    ///
+    /// This avoids a spurious large delta when the manipulation is resumed
+    /// after having been ignored for a while.
+    pub fn reset(&mut self)
+    {
+        *self = Self { long_press_duration: self.long_press_duration,
+                       tap_duration: self.tap_duration,
+                       double_tap_duration: self.double_tap_duration,
+                       trans_deadzone: self.trans_deadzone,
+                       rot_deadzone: self.rot_deadzone,
+                       scale_deadzone: self.scale_deadzone,
+                       inertia: self.inertia,
+                       ..Self::new() };
+    }
+
+    /// Returns the amount translated since the last sample.
+    #[cfg(not(test))]
+    pub fn translated(&self) -> Vector
+    {
+        self.trans
+    }
+
+    /// Returns the amount rotated since last sampled.
+    #[cfg(not(test))]
+    pub fn rotated(&self) -> Quaternion
+    {
+        self.rot
+    }
+
+    /// Returns the amount scaled since the last sample, as the ratio between
+    /// the new and the old distance between the two fingers.
+    #[cfg(not(test))]
+    pub fn scaled(&self) -> Scalar
+    {
+        self.scale
+    }
+
+    /// Computes the composite transformation of the two finger manipulation
+    /// since the last sample.
+    ///
+    /// The transformation rotates and scales about [`Self::pivot`], which is
+    /// the middle point between the two fingers in the previous sample, and
+    /// then translates the pivot to the middle point between the two fingers
+    /// in the latest sample, so that anything under the fingers follows them.
+    ///
+    /// Returns the computed matrix, which is the identity unless exactly two
+    /// fingers are touching.
+    pub fn transform(&self) -> Matrix
+    {
+        let to_origin = Matrix::from_translation(Vector::default() - self.pivot);
+        let manip = Matrix::from_components(self.pivot + self.trans, self.rot, self.scale);
+        manip * to_origin
+    }
+
+    /// Returns the speed and direction of the contacts at the last sample, in
+    /// normalized units per second.
+    #[cfg(not(test))]
+    pub fn velocity(&self) -> Vector
+    {
+        self.velocity
+    }
+
+    /// Extrapolates where the contacts will be after the specified amount of
+    /// time, assuming that they keep moving at the velocity of the last
+    /// sample, to compensate for input lag.
+    ///
+    /// * `ahead`: Time to extrapolate by, usually the time until the next frame
+    ///   is displayed.
+    ///
+    /// Returns the predicted position of the dragging finger or of the point
+    /// between the two fingers, never further than a small distance from the
+    /// current position, or the origin if neither gesture is in progress.
+    pub fn predict(&self, ahead: Duration) -> Vector
+    {
+        let current = match (self.drag, self.phase) {
+            (Some(point), _) => point.pos,
+            (None, GesturePhase::Began | GesturePhase::Changed) => self.pivot + self.trans,
+            (None, _) => return Vector::default(),
+        };
+        let delta = self.velocity * Scalar::from_val(ahead.as_secs_f32());
+        let dist = delta.length();
+        let max_dist = Scalar::from_val(MAX_PREDICTION);
+        if dist <= max_dist {
+            return current + delta;
+        }
+        current + delta * (max_dist / dist)
+    }
+
+    /// Returns the release velocity of the single finger drag that ended since
+    /// the last sample, if it was fast enough to be a fling.
+    #[cfg(not(test))]
+    pub fn fling(&self) -> Option<Vector>
+    {
+        self.fling
+    }
+
+    /// Returns the position of the tap that ended since the last sample, if
+    /// any.
+    #[cfg(not(test))]
+    pub fn tapped(&self) -> Option<Vector>
+    {
+        self.tap
+    }
+
+    /// Returns the position of the double tap that ended since the last
+    /// sample, if any.
+    ///
+    /// The second tap of a double tap is also reported by [`Self::tapped`].
+    #[cfg(not(test))]
+    pub fn double_tapped(&self) -> Option<Vector>
+    {
+        self.double_tap
+    }
+
+    /// Returns the point between the two fingers of the two finger tap that
+    /// ended since the last sample, if any.
+    ///
+    /// Both fingers must touch down and lift quickly without moving, so brief
+    /// pinches aren't mistaken for two finger taps.
+    #[cfg(not(test))]
+    pub fn two_finger_tap(&self) -> Option<Vector>
+    {
+        self.two_finger_tap
+    }
+
+    /// Returns the position of the press that became a long press since the
+    /// last sample, if any.
+    ///
+    /// A long press is reported only once, and is never followed by a tap when
+    /// the finger is lifted.
+    #[cfg(not(test))]
+    pub fn long_press(&self) -> Option<Vector>
+    {
+        self.long_press
+    }
+
+    /// Sets the time that a finger must stay down without moving for the press
+    /// to be considered a long press.
+    ///
+    /// * `duration`: Minimum duration of a long press.
+    #[cfg(not(test))]
+    pub fn set_long_press_duration(&mut self, duration: Duration)
+    {
+        self.long_press_duration = duration;
+    }
+
+    /// Sets the time windows within which presses are considered taps and
+    /// double taps.
+    ///
+    /// * `tap`: Longest time that a finger can stay down for the press to be
+    ///   considered a tap.
+    /// * `double_tap`: Longest time between two taps for them to be considered
+    ///   a double tap.
+    #[cfg(not(test))]
+    pub fn set_tap_durations(&mut self, tap: Duration, double_tap: Duration)
+    {
+        self.tap_duration = tap;
+        self.double_tap_duration = double_tap;
+    }
+
+    /// Sets the thresholds below which the two finger manipulation deltas
+    /// accumulated since the manipulation began are considered sensor noise
+    /// and reported as no change, which keeps anything being manipulated from
+    /// drifting while the fingers are held still.
+    ///
+    /// * `translation`: Largest distance reported as no translation.
+    /// * `rotation`: Largest angle in radians reported as no rotation.
+    /// * `scale`: Largest deviation from 1 reported as no scale.
+    #[cfg(not(test))]
+    pub fn set_deadzones(&mut self, translation: f32, rotation: f32, scale: f32)
+    {
+        self.trans_deadzone = translation;
+        self.rot_deadzone = rotation;
+        self.scale_deadzone = scale;
+    }
+
+    /// Sets how much momentum the translation carries after the fingers lift,
+    /// so that anything being dragged glides to a stop instead of stopping
+    /// abruptly.
+    ///
+    /// * `damping`: Fraction of the velocity kept from one sample to the next,
+    ///   below 1, with zero disabling the momentum.
+    #[cfg(not(test))]
+    pub fn set_inertia(&mut self, damping: Scalar)
+    {
+        self.inertia = damping;
+    }
+
+    /// Returns the average amount moved since the last sample by three or more
+    /// fingers swiping together in the same direction, if any.
+    #[cfg(not(test))]
+    pub fn three_finger_swipe(&self) -> Option<Vector>
+    {
+        self.swipe
+    }
+
+    /// Returns the stage of the two finger manipulation as of the last sample,
+    /// which tells a manipulation that just ended apart from no manipulation
+    /// at all.
+    #[cfg(not(test))]
+    pub fn phase(&self) -> GesturePhase
+    {
+        self.phase
+    }
+
+    /// Samples the touch sensor and computes the deltas since the last sample.
+    ///
+    /// * `elapsed`: Time elapsed since the last sample.
+    #[cfg(not(test))]
+    pub fn sample(&mut self, elapsed: Duration)
+    {
+        let contacts = TOUCH.detailed_points();
+        self.update(contacts, elapsed);
+    }
+
+    /// Computes the deltas between the last and the provided touch points.
+    ///
+    /// * `contacts`: Newly sampled touch points.
+    /// * `elapsed`: Time elapsed since the last sample.
+    fn update(&mut self, contacts: Contacts<TouchPoint>, elapsed: Duration)
+    {
+        let velocity = self.velocity;
+        // Two finger presses depend on the single finger press from before this
+        // sample.
+        self.update_two_finger_tap(contacts, elapsed);
+        self.update_taps(contacts, elapsed);
+        self.update_drag(contacts, elapsed);
+        let saved = core::mem::replace(&mut self.saved, contacts);
+        self.update_swipe(saved, contacts);
+        let was_active = matches!(self.phase, GesturePhase::Began | GesturePhase::Changed);
+        let new = if let [point0, point1] = *contacts.as_slice() {
+            self.phase = if was_active {
+                GesturePhase::Changed
+            } else {
+                // Every delta of a new manipulation starts within its deadzone.
+                self.pending_trans = Some(Vector::default());
+                self.pending_rot = Some(Quaternion::default());
+                self.pending_scale = Some(Scalar::default());
+                GesturePhase::Began
+            };
+            (point0, point1)
+        } else {
+            self.phase = if was_active {
+                GesturePhase::Ended
+            } else {
+                GesturePhase::Idle
+            };
+            self.update_momentum(velocity, saved, contacts, elapsed);
+            self.rot = Quaternion::default();
+            self.scale = Scalar::default();
+            self.pivot = Vector::default();
+            return;
+        };
+        // Put the points in the same order as in the last poll, and start over if
+        // either finger was replaced in the meantime.
+        let new = match *saved.as_slice() {
+            [old0, old1] if old0.id == new.1.id && old1.id == new.0.id => (new.1, new.0),
+            _ => new,
+        };
+        let old = match *saved.as_slice() {
+            [old0, old1] if old0.id == new.0.id && old1.id == new.1.id => (old0, old1),
+            _ => new,
+        };
+        let old = (old.0.pos, old.1.pos);
+        let new = (new.0.pos, new.1.pos);
+        // Compute the pivot of the two touch point samples, which is the middle point
+        // between their two respective touch points.
+        let old_pivot = old.0.lerp(old.1, Scalar::from_val(0.5));
+        let new_pivot = new.0.lerp(new.1, Scalar::from_val(0.5));
+        // Compute the translation, which is just the difference between the pivots.
+        // The deadzones apply to the deltas accumulated since the manipulation began,
+        // so that slow but steady motion eventually gets through, and then report the
+        // whole accumulated delta at once.
+        self.trans = new_pivot - old_pivot;
+        if let Some(pending) = self.pending_trans {
+            let pending = pending + self.trans;
+            if pending.length() <= Scalar::from_val(self.trans_deadzone) {
+                self.pending_trans = Some(pending);
+                self.trans = Vector::default();
+            } else {
+                self.pending_trans = None;
+                self.trans = pending;
+            }
+        }
+        self.pivot = old_pivot;
+        self.velocity = per_second(self.trans, elapsed);
+        // Compute the scale, which is the ratio between the distances separating the
+        // two contacts in each sample, unless the fingers were too close together to
+        // tell.
+        let old_dist = (old.1 - old.0).length();
+        let new_dist = (new.1 - new.0).length();
+        self.scale = if old_dist > Scalar::from_val(MIN_PINCH_DISTANCE) {
+            new_dist / old_dist
+        } else {
+            Scalar::default()
+        };
+        if let Some(pending) = self.pending_scale {
+            let pending = pending * self.scale;
+            let (min_scale, max_scale) = (1.0 - self.scale_deadzone, 1.0 + self.scale_deadzone);
+            if pending >= Scalar::from_val(min_scale) && pending <= Scalar::from_val(max_scale) {
+                self.pending_scale = Some(pending);
+                self.scale = Scalar::default();
+            } else {
+                self.pending_scale = None;
+                self.scale = pending;
+            }
+        }
+        // Compute the rotation by calculating the angle between the vectors created by
+        // the difference between the two contacts in each sample.
+        let old = Normal::from_vec(old.1 - old.0);
+        let new = Normal::from_vec(new.1 - new.0);
+        self.rot = Quaternion::from_normals(old, new);
+        if let Some(pending) = self.pending_rot {
+            let pending = self.rot * pending;
+            if pending.to_axis_angle().1 <= Scalar::from_val(self.rot_deadzone) {
+                self.pending_rot = Some(pending);
+                self.rot = Quaternion::default();
+            } else {
+                self.pending_rot = None;
+                self.rot = pending;
+            }
+        }
+    }
+
+    /// Keeps translating at a decaying velocity after the fingers lift.
+    ///
+    /// * `velocity`: Velocity of the contacts in the previous sample.
+    /// * `old`: Previously sampled touch points.
+    /// * `new`: Newly sampled touch points.
+    /// * `elapsed`: Time elapsed since the last sample.
+    fn update_momentum(&mut self, velocity: Vector, old: Contacts<TouchPoint>, new: Contacts<TouchPoint>,
+                       elapsed: Duration)
+    {
+        self.trans = Vector::default();
+        if !new.is_empty() {
+            self.momentum = Vector::default();
+            return;
+        }
+        // The momentum starts with the velocity of the fingers as they lift.
+        let momentum = if old.is_empty() { self.momentum } else { velocity };
+        self.momentum = momentum * self.inertia;
+        if self.momentum.length() < Scalar::from_val(MIN_INERTIA_SPEED) {
+            self.momentum = Vector::default();
+            return;
+        }
+        self.trans = self.momentum * Scalar::from_val(elapsed.as_secs_f32());
+        self.velocity = self.momentum;
+    }
+
+    /// Tracks single finger touch-down and touch-up transitions to detect taps,
+    /// double taps, and long presses.
+    ///
+    /// * `contacts`: Newly sampled touch points.
+    /// * `elapsed`: Time elapsed since the last sample.
+    fn update_taps(&mut self, contacts: Contacts<TouchPoint>, elapsed: Duration)
+    {
+        self.tap = None;
+        self.double_tap = None;
+        self.long_press = None;
+        if let Some((_, age)) = self.last_tap.as_mut() {
+            *age += elapsed;
+        }
+        match (contacts.as_slice(), self.press.as_mut()) {
+            // Touch-down, which only happens when no finger was touching before, as a
+            // finger that remains after the others lift is still part of a multi-finger
+            // touch.
+            ([point], None) if self.saved.is_empty() => {
+                self.press = Some(Press { origin: point.pos,
+                                          held: Duration::ZERO,
+                                          is_still: true,
+                                          is_long: false })
+            }
+            // Still touching.
+            ([point], Some(press)) => {
+                let max_sqdist = Scalar::from_val(TAP_DISTANCE * TAP_DISTANCE);
+                press.is_still &= press.origin.sq_distance(point.pos) <= max_sqdist;
+                press.held += elapsed;
+                if press.is_still && !press.is_long && press.held >= self.long_press_duration {
+                    press.is_long = true;
+                    self.long_press = Some(press.origin);
+                }
+            }
+            // Touch-up.
+            ([], press) => {
+                let press = if let Some(press) = press.copied() {
+                    press
+                } else {
+                    return;
+                };
+                self.press = None;
+                if !press.is_still || press.is_long || press.held > self.tap_duration {
+                    return;
+                }
+                self.tap = Some(press.origin);
+                let max_sqdist = Scalar::from_val(DOUBLE_TAP_DISTANCE * DOUBLE_TAP_DISTANCE);
+                match self.last_tap.take() {
+                    Some((last, age))
+                        if age <= self.double_tap_duration && last.sq_distance(press.origin) <= max_sqdist =>
+                    {
+                        self.double_tap = Some(press.origin)
+                    }
+                    _ => self.last_tap = Some((press.origin, Duration::ZERO)),
+                }
+            }
+            // Other fingers joined, so this can no longer be a tap.
+            (_, Some(press)) => press.is_still = false,
+            (_, None) => (),
+        }
+    }
+
+    /// Tracks two finger touch-down and touch-up transitions to detect two
+    /// finger taps.
+    ///
+    /// * `contacts`: Newly sampled touch points.
+    /// * `elapsed`: Time elapsed since the last sample.
+    fn update_two_finger_tap(&mut self, contacts: Contacts<TouchPoint>, elapsed: Duration)
+    {
+        self.two_finger_tap = None;
+        let max_sqdist = Scalar::from_val(TAP_DISTANCE * TAP_DISTANCE);
+        // Only fingers touching down together, or a second finger joining one that
+        // could still be a tap, start a two finger press, as fingers left over from
+        // other gestures or already dragging never make a tap.
+        let can_start = match (self.saved.len(), self.press) {
+            (0, _) => true,
+            (1, Some(press)) => press.is_still && !press.is_long && press.held + elapsed <= self.tap_duration,
+            _ => false,
+        };
+        match (contacts.as_slice(), self.pair.as_mut()) {
+            // Second finger touch-down.
+            ([point0, point1], None) if can_start => {
+                self.pair = Some(PairPress { origins: [(point0.id, point0.pos), (point1.id, point1.pos)],
+                                             held: Duration::ZERO,
+                                             is_still: true })
+            }
+            // Still touching or lifting, which doesn't have to happen in the same sample
+            // for both fingers.
+            (points @ ([_] | [_, _]), Some(pair)) => {
+                pair.held += elapsed;
+                for point in points {
+                    pair.is_still &= match pair.origins.iter().find(|(id, _)| *id == point.id) {
+                        Some((_, origin)) => origin.sq_distance(point.pos) <= max_sqdist,
+                        None => false,
+                    };
+                }
+                if let [point0, point1] = points {
+                    let (origin0, origin1) = (pair.origins[0].1, pair.origins[1].1);
+                    let old_dist = (origin1 - origin0).length();
+                    let new_dist = (point1.pos - point0.pos).length();
+                    // Fingers that touched down too close together can't tell a pinch apart,
+                    // so only their distances from the origins count.
+                    if old_dist > Scalar::from_val(MIN_PINCH_DISTANCE) {
+                        let ratio = new_dist / old_dist;
+                        let (min_ratio, max_ratio) = (1.0 - TWO_FINGER_TAP_SCALE, 1.0 + TWO_FINGER_TAP_SCALE);
+                        pair.is_still &= ratio >= Scalar::from_val(min_ratio) && ratio <= Scalar::from_val(max_ratio);
+                    }
+                }
+            }
+            // Touch-up.
+            ([], Some(pair)) => {
+                let pair = *pair;
+                self.pair = None;
+                if pair.is_still && pair.held <= self.tap_duration {
+                    let (origin0, origin1) = (pair.origins[0].1, pair.origins[1].1);
+                    self.two_finger_tap = Some(origin0.lerp(origin1, Scalar::from_val(0.5)));
+                }
+            }
+            // More fingers joined, so this can no longer be a two finger tap.
+            (_, Some(pair)) => pair.is_still = false,
+            (_, None) => (),
+        }
+    }
+
+    /// Tracks single finger drags to compute their velocity and detect flings.
+    ///
+    /// * `contacts`: Newly sampled touch points.
+    /// * `elapsed`: Time elapsed since the last sample.
+    fn update_drag(&mut self, contacts: Contacts<TouchPoint>, elapsed: Duration)
+    {
+        let velocity = self.velocity;
+        self.velocity = Vector::default();
+        self.fling = None;
+        match (contacts.as_slice(), self.drag) {
+            // Still dragging the same finger.
+            ([point], Some(last)) if point.id == last.id => {
+                self.velocity = per_second(point.pos - last.pos, elapsed);
+                self.drag = Some(*point);
+            }
+            // Touch-down.
+            ([point], _) => self.drag = Some(*point),
+            // Touch-up.
+            ([], Some(_)) => {
+                self.drag = None;
+                if velocity.length() >= Scalar::from_val(FLING_SPEED) {
+                    self.fling = Some(velocity);
+                }
+            }
+            _ => self.drag = None,
+        }
+    }
+
+    /// Detects three or more fingers swiping together in the same direction.
+    ///
+    /// * `old`: Previously sampled touch points.
+    /// * `new`: Newly sampled touch points.
+    fn update_swipe(&mut self, old: Contacts<TouchPoint>, new: Contacts<TouchPoint>)
+    {
+        self.swipe = None;
+        let (old, new) = (old.as_slice(), new.as_slice());
+        if new.len() < SWIPE_FINGERS || new.len() != old.len() {
+            return;
+        }
+        // Compute the displacement of every finger, and give up if any of them was
+        // replaced in the meantime.
+        let mut deltas = [Vector::default(); MAX_POINTS];
+        let mut total = Vector::default();
+        for (delta, point) in deltas.iter_mut().zip(new) {
+            let last = if let Some(last) = old.iter().find(|last| last.id == point.id) {
+                last
+            } else {
+                return;
+            };
+            *delta = point.pos - last.pos;
+            total += *delta;
+        }
+        let mean = total * Scalar::from_val(1.0 / new.len() as f32);
+        let sq_len = mean.dot(mean);
+        if sq_len < Scalar::from_val(MIN_SWIPE_DISTANCE * MIN_SWIPE_DISTANCE) {
+            return;
+        }
+        // Every finger must have travelled a significant fraction of the average
+        // displacement along its direction, which rules out pinches and rotations.
+        let min_dot = sq_len * Scalar::from_val(SWIPE_COHERENCE);
+        if deltas[.. new.len()].iter().any(|delta| delta.dot(mean) < min_dot) {
+            return;
+        }
+        self.swipe = Some(mean);
+    }
+}
+
+/// Converts a displacement into a velocity.
+///
+/// * `delta`: Displacement.
+/// * `elapsed`: Time taken by the displacement.
+///
+/// Returns the velocity in units per second, or zero if no time has elapsed.
+fn per_second(delta: Vector, elapsed: Duration) -> Vector
+{
+    if elapsed.is_zero() {
+        return Vector::default();
+    }
+    delta * Scalar::from_val(1.0 / elapsed.as_secs_f32())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::math::ApproxEq;
+
+    const FRAME: Duration = Duration::from_millis(16);
+
+    fn touch(id: u32, pos: Vector) -> TouchPoint
+    {
+        TouchPoint { id,
+                     pos,
+                     force: 0,
+                     area: 0 }
+    }
+
+    #[test]
+    fn recognizer_scale()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(-0.1, 0.0, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert_eq!(recog.scale, Scalar::from_val(1.0));
+        let point0 = Vector::from_components(-0.2, 0.0, 0.0);
+        let point1 = Vector::from_components(0.2, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(1, point1), touch(0, point0)]), FRAME);
+        assert!(recog.scale > Scalar::from_val(1.999) && recog.scale < Scalar::from_val(2.001));
+        let point0 = Vector::from_components(-0.1, 0.0, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert!(recog.scale > Scalar::from_val(0.499) && recog.scale < Scalar::from_val(0.501));
+        recog.update(Contacts::default(), FRAME);
+        assert_eq!(recog.scale, Scalar::from_val(1.0));
+    }
+
+    #[test]
+    fn recognizer_scale_degenerate()
+    {
+        let mut recog = Recognizer::new();
+        let point = Vector::from_components(0.0, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point), touch(1, point)]), FRAME);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point), touch(1, point1)]), FRAME);
+        assert_eq!(recog.scale, Scalar::from_val(1.0));
+    }
+
+    #[test]
+    fn recognizer_replaced_finger()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(-0.1, 0.0, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        let point1 = Vector::from_components(0.5, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert!(recog.trans.sq_distance(Vector::default()) > Scalar::from_val(0.0));
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(2, point1)]), FRAME);
+        assert_eq!(recog.trans.sq_distance(Vector::default()), Scalar::from_val(0.0));
+        assert_eq!(recog.scale, Scalar::from_val(1.0));
+    }
+
+    #[test]
+    fn recognizer_tap()
+    {
+        let mut recog = Recognizer::new();
+        let point = Vector::from_components(0.25, -0.5, 0.0);
+        let nudged = Vector::from_components(0.26, -0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, nudged)]), FRAME);
+        assert!(recog.tap.is_none());
+        recog.update(Contacts::default(), FRAME);
+        let tap = recog.tap.unwrap();
+        assert_eq!(tap.sq_distance(point), Scalar::from_val(0.0));
+        assert!(recog.double_tap.is_none());
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_tap_moved()
+    {
+        let mut recog = Recognizer::new();
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.0, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.5, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.0, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_tap_held()
+    {
+        let mut recog = Recognizer::new();
+        let point = Vector::from_components(0.0, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point)]), TAP_DURATION + FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_tap_fast_samples()
+    {
+        let mut recog = Recognizer::new();
+        let point = Vector::from_components(0.0, 0.0, 0.0);
+        let sample = Duration::from_millis(1);
+        // The window is measured in time, so a high sample rate doesn't shorten it.
+        for _ in 0 .. 100 {
+            recog.update(Contacts::from_slice(&[touch(0, point)]), sample);
+        }
+        recog.update(Contacts::default(), sample);
+        assert!(recog.tap.is_some());
+    }
+
+    #[test]
+    fn recognizer_tap_second_finger()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(0.0, 0.0, 0.0);
+        let point1 = Vector::from_components(0.5, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_tap_remaining_finger()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(0.0, 0.0, 0.0);
+        let point1 = Vector::from_components(0.5, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        for _ in 0 .. 3 {
+            recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        }
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_none());
+        // A new touch after every finger lifted is a tap again.
+        recog.update(Contacts::from_slice(&[touch(2, point0)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_some());
+    }
+
+    #[test]
+    fn recognizer_double_tap()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(0.0, 0.0, 0.0);
+        let point1 = Vector::from_components(0.05, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_some());
+        assert!(recog.double_tap.is_none());
+        recog.update(Contacts::default(), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point1)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_some());
+        let double_tap = recog.double_tap.unwrap();
+        assert_eq!(double_tap.sq_distance(point1), Scalar::from_val(0.0));
+        // A third tap starts over instead of producing another double tap.
+        recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_some());
+        assert!(recog.double_tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_double_tap_late()
+    {
+        let mut recog = Recognizer::new();
+        let point = Vector::from_components(0.0, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        recog.update(Contacts::default(), DOUBLE_TAP_DURATION);
+        recog.update(Contacts::from_slice(&[touch(0, point)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.tap.is_some());
+        assert!(recog.double_tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_double_tap_fast_samples()
+    {
+        let mut recog = Recognizer::new();
+        let point = Vector::from_components(0.0, 0.0, 0.0);
+        let sample = Duration::from_millis(1);
+        recog.update(Contacts::from_slice(&[touch(0, point)]), sample);
+        recog.update(Contacts::default(), sample);
+        for _ in 0 .. 100 {
+            recog.update(Contacts::default(), sample);
+        }
+        recog.update(Contacts::from_slice(&[touch(0, point)]), sample);
+        recog.update(Contacts::default(), sample);
+        assert!(recog.double_tap.is_some());
+    }
+
+    #[test]
+    fn recognizer_velocity()
+    {
+        let mut recog = Recognizer::new();
+        let elapsed = Duration::from_millis(500);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.0, 0.0, 0.0))]),
+                     elapsed);
+        assert_eq!(recog.velocity.length(), Scalar::from_val(0.0));
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.5, 0.0, 0.0))]),
+                     elapsed);
+        let speed = recog.velocity.length();
+        assert!(speed > Scalar::from_val(0.999) && speed < Scalar::from_val(1.001));
+    }
+
+    #[test]
+    fn recognizer_velocity_pinch()
+    {
+        let mut recog = Recognizer::new();
+        let elapsed = Duration::from_millis(250);
+        let point0 = Vector::from_components(-0.1, 0.0, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), elapsed);
+        let point0 = Vector::from_components(-0.1, 0.5, 0.0);
+        let point1 = Vector::from_components(0.1, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), elapsed);
+        let speed = recog.velocity.length();
+        assert!(speed > Scalar::from_val(1.999) && speed < Scalar::from_val(2.001));
+    }
+
+    #[test]
+    fn recognizer_fling()
+    {
+        let mut recog = Recognizer::new();
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.0, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.1, 0.0, 0.0))]),
+                     FRAME);
+        assert!(recog.fling.is_none());
+        recog.update(Contacts::default(), FRAME);
+        let fling = recog.fling.unwrap();
+        assert!(fling.length() > Scalar::from_val(FLING_SPEED));
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.fling.is_none());
+    }
+
+    #[test]
+    fn recognizer_fling_slow()
+    {
+        let mut recog = Recognizer::new();
+        let elapsed = Duration::from_secs(1);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.0, 0.0, 0.0))]),
+                     elapsed);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.1, 0.0, 0.0))]),
+                     elapsed);
+        recog.update(Contacts::default(), elapsed);
+        assert!(recog.fling.is_none());
+    }
+
+    #[test]
+    fn recognizer_three_finger_swipe()
+    {
+        let mut recog = Recognizer::new();
+        let points = [Vector::from_components(0.1, 0.5, 0.0),
+                      Vector::from_components(0.2, 0.4, 0.0),
+                      Vector::from_components(0.3, 0.5, 0.0)];
+        let step = Vector::from_components(0.0, -0.05, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, points[0]), touch(1, points[1]), touch(2, points[2])]),
+                     FRAME);
+        assert!(recog.swipe.is_none());
+        recog.update(Contacts::from_slice(&[touch(2, points[2] + step),
+                                            touch(0, points[0] + step),
+                                            touch(1, points[1] + step)]),
+                     FRAME);
+        let swipe = recog.swipe.unwrap();
+        assert!(swipe.approx_eq(step, Scalar::from_val(1.0 / 4096.0)));
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.swipe.is_none());
+    }
+
+    #[test]
+    fn recognizer_three_finger_spread()
+    {
+        let mut recog = Recognizer::new();
+        let points = [Vector::from_components(0.4, 0.5, 0.0),
+                      Vector::from_components(0.5, 0.5, 0.0),
+                      Vector::from_components(0.6, 0.5, 0.0)];
+        recog.update(Contacts::from_slice(&[touch(0, points[0]), touch(1, points[1]), touch(2, points[2])]),
+                     FRAME);
+        let left = Vector::from_components(-0.05, 0.0, 0.0);
+        let right = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, points[0] + left),
+                                            touch(1, points[1] + right),
+                                            touch(2, points[2] + right)]),
+                     FRAME);
+        assert!(recog.swipe.is_none());
+    }
+
+    #[test]
+    fn recognizer_long_press()
+    {
+        let mut recog = Recognizer::new();
+        let point = Vector::from_components(0.5, 0.5, 0.0);
+        let mut fired = 0;
+        for _ in 0 .. 60 {
+            recog.update(Contacts::from_slice(&[touch(0, point)]), FRAME);
+            fired += recog.long_press.is_some() as u32;
+        }
+        assert_eq!(fired, 1);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.long_press.is_none());
+        assert!(recog.tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_long_press_moved()
+    {
+        let mut recog = Recognizer::new();
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.5, 0.5, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.7, 0.5, 0.0))]),
+                     FRAME);
+        for _ in 0 .. 60 {
+            recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.5, 0.5, 0.0))]),
+                         FRAME);
+            assert!(recog.long_press.is_none());
+        }
+    }
+
+    #[test]
+    fn recognizer_transform_pivot()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(0.4, 0.5, 0.0);
+        let point1 = Vector::from_components(0.6, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        let point0 = Vector::from_components(0.5, 0.4, 0.0);
+        let point1 = Vector::from_components(0.5, 0.6, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        let point = recog.transform() * Vector::from_components(0.7, 0.5, 0.0);
+        let expected = Vector::from_components(0.5, 0.7, 0.0);
+        assert!(point.approx_eq(expected, Scalar::from_val(1.0 / 4096.0)));
+        let pivot = recog.transform() * Vector::from_components(0.5, 0.5, 0.0);
+        assert!(pivot.approx_eq(Vector::from_components(0.5, 0.5, 0.0), Scalar::from_val(1.0 / 4096.0)));
+    }
+
+    #[test]
+    fn recognizer_phase()
+    {
+        let mut recog = Recognizer::new();
+        assert_eq!(recog.phase, GesturePhase::Idle);
+        let point0 = Vector::from_components(-0.1, 0.0, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        assert_eq!(recog.phase, GesturePhase::Idle);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert_eq!(recog.phase, GesturePhase::Began);
+        let point1 = Vector::from_components(0.2, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert_eq!(recog.phase, GesturePhase::Changed);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert_eq!(recog.phase, GesturePhase::Changed);
+        recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        assert_eq!(recog.phase, GesturePhase::Ended);
+        recog.update(Contacts::default(), FRAME);
+        assert_eq!(recog.phase, GesturePhase::Idle);
+    }
+
+    #[test]
+    fn recognizer_deadzones()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(-0.1, 0.0, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        // Jiggle both fingers by less than every threshold.
+        let point0 = Vector::from_components(-0.1002, 0.0002, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0001, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert_eq!(recog.trans.sq_distance(Vector::default()), Scalar::from_val(0.0));
+        assert!(recog.rot.approx_eq(Quaternion::default(), Scalar::from_val(0.0)));
+        assert_eq!(recog.scale, Scalar::default());
+        // Motion past the thresholds is still reported.
+        recog.rot_deadzone = 0.0;
+        let point1 = Vector::from_components(0.1, 0.01, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert!(recog.trans.length() > Scalar::from_val(0.0));
+        assert!(!recog.rot
+                      .approx_eq(Quaternion::default(), Scalar::from_val(1.0 / 4096.0)));
+    }
+
+    #[test]
+    fn recognizer_deadzones_accumulated()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(-0.1, 0.0, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        // Pan slowly enough for every step to stay within the deadzone.
+        let step = Vector::from_components(0.0005, 0.0, 0.0);
+        let mut total = Vector::default();
+        for idx in 1 ..= 8 {
+            let offset = step * Scalar::from_val(idx as f32);
+            recog.update(Contacts::from_slice(&[touch(0, point0 + offset), touch(1, point1 + offset)]),
+                         FRAME);
+            total += recog.trans;
+        }
+        // Nothing is lost once the accumulated pan leaves the deadzone.
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        assert!(total.approx_eq(step * Scalar::from_val(8.0), epsilon));
+        // Later steps are reported as they are.
+        let offset = step * Scalar::from_val(9.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0 + offset), touch(1, point1 + offset)]),
+                     FRAME);
+        assert!(recog.trans.approx_eq(step, epsilon));
+        // The same goes for slow rotations.
+        let mut recog = Recognizer::new();
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        let mut rotated = false;
+        for idx in 1 ..= 10 {
+            let angle = 0.001 * idx as f32;
+            let offset = Vector::from_components(0.1 * angle.cos(), 0.1 * angle.sin(), 0.0);
+            recog.update(Contacts::from_slice(&[touch(0, Vector::default() - offset), touch(1, offset)]),
+                         FRAME);
+            rotated |= !recog.rot.approx_eq(Quaternion::default(), Scalar::from_val(0.0));
+        }
+        assert!(rotated);
+    }
+
+    #[test]
+    fn recognizer_inertia()
+    {
+        let mut recog = Recognizer::new();
+        recog.inertia = Scalar::from_val(0.5);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.0, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.016, 0.0, 0.0))]),
+                     FRAME);
+        // The first sample after release carries half of the release velocity.
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let mut expected = Vector::from_components(0.008, 0.0, 0.0);
+        for _ in 0 .. 5 {
+            recog.update(Contacts::default(), FRAME);
+            assert!(recog.trans.approx_eq(expected, epsilon));
+            expected *= Scalar::from_val(0.5);
+        }
+        while recog.trans.length() > Scalar::from_val(0.0) {
+            recog.update(Contacts::default(), FRAME);
+        }
+        recog.update(Contacts::default(), FRAME);
+        assert_eq!(recog.trans.length(), Scalar::from_val(0.0));
+        // Touching again stops the momentum.
+        recog.inertia = Scalar::from_val(0.9);
+        recog.update(Contacts::from_slice(&[touch(1, Vector::from_components(0.0, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::from_slice(&[touch(1, Vector::from_components(0.016, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.trans.length() > Scalar::from_val(0.0));
+        recog.update(Contacts::from_slice(&[touch(2, Vector::from_components(0.5, 0.0, 0.0))]),
+                     FRAME);
+        assert_eq!(recog.trans.length(), Scalar::from_val(0.0));
+    }
+
+    #[test]
+    fn recognizer_inertia_disabled()
+    {
+        let mut recog = Recognizer::new();
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.0, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.016, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert_eq!(recog.trans.length(), Scalar::from_val(0.0));
+    }
+
+    #[test]
+    fn recognizer_reset()
+    {
+        let mut recog = Recognizer::new();
+        recog.long_press_duration = Duration::from_secs(1);
+        let point0 = Vector::from_components(-0.1, 0.0, 0.0);
+        let point1 = Vector::from_components(0.1, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        let point0 = Vector::from_components(-0.4, 0.3, 0.0);
+        let point1 = Vector::from_components(0.4, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert!(recog.trans.length() > Scalar::from_val(0.0));
+        recog.reset();
+        assert_eq!(recog.phase, GesturePhase::Idle);
+        assert_eq!(recog.long_press_duration, Duration::from_secs(1));
+        let point0 = Vector::from_components(-0.6, 0.1, 0.0);
+        let point1 = Vector::from_components(0.6, 0.7, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        assert_eq!(recog.phase, GesturePhase::Began);
+        assert_eq!(recog.trans.sq_distance(Vector::default()), Scalar::from_val(0.0));
+        assert!(recog.rot.approx_eq(Quaternion::default(), Scalar::from_val(0.0)));
+        assert_eq!(recog.scale, Scalar::default());
+    }
+
+    #[test]
+    fn recognizer_predict()
+    {
+        let mut recog = Recognizer::new();
+        let epsilon = Scalar::from_val(1.0 / 4096.0);
+        let ahead = Duration::from_millis(8);
+        let point = Vector::from_components(0.5, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point)]), FRAME);
+        assert!(recog.predict(ahead).approx_eq(point, epsilon));
+        let step = Vector::from_components(0.016, 0.0, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point + step)]), FRAME);
+        let expected = point + step + Vector::from_components(0.008, 0.0, 0.0);
+        assert!(recog.predict(ahead).approx_eq(expected, epsilon));
+        let expected = point + step + Vector::from_components(0.016, 0.0, 0.0);
+        assert!(recog.predict(ahead * 2).approx_eq(expected, epsilon));
+        // A spike is limited to the maximum prediction distance.
+        let expected = point + step + Vector::from_components(MAX_PREDICTION, 0.0, 0.0);
+        assert!(recog.predict(Duration::from_secs(1)).approx_eq(expected, epsilon));
+    }
+
+    #[test]
+    fn recognizer_two_finger_tap()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(0.2, 0.5, 0.0);
+        let point1 = Vector::from_components(0.4, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(1, point1)]), FRAME);
+        assert!(recog.two_finger_tap.is_none());
+        recog.update(Contacts::default(), FRAME);
+        let tap = recog.two_finger_tap.unwrap();
+        assert!(tap.approx_eq(Vector::from_components(0.3, 0.5, 0.0), Scalar::from_val(1.0 / 4096.0)));
+        // Neither finger counts as a single finger tap.
+        assert!(recog.tap.is_none());
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.two_finger_tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_two_finger_tap_pinch()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(0.48, 0.5, 0.0);
+        let point1 = Vector::from_components(0.52, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        // Both fingers stay within the tap distance, but their distance doubles.
+        let point0 = Vector::from_components(0.46, 0.5, 0.0);
+        let point1 = Vector::from_components(0.54, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.two_finger_tap.is_none());
+        assert!(recog.tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_two_finger_tap_after_lift()
+    {
+        let mut recog = Recognizer::new();
+        let points = [Vector::from_components(0.2, 0.5, 0.0),
+                      Vector::from_components(0.4, 0.5, 0.0),
+                      Vector::from_components(0.6, 0.5, 0.0)];
+        recog.update(Contacts::from_slice(&[touch(0, points[0]), touch(1, points[1]), touch(2, points[2])]),
+                     FRAME);
+        // Two fingers left over from a three finger touch don't make a tap.
+        recog.update(Contacts::from_slice(&[touch(0, points[0]), touch(1, points[1])]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, points[0]), touch(1, points[1])]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.two_finger_tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_two_finger_tap_after_drag()
+    {
+        let mut recog = Recognizer::new();
+        let point0 = Vector::from_components(0.2, 0.5, 0.0);
+        let point1 = Vector::from_components(0.4, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, Vector::from_components(0.0, 0.0, 0.0))]),
+                     FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point0)]), FRAME);
+        // A second finger joining a drag doesn't make a tap.
+        recog.update(Contacts::from_slice(&[touch(0, point0), touch(1, point1)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.two_finger_tap.is_none());
+        // Neither does one joining a finger held for longer than a tap.
+        recog.update(Contacts::from_slice(&[touch(2, point0)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(2, point0)]), TAP_DURATION);
+        recog.update(Contacts::from_slice(&[touch(2, point0), touch(3, point1)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        assert!(recog.two_finger_tap.is_none());
+    }
+
+    #[test]
+    fn recognizer_two_finger_tap_coincident()
+    {
+        let mut recog = Recognizer::new();
+        let point = Vector::from_components(0.5, 0.5, 0.0);
+        recog.update(Contacts::from_slice(&[touch(0, point), touch(1, point)]), FRAME);
+        recog.update(Contacts::from_slice(&[touch(0, point), touch(1, point)]), FRAME);
+        recog.update(Contacts::default(), FRAME);
+        let tap = recog.two_finger_tap.unwrap();
+        assert!(tap.approx_eq(point, Scalar::from_val(1.0 / 4096.0)));
+    }
+}
@@ -0,0 +1,841 @@
+//! Driver for the official touchscreen.
+//!
+//! There is no official documentation for this driver, so its implementation is my interpretation of the implementation in the [Linux kernel source](https://github.com/raspberrypi/linux/blob/rpi-5.15.y/drivers/input/touchscreen/raspberrypi-ts.c).
+
+extern crate alloc;
+
+mod gesture;
+
+#[cfg(not(test))]
+use alloc::boxed::Box;
+#[cfg(not(test))]
+use alloc::vec::Vec;
+#[cfg(not(test))]
+use core::cmp::min;
+#[cfg(not(test))]
+use core::mem::{size_of, MaybeUninit};
+#[cfg(not(test))]
+use core::ptr::null_mut;
+#[cfg(not(test))]
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
+#[cfg(not(test))]
+pub use self::gesture::*;
+#[cfg(not(test))]
+use crate::alloc::{dma_clean, dma_clean_invalidate, Shell as Allocator, DMA};
+#[cfg(not(test))]
+use crate::debug;
+#[cfg(not(test))]
+use crate::irq::IRQ;
+use crate::math::{Scalar, Vector};
+#[cfg(not(test))]
+use crate::mbox::{Request, RequestProperty, MBOX};
+#[cfg(not(test))]
+use crate::sync::{AtomicScalar, DoubleBuffer, Lazy, Lock, RwLock};
+
+/// Video IRQ which we piggyback on since the touchscreen has no IRQ of its own.
+#[cfg(not(test))]
+const TOUCH_IRQ: u32 = 142;
+/// Maximum number of touch points tracked by the video core.
+pub const MAX_POINTS: usize = 10;
+/// Invalid points length used by the VC as a poor man's lock: the VC only
+/// writes a new frame once the buffer is marked with it, so it's also how polls
+/// tell the VC that the last frame was consumed. Readers never see it, as they
+/// only copy the saved touch points.
+#[cfg(not(test))]
+const INVALID_POINTS: u8 = 99;
+/// Default touch sensor's width, matching the official touchscreen.
+#[cfg(not(test))]
+const WIDTH: i16 = 800;
+/// Default touch sensor's height, matching the official touchscreen.
+#[cfg(not(test))]
+const HEIGHT: i16 = 480;
+/// Maximum distance that a touch point can travel between polls and still be
+/// considered the same contact.
+const TRACK_DISTANCE: f32 = 0.1;
+/// Calibration transform that leaves coordinates unchanged.
+#[cfg(not(test))]
+const IDENTITY_CALIBRATION: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Global touchscreen driver instance.
+#[cfg(not(test))]
+pub static TOUCH: Lazy<Touch> = Lazy::new(Touch::new);
+
+/// Touchscreen driver.
+#[cfg(not(test))]
+#[derive(Debug)]
+pub struct Touch
+{
+    /// Touchscreen buffer. The VC only takes a single buffer, which can't be
+    /// swapped per frame since setting it takes a blocking mailbox exchange
+    /// that the IRQ poll can't perform, so it's double buffered after being
+    /// decoded instead.
+    state: Lock<Box<State, Allocator<'static>>>,
+    /// Saved touch points for comparison, updated only by polls, which fill
+    /// the back buffer and flip it to the front so that readers always find
+    /// the last completed frame.
+    saved: DoubleBuffer<Contacts<TouchPoint>>,
+    /// Number of saved touch points, readable without taking any locks.
+    count: AtomicUsize,
+    /// Tracking identifier to assign to the next new contact.
+    next_id: Lock<u32>,
+    /// Registered event handlers.
+    subscribers: RwLock<Vec<fn(TouchEvent)>>,
+    /// Driver settings.
+    config: RwLock<Config>,
+    /// Weight of the previous position of each contact in the low-pass filter,
+    /// kept out of the settings so that it can be tuned without a lock.
+    smoothing: AtomicScalar,
+}
+
+/// Touch points captured by a poll.
+#[derive(Clone, Copy, Debug)]
+pub struct Contacts<T = Vector>
+{
+    /// Touch points.
+    points: [T; MAX_POINTS],
+    /// Number of touch points.
+    len: usize,
+}
+
+/// Detailed information about a touch point.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TouchPoint
+{
+    /// Tracking identifier, which remains the same for as long as the finger
+    /// stays on the screen.
+    pub id: u32,
+    /// Normalized position.
+    pub pos: Vector,
+    /// Raw touch force from 0 to 255, as the video core doesn't scale it.
+    pub force: u8,
+    /// Raw touch area from 0 to 255, as the video core doesn't scale it.
+    pub area: u8,
+}
+
+/// Change to an individual contact between two polls.
+#[derive(Clone, Copy, Debug)]
+pub enum TouchEvent
+{
+    /// A finger touched the screen.
+    Down
+    {
+        /// Tracking identifier of the contact.
+        id: u32,
+        /// Normalized position of the contact.
+        pos: Vector,
+    },
+    /// A finger moved across the screen.
+    Move
+    {
+        /// Tracking identifier of the contact.
+        id: u32,
+        /// Normalized position of the contact.
+        pos: Vector,
+    },
+    /// A finger was lifted from the screen.
+    Up
+    {
+        /// Tracking identifier of the contact.
+        id: u32,
+        /// Last normalized position of the contact.
+        pos: Vector,
+    },
+}
+
+/// Orientation of the display relative to the touch sensor.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Orientation
+{
+    /// Native orientation, leaving coordinates unchanged.
+    #[default]
+    Landscape,
+    /// Rotated by 90 degrees clockwise, mapping `(x, y)` to `(y, -x)`.
+    Portrait,
+    /// Rotated by 180 degrees, mapping `(x, y)` to `(-x, -y)`.
+    LandscapeFlipped,
+    /// Rotated by 90 degrees anticlockwise, mapping `(x, y)` to `(-y, x)`.
+    PortraitFlipped,
+}
+
+/// Touchscreen driver settings.
+#[cfg(not(test))]
+#[derive(Clone, Copy, Debug)]
+struct Config
+{
+    /// Touch sensor's width.
+    width: i16,
+    /// Touch sensor's height.
+    height: i16,
+    /// Largest raw touch area that isn't rejected as a palm.
+    palm_area: u8,
+    /// Affine transform applied to normalized coordinates.
+    calibration: [[f32; 3]; 3],
+    /// Orientation of the display.
+    orientation: Orientation,
+    /// Whether to clamp coordinates reported outside of the sensor's area.
+    clamping: bool,
+    /// Maximum number of contacts to track.
+    max_points: usize,
+}
+
+/// Touchscreen state information from the video core.
+#[cfg(not(test))]
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct State
+{
+    /// Not sure about the purpose of this field.
+    _mode: u8,
+    /// Not sure about the purpose of this field.
+    _gesture: u8,
+    /// Number of touch points in the buffer.
+    points_len: u8,
+    /// Information about individual touch points.
+    points: [Point; MAX_POINTS],
+}
+
+/// Information about an individual point.
+#[cfg(not(test))]
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct Point
+{
+    /// Most significant byte of the horizontal coordinate.
+    x_msb: u8,
+    /// Least significant byte of the horizontal coordinate.
+    x_lsb: u8,
+    /// Most significant byte of the vertical coordinate.
+    y_msb: u8,
+    /// Least significant byte of the vertical coordinate.
+    y_lsb: u8,
+    /// Touch force.
+    force: u8,
+    /// Touch area.
+    area: u8,
+}
+
+#[cfg(not(test))]
+impl Touch
+{
+    /// Creates and initializes a new touchscreen driver.
+    ///
+    /// Returns the initialized touchscreen driver.
+    fn new() -> Self
+    {
+        #[allow(invalid_value)] // Filled by the hardware.
+        #[allow(clippy::uninit_assumed_init)] // Same as above.
+        let mut state = unsafe { MaybeUninit::<State>::uninit().assume_init() };
+        state.points_len = INVALID_POINTS;
+        let mut state = Box::new_in(state, DMA);
+        dma_clean(state.as_ref() as *const State as _, size_of::<State>());
+        let mut req = Request::new();
+        req.push(RequestProperty::SetTouchBuffer { buf: state.as_mut() as *mut State as _ });
+        // The driver keeps working without the buffer but never sees any touch points.
+        if let Err(err) = MBOX.exchange(req) {
+            debug!("Failed to set up the touchscreen buffer: {err:?}");
+        }
+        let saved = Contacts::default();
+        let config = Config { width: WIDTH,
+                              height: HEIGHT,
+                              palm_area: u8::MAX,
+                              calibration: IDENTITY_CALIBRATION,
+                              orientation: Orientation::Landscape,
+                              clamping: false,
+                              max_points: MAX_POINTS };
+        IRQ.register(TOUCH_IRQ, Self::poll);
+        Self { state: Lock::new(state),
+               saved: DoubleBuffer::new(saved),
+               count: AtomicUsize::new(0),
+               next_id: Lock::new(0),
+               subscribers: RwLock::new(Vec::new()),
+               config: RwLock::new(config),
+               smoothing: AtomicScalar::new(0.0) }
+    }
+
+    /// Stops the video core from writing touch points to the buffer and stops
+    /// polling it, which must be done before the buffer is freed.
+    ///
+    /// The saved touch points remain available, but are never updated again.
+    pub fn shutdown(&self)
+    {
+        // Hold the buffer so that no poll reads it in the meantime.
+        let mut state = self.state.lock();
+        let mut req = Request::new();
+        req.push(RequestProperty::SetTouchBuffer { buf: null_mut() });
+        if let Err(err) = MBOX.exchange(req) {
+            debug!("Failed to disable the touchscreen buffer: {err:?}");
+        }
+        state.points_len = INVALID_POINTS;
+        drop(state);
+        IRQ.unregister(TOUCH_IRQ, Self::poll);
+    }
+
+    /// Sets the resolution of the touch sensor, which is used to normalize the
+    /// coordinates of the touch points.
+    ///
+    /// * `width`: Touch sensor's width.
+    /// * `height`: Touch sensor's height.
+    ///
+    /// Panics if either dimension is not positive.
+    pub fn set_resolution(&self, width: i16, height: i16)
+    {
+        assert!(width > 0 && height > 0,
+                "Invalid touch sensor resolution: {width}x{height}");
+        let mut config = self.config.wlock();
+        config.width = width;
+        config.height = height;
+    }
+
+    /// Sets the largest raw touch area that the driver accepts, rejecting
+    /// larger contacts as palms resting on the screen.
+    ///
+    /// * `area`: Largest accepted touch area, with the default of 255 accepting
+    ///   every contact.
+    pub fn set_palm_area_threshold(&self, area: u8)
+    {
+        self.config.wlock().palm_area = area;
+    }
+
+    /// Sets the affine transform applied to the normalized coordinates of
+    /// every touch point to correct for panel rotation, flipping, and offset.
+    ///
+    /// * `matrix`: Row-major transform whose first two rows produce the
+    ///   calibrated horizontal and vertical coordinates from the raw ones and a
+    ///   constant one, with the third row ignored.
+    pub fn set_calibration(&self, matrix: [[f32; 3]; 3])
+    {
+        self.config.wlock().calibration = matrix;
+    }
+
+    /// Sets the orientation of the display, which is applied to the
+    /// coordinates of every touch point after calibration.
+    ///
+    /// * `orientation`: Orientation of the display.
+    pub fn set_orientation(&self, orientation: Orientation)
+    {
+        self.config.wlock().orientation = orientation;
+    }
+
+    /// Sets the strength of the low-pass filter applied to the position of
+    /// every contact to reduce jitter.
+    ///
+    /// * `alpha`: Weight of the previous position of a contact, with 0
+    ///   disabling the filter and values close to 1 smoothing heavily.
+    ///
+    /// Panics if `alpha` is not in the [0, 1) range.
+    pub fn set_smoothing(&self, alpha: f32)
+    {
+        assert!((0.0 .. 1.0).contains(&alpha), "Invalid smoothing factor: {alpha}");
+        self.smoothing.store(Scalar::from_val(alpha), Ordering::Relaxed);
+    }
+
+    /// Sets whether coordinates that noisy panels report outside of the
+    /// sensor's area are clamped to its edges.
+    ///
+    /// * `enabled`: Whether to clamp the coordinates, which is disabled by
+    ///   default.
+    pub fn set_clamping(&self, enabled: bool)
+    {
+        self.config.wlock().clamping = enabled;
+    }
+
+    /// Sets the maximum number of contacts to track, ignoring any contact
+    /// beyond the first ones reported by the video core.
+    ///
+    /// * `count`: Maximum number of contacts, clamped to [`MAX_POINTS`], which
+    ///   is the default.
+    pub fn set_max_active_points(&self, count: usize)
+    {
+        self.config.wlock().max_points = count.min(MAX_POINTS);
+    }
+
+    /// Registers a handler to be called for every change to the contacts.
+    ///
+    /// * `handler`: Handler function to register.
+    pub fn subscribe(&self, handler: fn(TouchEvent))
+    {
+        self.subscribers.wlock().push(handler);
+    }
+
+    /// Checks whether any fingers were touching the screen at the last poll.
+    ///
+    /// Returns whether the screen is being touched.
+    pub fn is_touched(&self) -> bool
+    {
+        self.contact_count() > 0
+    }
+
+    /// Returns the number of contacts captured by the last poll.
+    pub fn contact_count(&self) -> usize
+    {
+        self.count.load(Ordering::Acquire)
+    }
+
+    /// Returns the touch points captured by the last poll, in the same
+    /// normalized coordinates used by the gesture recognizer.
+    pub fn points(&self) -> Contacts
+    {
+        let saved = self.saved.read();
+        Contacts { points: saved.points.map(|point| point.pos),
+                   len: saved.len }
+    }
+
+    /// Returns the touch points captured by the last poll along with their
+    /// force and area.
+    pub fn detailed_points(&self) -> Contacts<TouchPoint>
+    {
+        self.saved.read()
+    }
+
+    /// Handler that polls the touchscreen buffer and updates the saved state
+    /// when new information is available.
+    fn poll()
+    {
+        fence(Ordering::Acquire);
+        // Skip this poll rather than risk a deadlock against the interrupted code.
+        let mut hw_state = if let Some(hw_state) = TOUCH.state.try_lock() {
+            hw_state
+        } else {
+            return;
+        };
+        let base = hw_state.as_ref() as *const State as *const u8;
+        // Nothing in the buffer is ever left modified in the cache, so this just
+        // discards the stale copies.
+        dma_clean_invalidate(base, size_of::<State>());
+        let state = **hw_state;
+        if state.points_len as usize > MAX_POINTS {
+            return;
+        }
+        hw_state.points_len = INVALID_POINTS;
+        fence(Ordering::Release);
+        dma_clean(base, size_of::<State>());
+        drop(hw_state);
+        // Copy the settings so that both dimensions come from the same update.
+        let config = *TOUCH.config.rlock();
+        let (width, height) = (config.width, config.height);
+        let mapper = |point: Point| {
+            let x = point.x_lsb as i16 | (point.x_msb as i16 & 0x3) << 8;
+            let y = point.y_lsb as i16 | (point.y_msb as i16 & 0x3) << 8;
+            let x = x * 2 - width;
+            let y = y * 2 - height;
+            let x = x as f32 / min(width, height) as f32;
+            let y = y as f32 / min(width, height) as f32;
+            let (x, y) = calibrate(x, y, &config.calibration);
+            let (x, y) = config.orientation.apply(x, y);
+            let mut pos = Vector::from_components(x, y, 0.0);
+            if config.clamping {
+                // The sensor's corner ends up in a different quadrant depending on the
+                // orientation, so the bounds are derived from both opposite corners.
+                let (x, y) = (width as f32 / min(width, height) as f32, height as f32 / min(width, height) as f32);
+                let (x, y) = config.orientation.apply(x, y);
+                let corner = Vector::from_components(x, y, 0.0);
+                let opposite = Vector::default() - corner;
+                pos = pos.clamp(corner.min(opposite), corner.max(opposite));
+            }
+            TouchPoint { id: 0,
+                         pos,
+                         force: point.force,
+                         area: point.area }
+        };
+        let new = state.points.map(mapper);
+        let mut new = Contacts::from_slice(&new[.. state.points_len as usize]);
+        new.reject_palms(config.palm_area);
+        new.truncate(config.max_points);
+        // The tracking identifier lock also serializes updates to the saved touch
+        // points, so the write below never waits, and readers copy the front buffer
+        // without ever blocking this handler or being blocked by it. The write
+        // releases the back buffer's contents before flipping it to the front, which
+        // readers acquire.
+        let mut next_id = TOUCH.next_id.lock();
+        let old = TOUCH.saved.read();
+        new.track(&old, &mut next_id);
+        new.smooth(&old, TOUCH.smoothing.load(Ordering::Relaxed).to_val());
+        TOUCH.saved.write(new);
+        TOUCH.count.store(new.len(), Ordering::Release);
+        drop(next_id);
+        // Dispatch the events without holding any locks so that the handlers are
+        // free to query the driver.
+        let subscribers = TOUCH.subscribers.rlock().clone();
+        new.diff(&old, |event| subscribers.iter().for_each(|handler| handler(event)));
+    }
+}
+
+#[cfg(not(test))]
+impl Drop for Touch
+{
+    fn drop(&mut self)
+    {
+        // The video core would otherwise keep writing to the freed buffer.
+        self.shutdown();
+    }
+}
+
+/// Applies an affine calibration transform to normalized coordinates.
+///
+/// * `x`: Horizontal coordinate.
+/// * `y`: Vertical coordinate.
+/// * `matrix`: Row-major affine transform.
+///
+/// Returns the transformed coordinates.
+fn calibrate(x: f32, y: f32, matrix: &[[f32; 3]; 3]) -> (f32, f32)
+{
+    let [row0, row1, _] = matrix;
+    (row0[0] * x + row0[1] * y + row0[2], row1[0] * x + row1[1] * y + row1[2])
+}
+
+impl Orientation
+{
+    /// Maps normalized sensor coordinates to display coordinates.
+    ///
+    /// * `x`: Horizontal coordinate.
+    /// * `y`: Vertical coordinate.
+    ///
+    /// Returns the mapped coordinates.
+    fn apply(self, x: f32, y: f32) -> (f32, f32)
+    {
+        match self {
+            Self::Landscape => (x, y),
+            Self::Portrait => (y, -x),
+            Self::LandscapeFlipped => (-x, -y),
+            Self::PortraitFlipped => (-y, x),
+        }
+    }
+}
+
+impl<T: Copy + Default> Contacts<T>
+{
+    /// Creates and initializes a new set of touch points.
+    ///
+    /// * `points`: Touch points.
+    ///
+    /// Returns the newly created set.
+    ///
+    /// Panics if there are more than [`MAX_POINTS`] points.
+    pub fn from_slice(points: &[T]) -> Self
+    {
+        assert!(points.len() <= MAX_POINTS, "Too many touch points: {}", points.len());
+        let mut this = Self::default();
+        this.points[.. points.len()].copy_from_slice(points);
+        this.len = points.len();
+        this
+    }
+
+    /// Returns the touch points.
+    pub fn as_slice(&self) -> &[T]
+    {
+        &self.points[.. self.len]
+    }
+
+    /// Returns the number of touch points.
+    pub fn len(&self) -> usize
+    {
+        self.len
+    }
+
+    /// Checks whether there are no touch points.
+    ///
+    /// Returns whether the set is empty.
+    pub fn is_empty(&self) -> bool
+    {
+        self.len == 0
+    }
+
+    /// Drops every touch point beyond the specified count.
+    ///
+    /// * `len`: Maximum number of touch points to keep.
+    fn truncate(&mut self, len: usize)
+    {
+        self.len = self.len.min(len);
+    }
+}
+
+impl Contacts<TouchPoint>
+{
+    /// Removes touch points whose area is too large to be a finger.
+    ///
+    /// * `max_area`: Largest raw touch area to keep.
+    fn reject_palms(&mut self, max_area: u8)
+    {
+        let mut len = 0;
+        for idx in 0 .. self.len {
+            if self.points[idx].area <= max_area {
+                self.points[len] = self.points[idx];
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
+
+    /// Assigns tracking identifiers to these touch points by matching them
+    /// with the closest touch points from the previous poll, allocating new
+    /// identifiers in order for those that match none.
+    ///
+    /// * `old`: Touch points from the previous poll.
+    /// * `next_id`: Identifier to assign to the next new contact, which is
+    ///   updated as identifiers are allocated.
+    fn track(&mut self, old: &Self, next_id: &mut u32)
+    {
+        let max_sqdist = Scalar::from_val(TRACK_DISTANCE * TRACK_DISTANCE);
+        let mut old_matched = [false; MAX_POINTS];
+        let mut new_matched = [false; MAX_POINTS];
+        // Repeatedly match the closest pair of unmatched points so that the result
+        // doesn't depend on the order in which the video core reports them.
+        loop {
+            let mut best: Option<(usize, usize, Scalar)> = None;
+            for (old_idx, old_point) in old.as_slice().iter().enumerate() {
+                if old_matched[old_idx] {
+                    continue;
+                }
+                for (new_idx, new_point) in self.as_slice().iter().enumerate() {
+                    if new_matched[new_idx] {
+                        continue;
+                    }
+                    let sqdist = old_point.pos.sq_distance(new_point.pos);
+                    if sqdist > max_sqdist || matches!(best, Some((_, _, best)) if sqdist >= best) {
+                        continue;
+                    }
+                    best = Some((old_idx, new_idx, sqdist));
+                }
+            }
+            let (old_idx, new_idx) = match best {
+                Some((old_idx, new_idx, _)) => (old_idx, new_idx),
+                None => break,
+            };
+            old_matched[old_idx] = true;
+            new_matched[new_idx] = true;
+            self.points[new_idx].id = old.points[old_idx].id;
+        }
+        for (point, matched) in self.points[.. self.len].iter_mut().zip(new_matched) {
+            if !matched {
+                point.id = *next_id;
+                *next_id = next_id.wrapping_add(1);
+            }
+        }
+    }
+
+    /// Filters the positions of these touch points by blending them with the
+    /// positions of the same contacts from the previous poll.
+    ///
+    /// * `old`: Tracked and filtered touch points from the previous poll.
+    /// * `alpha`: Weight of the previous positions.
+    ///
+    /// Contacts that weren't present in the previous poll are left unfiltered,
+    /// which resets the filter whenever a finger touches the screen.
+    fn smooth(&mut self, old: &Self, alpha: f32)
+    {
+        if alpha == 0.0 {
+            return;
+        }
+        let bias = Scalar::from_val(1.0 - alpha);
+        for point in self.points[.. self.len].iter_mut() {
+            if let Some(old_point) = old.as_slice().iter().find(|old_point| old_point.id == point.id) {
+                point.pos = old_point.pos.lerp(point.pos, bias);
+            }
+        }
+    }
+
+    /// Compares these touch points with those from the previous poll.
+    ///
+    /// * `old`: Tracked touch points from the previous poll.
+    /// * `emit`: Function to call for every change, with lifted contacts
+    ///   reported first.
+    fn diff(&self, old: &Self, mut emit: impl FnMut(TouchEvent))
+    {
+        for old_point in old.as_slice() {
+            if !self.as_slice().iter().any(|point| point.id == old_point.id) {
+                emit(TouchEvent::Up { id: old_point.id,
+                                      pos: old_point.pos });
+            }
+        }
+        for point in self.as_slice() {
+            match old.as_slice().iter().find(|old_point| old_point.id == point.id) {
+                Some(old_point) if old_point.pos.sq_distance(point.pos) == Scalar::from_val(0.0) => (),
+                Some(_) => emit(TouchEvent::Move { id: point.id,
+                                                   pos: point.pos }),
+                None => emit(TouchEvent::Down { id: point.id,
+                                                pos: point.pos }),
+            }
+        }
+    }
+}
+
+impl<T: Copy + Default> Default for Contacts<T>
+{
+    fn default() -> Self
+    {
+        Self { points: [T::default(); MAX_POINTS],
+               len: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn touch(id: u32, x: f32, y: f32) -> TouchPoint
+    {
+        TouchPoint { id,
+                     pos: Vector::from_components(x, y, 0.0),
+                     force: 0x80,
+                     area: 0x40 }
+    }
+
+    #[test]
+    fn contacts_len()
+    {
+        let none = Contacts::<TouchPoint>::default();
+        assert_eq!(none.len(), 0);
+        assert!(none.is_empty());
+        let one = Contacts::from_slice(&[touch(0, 0.0, 0.0)]);
+        assert_eq!(one.len(), 1);
+        assert!(!one.is_empty());
+        let two = Contacts::from_slice(&[touch(0, 0.0, 0.0), touch(1, 0.5, 0.5)]);
+        assert_eq!(two.len(), 2);
+        assert!(!two.is_empty());
+    }
+
+    #[test]
+    fn contacts_truncate()
+    {
+        let mut contacts = Contacts::from_slice(&[touch(0, 0.0, 0.0), touch(1, 0.5, 0.5), touch(2, -0.5, 0.5)]);
+        contacts.truncate(MAX_POINTS);
+        assert_eq!(contacts.len(), 3);
+        contacts.truncate(2);
+        assert_eq!(contacts.len(), 2);
+        assert_eq!(contacts.as_slice()[1].id, 1);
+    }
+
+    #[test]
+    fn calibrate_identity()
+    {
+        let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        assert_eq!(calibrate(0.25, -0.75, &identity), (0.25, -0.75));
+    }
+
+    #[test]
+    fn calibrate_rotation()
+    {
+        // Rotates by 90 degrees counter-clockwise and shifts right.
+        let rotation = [[0.0, -1.0, 0.5], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+        assert_eq!(calibrate(0.25, -0.75, &rotation), (1.25, 0.25));
+        assert_eq!(calibrate(1.0, 0.0, &rotation), (0.5, 1.0));
+    }
+
+    #[test]
+    fn orientation_corner()
+    {
+        let (x, y) = (5.0 / 3.0, 1.0);
+        assert_eq!(Orientation::default().apply(x, y), (x, y));
+        assert_eq!(Orientation::Portrait.apply(x, y), (y, -x));
+        assert_eq!(Orientation::LandscapeFlipped.apply(x, y), (-x, -y));
+        assert_eq!(Orientation::PortraitFlipped.apply(x, y), (-y, x));
+    }
+
+    #[test]
+    fn contacts_smooth_step()
+    {
+        let mut old = Contacts::from_slice(&[touch(1, 0.0, 0.0)]);
+        for expected in [0.5, 0.75, 0.875, 0.9375] {
+            let mut new = Contacts::from_slice(&[touch(1, 1.0, 0.0)]);
+            new.smooth(&old, 0.5);
+            let target = Vector::from_components(expected, 0.0, 0.0);
+            assert_eq!(new.as_slice()[0].pos.sq_distance(target), Scalar::from_val(0.0));
+            old = new;
+        }
+    }
+
+    #[test]
+    fn contacts_smooth_reset()
+    {
+        let old = Contacts::from_slice(&[touch(1, 0.0, 0.0)]);
+        let mut new = Contacts::from_slice(&[touch(2, 1.0, 0.0)]);
+        new.smooth(&old, 0.5);
+        let target = Vector::from_components(1.0, 0.0, 0.0);
+        assert_eq!(new.as_slice()[0].pos.sq_distance(target), Scalar::from_val(0.0));
+    }
+
+    #[test]
+    fn contacts_reject_palms()
+    {
+        let palm = TouchPoint { area: 0xC0,
+                                ..touch(0, 0.5, 0.5) };
+        let mut contacts = Contacts::from_slice(&[touch(0, 0.0, 0.0), palm, touch(0, -0.5, 0.0)]);
+        contacts.reject_palms(u8::MAX);
+        assert_eq!(contacts.as_slice().len(), 3);
+        contacts.reject_palms(0x80);
+        assert_eq!(contacts.as_slice().len(), 2);
+        assert!(contacts.as_slice().iter().all(|point| point.area == 0x40));
+        assert_eq!(contacts.as_slice()[1].pos
+                                         .sq_distance(Vector::from_components(-0.5, 0.0, 0.0)),
+                   Scalar::from_val(0.0));
+    }
+
+    #[test]
+    fn contacts_track_new()
+    {
+        let old = Contacts::default();
+        let mut new = Contacts::from_slice(&[touch(0, 0.0, 0.0), touch(0, 0.5, 0.0)]);
+        let mut next_id = 7;
+        new.track(&old, &mut next_id);
+        assert_eq!(new.as_slice()[0].id, 7);
+        assert_eq!(new.as_slice()[1].id, 8);
+        assert_eq!(next_id, 9);
+    }
+
+    #[test]
+    fn contacts_track_moved()
+    {
+        let old = Contacts::from_slice(&[touch(3, 0.0, 0.0), touch(4, 0.5, 0.0)]);
+        let mut new = Contacts::from_slice(&[touch(0, 0.55, 0.0), touch(0, 0.05, 0.0)]);
+        let mut next_id = 5;
+        new.track(&old, &mut next_id);
+        assert_eq!(new.as_slice()[0].id, 4);
+        assert_eq!(new.as_slice()[1].id, 3);
+        assert_eq!(next_id, 5);
+        // Tracking leaves the remaining information untouched.
+        assert_eq!(new.as_slice()[0].force, 0x80);
+        assert_eq!(new.as_slice()[0].area, 0x40);
+    }
+
+    #[test]
+    fn contacts_track_lift_and_add()
+    {
+        let old = Contacts::from_slice(&[touch(3, 0.0, 0.0), touch(4, 0.5, 0.0)]);
+        let mut new = Contacts::from_slice(&[touch(0, -0.5, 0.0), touch(0, 0.5, 0.0), touch(0, 0.0, 0.5)]);
+        let mut next_id = 5;
+        new.track(&old, &mut next_id);
+        assert_eq!(new.as_slice()[0].id, 5);
+        assert_eq!(new.as_slice()[1].id, 4);
+        assert_eq!(new.as_slice()[2].id, 6);
+        assert_eq!(next_id, 7);
+    }
+
+    #[test]
+    fn contacts_track_closest()
+    {
+        // Both new points are within range of the old point, so only the closest
+        // one inherits its identifier.
+        let old = Contacts::from_slice(&[touch(3, 0.0, 0.0)]);
+        let mut new = Contacts::from_slice(&[touch(0, 0.08, 0.0), touch(0, 0.02, 0.0)]);
+        let mut next_id = 4;
+        new.track(&old, &mut next_id);
+        assert_eq!(new.as_slice()[0].id, 4);
+        assert_eq!(new.as_slice()[1].id, 3);
+    }
+
+    #[test]
+    fn contacts_diff()
+    {
+        let old = Contacts::from_slice(&[touch(3, 0.0, 0.0), touch(4, 0.5, 0.0), touch(5, 0.0, 0.5)]);
+        let new = Contacts::from_slice(&[touch(6, -0.5, 0.0), touch(4, 0.55, 0.0), touch(5, 0.0, 0.5)]);
+        let mut events = Vec::new();
+        new.diff(&old, |event| events.push(event));
+        let is_at = |pos: Vector, point: TouchPoint| pos.sq_distance(point.pos) == Scalar::from_val(0.0);
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], TouchEvent::Up { id: 3, pos } if is_at(pos, old.as_slice()[0])));
+        assert!(matches!(events[1], TouchEvent::Down { id: 6, pos } if is_at(pos, new.as_slice()[0])));
+        assert!(matches!(events[2], TouchEvent::Move { id: 4, pos } if is_at(pos, new.as_slice()[1])));
+    }
+}
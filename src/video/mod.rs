@@ -16,7 +16,6 @@ use alloc::vec::Vec;
 use core::future::Future;
 use core::mem::{align_of, size_of_val};
 use core::pin::Pin;
-use core::ptr::null_mut;
 use core::simd::u32x4;
 use core::sync::atomic::{fence, AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use core::task::{Context, Poll, Waker};
@@ -24,7 +23,7 @@ use core::task::{Context, Poll, Waker};
 pub use self::geom::*;
 use crate::irq::IRQ;
 use crate::math::{Color, Matrix, Projector, Triangulation, Vector};
-use crate::mbox::{Request, RequestProperty, ResponseProperty, MBOX};
+use crate::mbox::{Framebuffer, Request, RequestProperty, MBOX};
 use crate::sched::SCHED;
 use crate::sync::{Lazy, Lock, RwLock};
 use crate::{CPU_COUNT, PERRY_RANGE};
@@ -57,8 +56,6 @@ pub struct Video
 {
     /// Frame buffer base.
     base: *mut u32x4,
-    /// Frame buffer size in bytes.
-    size: usize,
     /// Display width.
     width: usize,
     /// Display height.
@@ -114,41 +111,16 @@ impl Video
     /// Returns the newly created instance.
     fn new() -> Self
     {
-        let mut req = Request::new();
-        req.push(RequestProperty::SetPhysicalSize { width: SCREEN_WIDTH,
-                                                    height: SCREEN_HEIGHT });
-        req.push(RequestProperty::SetVirtualSize { width: SCREEN_WIDTH,
-                                                   height: SCREEN_HEIGHT * 2 });
-        req.push(RequestProperty::SetDepth { bits: 32 });
-        req.push(RequestProperty::GetPitch);
-        req.push(RequestProperty::Allocate { align: align_of::<u32x4>() });
-        let resp = MBOX.exchange(req);
-        let mut this = Self { base: null_mut(),
-                              size: 0,
-                              width: 0,
-                              height: 0,
-                              pitch: 0,
-                              frame: AtomicU64::new(0),
-                              did_commit: AtomicBool::new(false),
-                              tile: AtomicUsize::new(0),
-                              waiters: Lock::new(Vec::new()),
-                              cmds: RwLock::new(Vec::new()) };
-        for prop in resp {
-            match prop {
-                ResponseProperty::Allocate { base, size } => {
-                    this.base = base.cast();
-                    this.size = size;
-                }
-                ResponseProperty::SetPhysicalSize { width, height } => {
-                    this.width = width;
-                    this.height = height;
-                }
-                ResponseProperty::GetPitch { pitch } => {
-                    this.pitch = pitch;
-                }
-                _ => continue,
-            }
-        }
+        let fb = Framebuffer::new(SCREEN_WIDTH, SCREEN_HEIGHT, SCREEN_HEIGHT * 2, 32, align_of::<u32x4>());
+        let this = Self { base: fb.base.cast(),
+                          width: fb.width,
+                          height: fb.height,
+                          pitch: fb.pitch,
+                          frame: AtomicU64::new(0),
+                          did_commit: AtomicBool::new(false),
+                          tile: AtomicUsize::new(0),
+                          waiters: Lock::new(Vec::new()),
+                          cmds: RwLock::new(Vec::new()) };
         IRQ.register(PV1_IRQ, Self::vsync);
         unsafe { PV1_INTEN.write_volatile(PV_VSYNC) };
         this
@@ -275,7 +247,7 @@ impl Video
             let mut req = Request::new();
             req.push(RequestProperty::SetPosition { x: 0,
                                                     y: VIDEO.height * (frame & 1) as usize });
-            MBOX.exchange(req);
+            MBOX.exchange(req).expect("Failed to flip the frame buffer");
         }
         VIDEO.did_commit.store(false, Ordering::SeqCst);
         let mut waiters = VIDEO.waiters.lock();